@@ -0,0 +1,267 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `crypto`
+//!
+//! Signing and verification of messages with node keys.
+//! Enabled with the `crypto` feature.
+//!
+//! Nodes hold Ed25519 keys. The public key block a node
+//! sends in its registration, and which is held as
+//! `Node::key()`, is the line `PUBLIC KEY` followed by the
+//! base64 encoding of the 32 byte public key.
+//!
+//! A signature of an HTTP request or response covers its
+//! request line (`POST /spring`) or status code (`200`), the
+//! time it was signed and the exact bytes of its body. It is
+//! carried, base64 encoded, in the `X-Spring-Signature` HTTP
+//! header, with the time in seconds since the epoch in the
+//! `X-Spring-Timestamp` header. Signatures older than
+//! `MAX_SIGNATURE_AGE`, or as far in the future, are
+//! rejected, so a captured request can only be replayed for
+//! that long and only to the same target.
+//!
+//! # Example
+//!
+//! ```
+//! use spring_dvs::crypto::{NodeKey, verify};
+//!
+//! let key = NodeKey::generate().unwrap();
+//! let signature = key.sign(b"info network");
+//!
+//! assert!(verify(&key.public_key_block(), b"info network", &signature).is_ok());
+//! assert!(verify(&key.public_key_block(), b"info node foo", &signature).is_err());
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+use crate::node::Node;
+use crate::protocol::{Message, ProtocolObject};
+
+/// The HTTP header carrying the signature of a message
+pub const SIGNATURE_HEADER: &str = "X-Spring-Signature";
+
+/// The HTTP header carrying the time a message was signed
+pub const TIMESTAMP_HEADER: &str = "X-Spring-Timestamp";
+
+/// How far the time a message was signed may be from the
+/// time it is verified
+pub const MAX_SIGNATURE_AGE: Duration = Duration::from_secs(5 * 60);
+
+const PUBLIC_KEY: &str = "PUBLIC KEY";
+const PRIVATE_KEY: &str = "PRIVATE KEY";
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CryptoFailure {
+	/// The key block is malformed
+	InvalidKey,
+
+	/// The signature is malformed
+	InvalidSignature,
+
+	/// The message carries no signature
+	MissingSignature,
+
+	/// The signature does not match the message and key
+	VerificationFailed,
+
+	/// The signature was made too long ago, or its time is
+	/// malformed
+	StaleSignature,
+
+	/// No randomness was available to generate a key
+	RandomFailure,
+}
+
+impl fmt::Display for CryptoFailure {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CryptoFailure::InvalidKey => write!(f, "invalid key block"),
+			CryptoFailure::InvalidSignature => write!(f, "invalid signature"),
+			CryptoFailure::MissingSignature => write!(f, "missing signature"),
+			CryptoFailure::VerificationFailed => write!(f, "signature verification failed"),
+			CryptoFailure::StaleSignature => write!(f, "stale signature"),
+			CryptoFailure::RandomFailure => write!(f, "no randomness available"),
+		}
+	}
+}
+
+impl std::error::Error for CryptoFailure {}
+
+/// The private key of this node
+pub struct NodeKey {
+	signing: SigningKey,
+}
+
+impl fmt::Debug for NodeKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "NodeKey({})", self.public_key_block().replace('\n', " "))
+	}
+}
+
+impl NodeKey {
+	/// Generate a new random key
+	pub fn generate() -> Result<NodeKey, CryptoFailure> {
+		let mut secret = [0u8; 32];
+		if getrandom::getrandom(&mut secret).is_err() {
+			return Err(CryptoFailure::RandomFailure)
+		}
+
+		Ok(NodeKey::from_secret(&secret))
+	}
+
+	pub fn from_secret(secret: &[u8; 32]) -> NodeKey {
+		NodeKey {
+			signing: SigningKey::from_bytes(secret),
+		}
+	}
+
+	/// Read a key from a block written by `to_secret_block`
+	pub fn from_secret_block(block: &str) -> Result<NodeKey, CryptoFailure> {
+		let secret = decode_block(PRIVATE_KEY, block)?;
+		Ok(NodeKey::from_secret(&secret))
+	}
+
+	/// The private key as a block for storing; keep it secret
+	pub fn to_secret_block(&self) -> String {
+		encode_block(PRIVATE_KEY, self.signing.as_bytes())
+	}
+
+	/// The public key block to send when registering
+	pub fn public_key_block(&self) -> String {
+		encode_block(PUBLIC_KEY, self.signing.verifying_key().as_bytes())
+	}
+
+	/// Sign bytes, returning the base64 signature
+	pub fn sign(&self, bytes: &[u8]) -> String {
+		STANDARD.encode(self.signing.sign(bytes).to_bytes())
+	}
+
+	/// Sign the serialised bytes of a message
+	pub fn sign_message(&self, msg: &Message) -> String {
+		self.sign(&msg.to_bytes())
+	}
+
+	/// Sign a body with its request line or status code and
+	/// the time `timestamp`, in seconds since the epoch
+	pub fn sign_line(&self, line: &str, timestamp: u64, body: &[u8]) -> String {
+		self.sign(&signed_bytes(line, timestamp, body))
+	}
+}
+
+/// The bytes covered by the signature of an HTTP request or
+/// response
+///
+/// These are the request line or status code, the time and
+/// the body, each of the first two ended by a newline
+pub fn signed_bytes(line: &str, timestamp: u64, body: &[u8]) -> Vec<u8> {
+	let mut v = format!("{}\n{}\n", line, timestamp).into_bytes();
+	v.extend_from_slice(body);
+	v
+}
+
+/// The current time in seconds since the epoch, for signing
+pub fn timestamp() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn encode_block(label: &str, key: &[u8; 32]) -> String {
+	format!("{}\n{}", label, STANDARD.encode(key))
+}
+
+fn decode_block(label: &str, block: &str) -> Result<[u8; 32], CryptoFailure> {
+	let mut lines = block.trim().lines();
+
+	if lines.next().map(|l| l.trim()) != Some(label) {
+		return Err(CryptoFailure::InvalidKey)
+	}
+
+	// The encoded key may be wrapped over several lines
+	let encoded : String = lines.map(|l| l.trim()).collect();
+
+	match STANDARD.decode(encoded) {
+		Ok(k) => <[u8; 32]>::try_from(k.as_slice()).map_err(|_| CryptoFailure::InvalidKey),
+		Err(_) => Err(CryptoFailure::InvalidKey),
+	}
+}
+
+/// Verify the signature of bytes against a public key block
+///
+/// # Arguments
+///
+/// * `key` - The public key block of the signer
+/// * `bytes` - The bytes that were signed
+/// * `signature` - The base64 signature
+pub fn verify(key: &str, bytes: &[u8], signature: &str) -> Result<(), CryptoFailure> {
+	let key = decode_block(PUBLIC_KEY, key)?;
+	let key = match VerifyingKey::from_bytes(&key) {
+		Ok(k) => k,
+		Err(_) => return Err(CryptoFailure::InvalidKey),
+	};
+
+	let signature = match STANDARD.decode(signature.trim()) {
+		Ok(s) => s,
+		Err(_) => return Err(CryptoFailure::InvalidSignature),
+	};
+
+	let signature = match Signature::from_slice(&signature) {
+		Ok(s) => s,
+		Err(_) => return Err(CryptoFailure::InvalidSignature),
+	};
+
+	key.verify(bytes, &signature).map_err(|_| CryptoFailure::VerificationFailed)
+}
+
+/// Verify the signature of a body with its request line or
+/// status code and the time it was signed
+///
+/// Fails with `StaleSignature` if `timestamp` is further than
+/// `MAX_SIGNATURE_AGE` from now
+pub fn verify_line(key: &str, line: &str, timestamp: &str, body: &[u8], signature: &str) -> Result<(), CryptoFailure> {
+	verify_line_at(key, line, timestamp, body, signature, SystemTime::now())
+}
+
+/// Verify a signature as if the time were `now`
+pub fn verify_line_at(key: &str, line: &str, timestamp: &str, body: &[u8], signature: &str, now: SystemTime) -> Result<(), CryptoFailure> {
+	let secs : u64 = match timestamp.trim().parse() {
+		Ok(s) => s,
+		Err(_) => return Err(CryptoFailure::StaleSignature),
+	};
+
+	let signed = match UNIX_EPOCH.checked_add(Duration::from_secs(secs)) {
+		Some(t) => t,
+		None => return Err(CryptoFailure::StaleSignature),
+	};
+
+	let skew = match now.duration_since(signed) {
+		Ok(d) => d,
+		Err(e) => e.duration(),
+	};
+
+	if skew > MAX_SIGNATURE_AGE {
+		return Err(CryptoFailure::StaleSignature)
+	}
+
+	verify(key, &signed_bytes(line, secs, body), signature)
+}
+
+/// Verify the signature of a request against the key a node
+/// registered with, rejecting stale signatures
+///
+/// # Arguments
+///
+/// * `node` - The node that sent the request
+/// * `line` - The request line, as `POST /spring`
+/// * `timestamp` - The value of the `X-Spring-Timestamp` header
+/// * `body` - The body that was signed
+/// * `signature` - The value of the `X-Spring-Signature` header
+pub fn verify_node(node: &Node, line: &str, timestamp: &str, body: &[u8], signature: &str) -> Result<(), CryptoFailure> {
+	verify_line(node.key(), line, timestamp, body, signature)
+}
@@ -0,0 +1,186 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `dispatch`
+//!
+//! Processes a parsed request `Message` against a `Netspace`
+//! and generates the response `Message` that should be sent
+//! back to the requesting node.
+//!
+//! The response codes follow the protocol:
+//!
+//! * `Ok` -- the request was carried out
+//! * `NetspaceError` -- the node is unknown, the token is
+//!   invalid or the netspace failed
+//! * `NetspaceDuplication` -- the node is already registered
+//! * `UnsupportedAction` -- the request is not something this
+//!   node handles, or the requester may not perform it
+//!
+//! Requests that change an existing node (`unregister` and
+//! `update`) are only accepted from the address the node is
+//! registered with.
+//!
+//! `resolve` requests are handled by a `Resolver` for the
+//! geosub the dispatcher serves.
+//!
+//! A registration needs a stored token given for its
+//! springname, which is consumed so it cannot be used again.
+//! A dispatcher given a `TokenIssuer` also requires the token
+//! to have been issued by it and not to have expired.
+
+use crate::enums::Success;
+use crate::formats::canonical_ip;
+use crate::node::{Node, nodevec_quadvec};
+use crate::protocol::*;
+use crate::resolve::Resolver;
+use crate::spaces::{Netspace, NetspaceFailure};
+use crate::token::TokenIssuer;
+
+/// Dispatches request messages against a netspace serving
+/// the geosub `geosub`
+pub struct Dispatcher<'a, N: Netspace + 'a> {
+	nio: &'a N,
+	geosub: String,
+	tokens: Option<TokenIssuer<'a, N>>,
+}
+
+fn response_code(result: Result<Success, NetspaceFailure>) -> Message {
+	match result {
+		Ok(_) => generate_response_empty_code(Response::Ok),
+		Err(NetspaceFailure::DuplicateNode) => generate_response_empty_code(Response::NetspaceDuplication),
+		Err(_) => generate_response_empty_code(Response::NetspaceError),
+	}
+}
+
+impl<'a, N: Netspace + 'a> Dispatcher<'a, N> {
+	pub fn new(nio: &'a N, geosub: &str) -> Dispatcher<'a, N> {
+		Dispatcher {
+			nio,
+			geosub: String::from(geosub),
+			tokens: None,
+		}
+	}
+
+	/// Check and consume registration tokens with `tokens`
+	pub fn with_token_issuer(mut self, tokens: TokenIssuer<'a, N>) -> Dispatcher<'a, N> {
+		self.tokens = Some(tokens);
+		self
+	}
+
+	/// Process a request and return the response
+	///
+	/// # Arguments
+	///
+	/// * `msg` - The request message
+	/// * `address` - The address of the node making the request
+	pub fn dispatch(&self, msg: &Message, address: IpAddr) -> Message {
+		match (msg.cmd, &msg.content) {
+			(CmdType::Register, MessageContent::Registration(r)) => self.register(r, address),
+			(CmdType::Unregister, MessageContent::NodeSingle(r)) => self.unregister(r, address),
+			(CmdType::Info, MessageContent::Info(r)) => self.info(r),
+			(CmdType::Update, MessageContent::Update(r)) => self.update(r, address),
+			(CmdType::Resolve, MessageContent::Resolve(r)) => self.resolve(r),
+			_ => generate_response_empty_code(Response::UnsupportedAction),
+		}
+	}
+
+	fn register(&self, reg: &ContentRegistration, address: IpAddr) -> Message {
+		let consumed = match self.tokens {
+			Some(ref t) => t.consume(reg).is_ok(),
+			None => self.consume_token(reg),
+		};
+
+		if !consumed {
+			return generate_response_empty_code(Response::NetspaceError)
+		}
+
+		let node = Node::from_registration(reg, address);
+		let result = self.nio.gsn_node_register(&node);
+
+		// A registration that failed has not used the token
+		if result.is_err() {
+			self.nio.gsn_add_token(&reg.token, &reg.ndouble.spring);
+		}
+
+		response_code(result)
+	}
+
+	/// Remove the stored token of a registration if it was
+	/// given for the springname being registered
+	fn consume_token(&self, reg: &ContentRegistration) -> bool {
+		match self.nio.gsn_token(&reg.token) {
+			Some(ref s) if *s == reg.ndouble.spring => self.nio.gsn_remove_token(&reg.token),
+			_ => false,
+		}
+	}
+
+	fn unregister(&self, single: &ContentNodeSingle, address: IpAddr) -> Message {
+		let node = match self.requesting_node(&single.nsingle.spring, address) {
+			Ok(n) => n,
+			Err(m) => return m,
+		};
+
+		response_code(self.nio.gsn_node_unregister(&node))
+	}
+
+	fn info(&self, info: &ContentInfoRequest) -> Message {
+		match info.info {
+			InfoContent::Network => {
+				generate_response_network(nodevec_quadvec(self.nio.gsn_nodes()))
+			},
+			InfoContent::Node(ref p) => {
+				match self.nio.gsn_node_by_springname(&p.spring) {
+					Ok(n) => generate_response_node_info(n.to_node_info_property(p.property.clone())),
+					Err(_) => generate_response_empty_code(Response::NetspaceError),
+				}
+			}
+		}
+	}
+
+	fn update(&self, update: &ContentNodeProperty, address: IpAddr) -> Message {
+		let mut node = match self.requesting_node(&update.spring, address) {
+			Ok(n) => n,
+			Err(m) => return m,
+		};
+
+		let result = match update.property {
+			NodeProperty::State(Some(s)) => {
+				node.update_state(s);
+				self.nio.gsn_node_update_state(&node)
+			},
+			NodeProperty::Service(Some(s)) => {
+				node.update_service(s);
+				self.nio.gsn_node_update_service(&node)
+			},
+			NodeProperty::Role(Some(r)) => {
+				node.update_role(r);
+				self.nio.gsn_node_update_role(&node)
+			},
+			_ => return generate_response_empty_code(Response::UnsupportedAction),
+		};
+
+		response_code(result)
+	}
+
+	fn resolve(&self, resolve: &ContentUri) -> Message {
+		match Resolver::new(self.nio, &self.geosub).resolve(&resolve.uri) {
+			Ok(r) => generate_response_resolution(r.to_content()),
+			Err(_) => generate_response_empty_code(Response::NetspaceError),
+		}
+	}
+
+	/// Get a registered node, checking the request came from it
+	fn requesting_node(&self, spring: &str, address: IpAddr) -> Result<Node, Message> {
+		let node = match self.nio.gsn_node_by_springname(spring) {
+			Ok(n) => n,
+			Err(_) => return Err(generate_response_empty_code(Response::NetspaceError)),
+		};
+
+		if node.address() != Some(canonical_ip(address)) {
+			return Err(generate_response_empty_code(Response::UnsupportedAction))
+		}
+
+		Ok(node)
+	}
+}
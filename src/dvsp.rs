@@ -0,0 +1,283 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `dvsp`
+//!
+//! Exchanges messages directly over TCP on `Port::Dvsp`,
+//! without wrapping them in HTTP.
+//!
+//! Each request and response is a text message framed as in
+//! module `stream`, and a connection may carry any number of
+//! requests, each answered in turn. A request that is not a
+//! valid message is answered with `MalformedContent`.
+//!
+//! A server serves at most `MAX_CONNECTIONS` connections at
+//! once by default; connections accepted beyond that are
+//! closed straight away.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use spring_dvs::dispatch::Dispatcher;
+//! use spring_dvs::dvsp::DvspServer;
+//! use spring_dvs::spaces::MemoryNetspace;
+//!
+//! let nio = MemoryNetspace::new();
+//! let dispatcher = Dispatcher::new(&nio, "esusx");
+//!
+//! let server = DvspServer::bind("0.0.0.0:55301").unwrap();
+//! server.serve(|msg, peer| dispatcher.dispatch(msg, peer.ip()));
+//! ```
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::formats::canonical_ip;
+use crate::http::{Endpoint, OutboundError, connect_timeout};
+use crate::node::Node;
+use crate::enums::Response;
+use crate::protocol::{Message, Port, generate_response_empty_code};
+use crate::stream::{MessageReader, MessageWriter, StreamError, MAX_FRAME};
+
+/// The number of connections a server serves at once by
+/// default
+pub const MAX_CONNECTIONS: usize = 128;
+
+impl From<StreamError> for OutboundError {
+	fn from(e: StreamError) -> OutboundError {
+		match e {
+			StreamError::Io(e) => OutboundError::from(e),
+			StreamError::Closed => OutboundError::Closed,
+			StreamError::MalformedFrame |
+			StreamError::FrameTooLarge => OutboundError::MalformedFrame,
+			StreamError::Parse(e) => OutboundError::Parse(e),
+		}
+	}
+}
+
+/// A connection to a node over DVSP
+pub struct DvspConnection {
+	reader: MessageReader<TcpStream>,
+	writer: MessageWriter<TcpStream>,
+}
+
+impl DvspConnection {
+	fn new(stream: TcpStream, max_frame: usize) -> io::Result<DvspConnection> {
+		Ok(DvspConnection {
+			reader: MessageReader::new(stream.try_clone()?).with_max_frame(max_frame),
+			writer: MessageWriter::new(stream),
+		})
+	}
+
+	/// Fail reads and writes that take longer than `limit`, or
+	/// wait indefinitely if it is `None`
+	pub fn set_timeout(&self, limit: Option<Duration>) -> io::Result<()> {
+		self.writer.get_ref().set_read_timeout(limit)?;
+		self.writer.get_ref().set_write_timeout(limit)
+	}
+
+	/// Send a message and read its response
+	pub fn request(&mut self, message: &Message) -> Result<Message, OutboundError> {
+		self.writer.write_message(message)?;
+
+		match self.reader.read_message()? {
+			Some(m) => Ok(m),
+			None => Err(OutboundError::Closed),
+		}
+	}
+
+	pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+		self.writer.get_ref().peer_addr()
+	}
+}
+
+/// Sends messages to nodes over DVSP
+#[derive(Clone, Debug)]
+pub struct DvspOutbound {
+	connect_timeout: Duration,
+	timeout: Option<Duration>,
+	max_frame: usize,
+}
+
+impl Default for DvspOutbound {
+	fn default() -> DvspOutbound {
+		DvspOutbound::new()
+	}
+}
+
+impl DvspOutbound {
+	/// Outbound connections with a five second connect timeout,
+	/// ten seconds for each read and write, and responses of
+	/// up to `MAX_FRAME` bytes
+	pub fn new() -> DvspOutbound {
+		DvspOutbound {
+			connect_timeout: Duration::from_secs(5),
+			timeout: Some(Duration::from_secs(10)),
+			max_frame: MAX_FRAME,
+		}
+	}
+
+	pub fn with_connect_timeout(mut self, limit: Duration) -> DvspOutbound {
+		self.connect_timeout = limit;
+		self
+	}
+
+	/// Fail reads and writes that take longer than `limit`, or
+	/// wait indefinitely if it is `None`
+	pub fn with_timeout(mut self, limit: Option<Duration>) -> DvspOutbound {
+		self.timeout = limit;
+		self
+	}
+
+	/// Accept responses of up to `limit` bytes, such as a large
+	/// `info network` answer
+	pub fn with_max_frame(mut self, limit: usize) -> DvspOutbound {
+		self.max_frame = limit;
+		self
+	}
+
+	/// Open a connection for sending many messages
+	pub fn connect(&self, address: &str, port: u16) -> Result<DvspConnection, OutboundError> {
+		let stream = connect_timeout(address, port, self.connect_timeout)?;
+		let connection = DvspConnection::new(stream, self.max_frame)?;
+		connection.set_timeout(self.timeout)?;
+		Ok(connection)
+	}
+
+	/// Send a message to `address` on `port` and parse its
+	/// response
+	pub fn request(&self, message: &Message, address: &str, port: u16) -> Result<Message, OutboundError> {
+		self.connect(address, port)?.request(message)
+	}
+
+	/// Send a message to a node on `Port::Dvsp` and parse its
+	/// response
+	pub fn request_node(&self, message: &Message, node: &Node) -> Result<Message, OutboundError> {
+		self.request(message, Endpoint::from_node(node).address(), Port::Dvsp.number())
+	}
+}
+
+/// Accepts DVSP connections and answers their messages
+pub struct DvspServer {
+	listener: TcpListener,
+	max_frame: usize,
+	timeout: Option<Duration>,
+	max_connections: usize,
+}
+
+impl DvspServer {
+	/// A server listening on `address`, accepting frames of up
+	/// to `MAX_FRAME` bytes, keeping idle connections open
+	/// for a minute and serving up to `MAX_CONNECTIONS` of
+	/// them at once
+	pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<DvspServer> {
+		Ok(DvspServer {
+			listener: TcpListener::bind(address)?,
+			max_frame: MAX_FRAME,
+			timeout: Some(Duration::from_secs(60)),
+			max_connections: MAX_CONNECTIONS,
+		})
+	}
+
+	pub fn with_max_frame(mut self, limit: usize) -> DvspServer {
+		self.max_frame = limit;
+		self
+	}
+
+	/// Close connections that are idle for longer than
+	/// `limit`, or never if it is `None`
+	pub fn with_timeout(mut self, limit: Option<Duration>) -> DvspServer {
+		self.timeout = limit;
+		self
+	}
+
+	/// Serve up to `limit` connections at once, closing any
+	/// more as soon as they are accepted
+	pub fn with_max_connections(mut self, limit: usize) -> DvspServer {
+		self.max_connections = limit;
+		self
+	}
+
+	pub fn local_addr(&self) -> io::Result<SocketAddr> {
+		self.listener.local_addr()
+	}
+
+	/// Accept connections and answer each message with the
+	/// response returned by `handler`, which is given the
+	/// message and the address it came from
+	///
+	/// Each connection is served on its own thread, up to the
+	/// connection limit. This only returns if accepting
+	/// connections fails, once the open connections have
+	/// closed.
+	pub fn serve<F>(&self, handler: F) -> io::Result<()>
+		where F: Fn(&Message, SocketAddr) -> Message + Sync {
+		let handler = &handler;
+		let open = &AtomicUsize::new(0);
+
+		thread::scope(|scope| {
+			loop {
+				let stream = match self.listener.accept() {
+					Ok((s, _)) => s,
+					Err(ref e) if e.kind() == io::ErrorKind::Interrupted || e.kind() == io::ErrorKind::ConnectionAborted => continue,
+					Err(e) => return Err(e),
+				};
+
+				if open.fetch_add(1, Ordering::SeqCst) >= self.max_connections {
+					open.fetch_sub(1, Ordering::SeqCst);
+					continue
+				}
+
+				let slot = ConnectionSlot(open);
+				scope.spawn(move || {
+					let _slot = slot;
+					let _ = self.serve_connection(stream, handler);
+				});
+			}
+		})
+	}
+
+	/// Answer the messages of one connection until it closes
+	///
+	/// The handler is given the address of the peer with IPv4
+	/// addresses mapped into IPv6 written as IPv4
+	///
+	/// Fails if the connection fails or its frames are
+	/// malformed, after which it is closed
+	pub fn serve_connection<F>(&self, stream: TcpStream, handler: &F) -> Result<(), StreamError>
+		where F: Fn(&Message, SocketAddr) -> Message {
+		let mut peer = stream.peer_addr()?;
+		peer.set_ip(canonical_ip(peer.ip()));
+		stream.set_read_timeout(self.timeout)?;
+		stream.set_write_timeout(self.timeout)?;
+
+		let mut reader = MessageReader::new(stream.try_clone()?).with_max_frame(self.max_frame);
+		let mut writer = MessageWriter::new(stream);
+
+		loop {
+			let response = match reader.read_message() {
+				Ok(Some(m)) => handler(&m, peer),
+				Ok(None) => return Ok(()),
+				Err(StreamError::Parse(_)) => generate_response_empty_code(Response::MalformedContent),
+				Err(e) => return Err(e),
+			};
+
+			writer.write_message(&response)?;
+		}
+	}
+}
+
+/// An open connection counted against the connection limit,
+/// which is given back when the connection ends however it
+/// ends
+struct ConnectionSlot<'a>(&'a AtomicUsize);
+
+impl<'a> Drop for ConnectionSlot<'a> {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::SeqCst);
+	}
+}
@@ -5,8 +5,30 @@
 
 use std::fmt;
 
+/// Serialise as the `Display` string and deserialise with a
+/// parser of that string
+#[cfg(feature = "serde")]
+macro_rules! serde_string {
+	($t:ident, $parse:expr) => (
+		impl serde::Serialize for $t {
+			fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+				serializer.collect_str(self)
+			}
+		}
+
+		impl<'de> serde::Deserialize<'de> for $t {
+			fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				let s = String::deserialize(deserializer)?;
+				let parse : fn(&str) -> Option<$t> = $parse;
+				parse(&s).ok_or_else(|| serde::de::Error::custom(format_args!("invalid {} `{}`", stringify!($t), s)))
+			}
+		}
+	)
+}
+
 // ----- Enumeration Lists ----- \\
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DvspCmdType {
 	Undefined = 0,
 	GsnRegistration = 1,
@@ -64,6 +86,9 @@ impl Response {
 	}
 }
 
+#[cfg(feature = "serde")]
+serde_string!(Response, Response::from_str);
+
 impl fmt::Display for Response {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let out = match *self {
@@ -113,6 +138,12 @@ impl NodeRole {
 	}
 }
 
+#[cfg(feature = "serde")]
+serde_string!(NodeRole, |s| match s {
+	"undefined" => Some(NodeRole::Undefined),
+	s => NodeRole::from_str(s),
+});
+
 impl fmt::Display for NodeRole {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let out = match self {
@@ -151,6 +182,12 @@ impl NodeService {
 	}
 }
 
+#[cfg(feature = "serde")]
+serde_string!(NodeService, |s| match s {
+	"undefined" => Some(NodeService::Undefined),
+	s => NodeService::from_str(s),
+});
+
 impl fmt::Display for NodeService {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let out = match self {
@@ -191,6 +228,9 @@ impl NodeState {
 	}
 }
 
+#[cfg(feature = "serde")]
+serde_string!(NodeState, NodeState::from_str);
+
 impl fmt::Display for NodeState {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let out = match self {
@@ -204,6 +244,7 @@ impl fmt::Display for NodeState {
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Bounds {
 	MaxNodeType = 3,
 	PacketContentSize = 512,
@@ -212,12 +253,14 @@ pub enum Bounds {
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Failure {
 	OutOfBounds, InvalidArgument, InvalidBytes, InvalidConversion, InvalidFormat,
 	Duplicate,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParseFailure {
 	ConversionError,
 	UnexpectedContent,
@@ -233,11 +276,13 @@ pub enum ParseFailure {
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Success {
 	Ok	
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnitTestAction {
 	Undefined = 0,
 	Reset = 1,
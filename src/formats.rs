@@ -4,19 +4,12 @@
  */
 use std::fmt;
 use std::str::FromStr;
-use regex::Regex;
 pub use std::net::{IpAddr};
 
-pub use ::enums::{ParseFailure,NodeService,NodeState,NodeRole};
+pub use crate::enums::{ParseFailure,NodeService,NodeState,NodeRole};
+use crate::names::{is_springname, is_hostfield, to_ascii, to_unicode};
 
 
-macro_rules! rng {
-	($chk:expr, $from:expr, $to:expr) => (
-		//($from..$to).contains($chk)
-		if $chk >= $from && $chk <= $to { true } else { false } 
-	)
-}
-
 #[macro_export]
 macro_rules! opt_parsefail {
 	($opt:expr) => (
@@ -52,85 +45,50 @@ macro_rules! res_parsefail {
 }
 
 
-fn valid_springname(s: &str) -> bool {
-	if rng!(s.len(),1,63) == false {
-		false
-	} else {
-		let rex = Regex::new(r"^[a-z0-9-s]+$").unwrap();
-		rex.is_match(s)
-	}
-}
-
-#[test]
-fn ts_valid_springname_pass() {
-	assert!(valid_springname("foo-bar"));
-	assert!(valid_springname("foobar"));
-	assert!(valid_springname("f"));
-	assert!(valid_springname("foo123"));
-}
-
-#[test]
-fn ts_valid_springname_fail() {
-	assert_eq!(valid_springname("foo.bar"),false);
-	assert_eq!(valid_springname("foobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobar"),false);
-	assert_eq!(valid_springname(""),false);
-	assert_eq!(valid_springname("foo.123"),false);
-	assert_eq!(valid_springname("foo_123"),false);
-	assert_eq!(valid_springname("foo*123"),false);
-}
-
-fn valid_hostname(s: &str) -> bool {
-	if rng!(s.len(),1,63) == false {
-		false
-	} else {
-		let rex = Regex::new(r"^[a-z0-9-s./]+$").unwrap();
-		rex.is_match(s)
-	}
-}
-
-#[test]
-fn ts_valid_hostname_pass() {
-	assert!(valid_hostname("foo.bar"));
-	assert!(valid_hostname("foo-bar"));
-	assert!(valid_hostname("f"));
-	assert!(valid_hostname("foo123"));
-}
-
-#[test]
-fn ts_valid_hostname_fail() {
-	assert_eq!(valid_hostname("foo(bar"),false);
-	assert_eq!(valid_hostname("foobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobar"),false);
-	assert_eq!(valid_hostname(""),false);
-	assert_eq!(valid_hostname("foo[123"),false);
-	assert_eq!(valid_hostname("foo_123"),false);
-	assert_eq!(valid_hostname("foo*123"),false);
+/// Parse an IP address from its text
+///
+/// IPv4 addresses mapped into IPv6 are taken as IPv4, so
+/// an address compares equal however it was given.
+pub fn canonical_address(s: &str) -> Option<IpAddr> {
+	IpAddr::from_str(s).ok().map(canonical_ip)
 }
 
-fn valid_ip(s: &str) -> bool {
-	match IpAddr::from_str(s) {
-		Ok(_) => true,
-		_ => false,
+/// IPv4 addresses mapped into IPv6 are taken as IPv4
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+	match ip {
+		IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+			Some(v4) => IpAddr::V4(v4),
+			None => ip,
+		},
+		v4 => v4,
 	}
 }
 
 #[test]
 fn ts_valid_ip_pass() {
-	assert!(valid_ip("192.168.1.1"));
-	assert!(valid_ip("1.1.1.1"));
-	assert!(valid_ip("1.255.0.0"));
+	assert!(canonical_address("192.168.1.1").is_some());
+	assert!(canonical_address("1.1.1.1").is_some());
+	assert!(canonical_address("1.255.0.0").is_some());
+	assert!(canonical_address("2001:db8::1").is_some());
+	assert!(canonical_address("::1").is_some());
+	assert_eq!(canonical_address("2001:0DB8:0:0::0001").unwrap().to_string(), "2001:db8::1");
+	assert_eq!(canonical_address("::ffff:192.168.1.1").unwrap().to_string(), "192.168.1.1");
 }
 
 #[test]
 fn ts_valid_ip_fail() {
-	assert_eq!(valid_ip("192.168.1.1.3"), false);
-	assert_eq!(valid_ip("1.1"), false);
-	assert_eq!(valid_ip("1"), false);
+	assert_eq!(canonical_address("192.168.1.1.3"), None);
+	assert_eq!(canonical_address("1.1"), None);
+	assert_eq!(canonical_address("1"), None);
+	assert_eq!(canonical_address("[::1]"), None);
+	assert_eq!(canonical_address("2001:db8::1::2"), None);
 }
 
 /// NodeSingle consists of the string Springname
 /// 
 /// Text Format: spring
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeSingleFmt {
 	pub spring: String,
 }
@@ -139,13 +97,14 @@ pub struct NodeSingleFmt {
 impl NodeSingleFmt {
 	pub fn from_str(sns: &str) -> Result<Self, ParseFailure> {
 		let s = sns.to_lowercase();
+		let s = opt_parsefail!(to_ascii(&s), ParseFailure::InvalidNaming);
 		
-		if valid_springname(&s) == false {
+		if !is_springname(&s) {
 			return Err(ParseFailure::InvalidNaming)
 		}
 
 		Ok( NodeSingleFmt { 
-				spring: String::from(s), 
+				spring: s.into_owned(), 
 			}
 		)
 	}
@@ -153,6 +112,11 @@ impl NodeSingleFmt {
 	pub fn to_string(&self) -> String {
 		format!("{}", self)
 	}
+	
+	/// The springname in Unicode for display
+	pub fn spring_unicode(&self) -> String {
+		to_unicode(&self.spring).into_owned()
+	}
 }
 
 impl fmt::Display for NodeSingleFmt {
@@ -165,6 +129,7 @@ impl fmt::Display for NodeSingleFmt {
 /// 
 /// Text Format: spring,host
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeDoubleFmt {
 	pub spring: String,
 	pub host: String,
@@ -180,12 +145,14 @@ impl NodeDoubleFmt {
 			return Err(ParseFailure::InvalidContentFormat) 
 		}
 		
-		if valid_springname(parts[0]) == false || valid_hostname(parts[1]) == false {
+		let spring = opt_parsefail!(to_ascii(parts[0]), ParseFailure::InvalidNaming);
+		
+		if !is_springname(&spring) || !is_hostfield(parts[1]) {
 			return Err(ParseFailure::InvalidNaming)
 		}
 
 		Ok( NodeDoubleFmt { 
-				spring: String::from(parts[0]),
+				spring: spring.into_owned(),
 				host: String::from(parts[1]), 
 			}
 		)
@@ -194,6 +161,11 @@ impl NodeDoubleFmt {
 	pub fn to_string(&self) -> String {
 		format!("{}", self)
 	}
+	
+	/// The springname in Unicode for display
+	pub fn spring_unicode(&self) -> String {
+		to_unicode(&self.spring).into_owned()
+	}
 }
 
 impl fmt::Display for NodeDoubleFmt {
@@ -204,12 +176,15 @@ impl fmt::Display for NodeDoubleFmt {
 
 /// NodeTriple consists of the strings Springname, Hostname and IP Address
 /// 
-/// Text Format: spring,host,###.###.###.###
+/// Text Format: spring,host,address
+///
+/// The address is IPv4 or IPv6
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeTripleFmt {
 	pub spring: String,
 	pub host: String,
-	pub address: String,
+	pub address: IpAddr,
 }
 
 impl NodeTripleFmt {
@@ -222,18 +197,17 @@ impl NodeTripleFmt {
 			return Err(ParseFailure::InvalidContentFormat) 
 		}
 		
-		if valid_ip(parts[2]) == false {
-			return Err(ParseFailure::InvalidAddress)
-		}
+		let address = opt_parsefail!(canonical_address(parts[2]), ParseFailure::InvalidAddress);
+		let spring = opt_parsefail!(to_ascii(parts[0]), ParseFailure::InvalidNaming);
 
-		if valid_springname(parts[0]) == false || valid_hostname(parts[1]) == false {
+		if !is_springname(&spring) || !is_hostfield(parts[1]) {
 			return Err(ParseFailure::InvalidNaming)
 		}
 
 		Ok( NodeTripleFmt { 
-				spring: String::from(parts[0]),
+				spring: spring.into_owned(),
 				host: String::from(parts[1]),
-				address: String::from(parts[2]) 
+				address,
 			}
 		)
 	}
@@ -251,12 +225,13 @@ impl fmt::Display for NodeTripleFmt {
 
 /// NodeQuad consists of the strings Springname, Hostname, IP Address and service
 /// 
-/// Text Format: spring,host,###.###.###.###,service
+/// Text Format: spring,host,address,service
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeQuadFmt {
 	pub spring: String,
 	pub host: String,
-	pub address: String,
+	pub address: IpAddr,
 	pub service: NodeService,
 }
 
@@ -270,20 +245,20 @@ impl NodeQuadFmt {
 			return Err(ParseFailure::InvalidContentFormat) 
 		}
 
-		if valid_springname(parts[0]) == false || valid_hostname(parts[1]) == false {
+		let spring = opt_parsefail!(to_ascii(parts[0]), ParseFailure::InvalidNaming);
+
+		if !is_springname(&spring) || !is_hostfield(parts[1]) {
 			return Err(ParseFailure::InvalidNaming)
 		}
 		
-		if valid_ip(parts[2]) == false {
-			return Err(ParseFailure::InvalidAddress)
-		}
+		let address = opt_parsefail!(canonical_address(parts[2]), ParseFailure::InvalidAddress);
 				
 		let service = opt_parsefail!(NodeService::from_str(parts[3]), ParseFailure::InvalidService);
 
 		Ok( NodeQuadFmt { 
-				spring: String::from(parts[0]),
+				spring: spring.into_owned(),
 				host: String::from(parts[1]),
-				address: String::from(parts[2]),
+				address,
 				service: service,
 				 
 			}
@@ -308,10 +283,11 @@ impl fmt::Display for NodeQuadFmt {
 /// It will display any fields that are correctly set and
 /// ignore fields that are `unset`
 #[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeInfoFmt {
 	pub spring: String,
 	pub host: String,
-	pub address: String,
+	pub address: Option<IpAddr>,
 	
 	pub service: NodeService,
 	pub state: NodeState,
@@ -323,7 +299,7 @@ impl NodeInfoFmt {
 		NodeInfoFmt {
 			spring: String::new(),
 			host: String::new(),
-			address: String::new(),
+			address: None,
 			
 			service: NodeService::Undefined,
 			state: NodeState::Unspecified,
@@ -336,7 +312,7 @@ impl NodeInfoFmt {
 		let mut ni = NodeInfoFmt {
 			spring: String::new(),
 			host: String::new(),
-			address: String::new(),
+			address: None,
 			
 			service: NodeService::Undefined,
 			state: NodeState::Unspecified,
@@ -356,9 +332,20 @@ impl NodeInfoFmt {
 									 } 
 								);
 			match key.trim() {
-				"spring"  => ni.spring = String::from( value[1..].trim() ),
-				"host"    => ni.host = String::from( value[1..].trim() ),
-				"address" => ni.address = String::from( value[1..].trim() ),
+				"spring"  => ni.spring = match to_ascii(value[1..].trim()) {
+								Some(v) if v.is_empty() => String::new(),
+								Some(v) if is_springname(&v) => v.into_owned(),
+								_ => return Err(ParseFailure::InvalidNaming),
+							},
+				"host"    => ni.host = match value[1..].trim() {
+								"" => String::new(),
+								v if is_hostfield(v) => String::from(v),
+								_ => return Err(ParseFailure::InvalidNaming),
+							},
+				"address" => ni.address = match value[1..].trim() {
+								"" => None,
+								a => Some(opt_parsefail!(canonical_address(a), ParseFailure::InvalidAddress)),
+							},
 				"service" => ni.service = opt_parsefail!(NodeService::from_str(value[1..].trim()), ParseFailure::InvalidService),
 				"state"   => ni.state = opt_parsefail!(NodeState::from_str(value[1..].trim()), ParseFailure::InvalidState),
 				"role"    => ni.role = opt_parsefail!(NodeRole::from_str(value[1..].trim()), ParseFailure::InvalidRole),
@@ -377,7 +364,7 @@ impl fmt::Display for NodeInfoFmt {
 		
 		if self.spring  != "" { v.push(format!("spring:{}", self.spring))   }
 		if self.host    != "" { v.push(format!("host:{}", self.host))       }
-		if self.address != "" { v.push(format!("address:{}", self.address)) }
+		if let Some(a) = self.address { v.push(format!("address:{}", a)) }
 		
 		if self.service != NodeService::Undefined   { v.push(format!("service:{}", self.service)) }
 		if self.state   != NodeState::Unspecified   { v.push(format!("state:{}", self.state))     }
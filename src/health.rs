@@ -0,0 +1,272 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `health`
+//!
+//! Probes the nodes of a `Netspace` and keeps their state in
+//! line with whether they answer.
+//!
+//! Each round a `HealthMonitor` sends `info node <spring> state`
+//! to every enabled node. A node that fails to answer for
+//! a number of consecutive rounds is marked `Unresponsive`.
+//! Unresponsive nodes are probed as well, and are marked
+//! `Enabled` again as soon as they answer.
+//!
+//! Any response counts as an answer, even an error, as the
+//! probe only asks whether the node is reachable.
+//!
+//! Nodes are probed one after another, so a round takes as
+//! long as all of its probes together. `HttpTransport` bounds
+//! connecting, sending and answering each probe by a timeout,
+//! five seconds by default, so a node that never answers
+//! delays the round by a bounded time and is counted as
+//! failing rather than holding up the monitor.
+//!
+//! The transport and clock are traits so the monitor can be
+//! driven without a network or real time passing.
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::http::{Outbound, OutboundError};
+use crate::node::Node;
+use crate::protocol::{Message, ProtocolObject, NodeState};
+use crate::spaces::Netspace;
+
+/// How many consecutive failures mark a node unresponsive by
+/// default
+pub const DEFAULT_THRESHOLD: u32 = 3;
+
+/// Sends probe requests to nodes
+pub trait Transport {
+	fn request_node(&self, message: &Message, node: &Node) -> Result<Message, OutboundError>;
+}
+
+/// The default limit on each probe by `HttpTransport`
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Probes nodes with `Outbound::request_node`
+#[derive(Clone, Debug)]
+pub struct HttpTransport {
+	outbound: Outbound,
+}
+
+impl Default for HttpTransport {
+	fn default() -> HttpTransport {
+		HttpTransport::new()
+	}
+}
+
+impl HttpTransport {
+	/// Probes bounded by `DEFAULT_PROBE_TIMEOUT`
+	pub fn new() -> HttpTransport {
+		HttpTransport { outbound: Outbound::new() }.with_timeout(DEFAULT_PROBE_TIMEOUT)
+	}
+
+	/// Give up on a node that takes longer than `limit` to
+	/// accept the connection, to take the probe or to answer it
+	pub fn with_timeout(mut self, limit: Duration) -> HttpTransport {
+		self.outbound = self.outbound
+			.with_connect_timeout(limit)
+			.with_write_timeout(limit)
+			.with_read_timeout(limit);
+		self
+	}
+}
+
+impl Transport for HttpTransport {
+	fn request_node(&self, message: &Message, node: &Node) -> Result<Message, OutboundError> {
+		self.outbound.request_node(message, node)
+	}
+}
+
+/// The time source of a monitor
+pub trait Clock {
+	fn now(&self) -> SystemTime;
+	fn sleep(&self, duration: Duration);
+}
+
+/// The system time, sleeping the current thread
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> SystemTime {
+		SystemTime::now()
+	}
+
+	fn sleep(&self, duration: Duration) {
+		thread::sleep(duration)
+	}
+}
+
+/// What the monitor knows of a node
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct NodeHealth {
+	/// Failed probes since the node last answered
+	pub failures: u32,
+
+	/// When the node was last probed
+	pub last_checked: Option<SystemTime>,
+
+	/// When the node last answered
+	pub last_seen: Option<SystemTime>,
+}
+
+/// The outcome of a round of probes
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Round {
+	/// The springnames of the nodes probed
+	pub checked: Vec<String>,
+
+	/// Nodes that failed to answer
+	pub failed: Vec<String>,
+
+	/// Nodes marked unresponsive this round
+	pub unresponsive: Vec<String>,
+
+	/// Nodes marked enabled again this round
+	pub restored: Vec<String>,
+}
+
+/// Probes the nodes of a netspace
+pub struct HealthMonitor<'a, N: Netspace + 'a, T: Transport = HttpTransport, C: Clock = SystemClock> {
+	nio: &'a N,
+	transport: T,
+	clock: C,
+	threshold: u32,
+	interval: Duration,
+	health: HashMap<String, NodeHealth>,
+}
+
+impl<'a, N: Netspace + 'a> HealthMonitor<'a, N> {
+	/// A monitor probing over HTTP every minute, marking nodes
+	/// unresponsive after `DEFAULT_THRESHOLD` failures
+	pub fn new(nio: &'a N) -> HealthMonitor<'a, N> {
+		HealthMonitor {
+			nio,
+			transport: HttpTransport::new(),
+			clock: SystemClock,
+			threshold: DEFAULT_THRESHOLD,
+			interval: Duration::from_secs(60),
+			health: HashMap::new(),
+		}
+	}
+}
+
+impl<'a, N: Netspace + 'a, T: Transport, C: Clock> HealthMonitor<'a, N, T, C> {
+	pub fn with_transport<U: Transport>(self, transport: U) -> HealthMonitor<'a, N, U, C> {
+		HealthMonitor {
+			nio: self.nio,
+			transport,
+			clock: self.clock,
+			threshold: self.threshold,
+			interval: self.interval,
+			health: self.health,
+		}
+	}
+
+	pub fn with_clock<D: Clock>(self, clock: D) -> HealthMonitor<'a, N, T, D> {
+		HealthMonitor {
+			nio: self.nio,
+			transport: self.transport,
+			clock,
+			threshold: self.threshold,
+			interval: self.interval,
+			health: self.health,
+		}
+	}
+
+	/// Mark nodes unresponsive after `threshold` consecutive
+	/// failures; a threshold of zero is taken as one
+	pub fn with_threshold(mut self, threshold: u32) -> HealthMonitor<'a, N, T, C> {
+		self.threshold = threshold.max(1);
+		self
+	}
+
+	/// The time between rounds when running
+	pub fn with_interval(mut self, interval: Duration) -> HealthMonitor<'a, N, T, C> {
+		self.interval = interval;
+		self
+	}
+
+	/// What is known of the node `spring`, if it has been probed
+	pub fn health(&self, spring: &str) -> Option<&NodeHealth> {
+		self.health.get(spring)
+	}
+
+	/// Probe every enabled and unresponsive node once
+	///
+	/// The nodes are probed in turn, so this takes as long as
+	/// the probes together
+	pub fn check(&mut self) -> Round {
+		let mut round = Round::default();
+
+		let mut nodes = self.nio.gsn_nodes_by_state(NodeState::Enabled);
+		nodes.extend(self.nio.gsn_nodes_by_state(NodeState::Unresponsive));
+
+		for node in &nodes {
+			self.check_node(node, &mut round);
+		}
+
+		// Forget nodes that are no longer probed
+		self.health.retain(|spring, _| round.checked.contains(spring));
+
+		round
+	}
+
+	/// Run rounds separated by the interval for as long as
+	/// `proceed` returns true for the last round
+	pub fn run<F>(&mut self, mut proceed: F)
+		where F: FnMut(&Round) -> bool {
+		loop {
+			let round = self.check();
+			if !proceed(&round) {
+				break
+			}
+
+			self.clock.sleep(self.interval);
+		}
+	}
+
+	fn check_node(&mut self, node: &Node, round: &mut Round) {
+		let spring = node.springname();
+		let probe = match Message::from_bytes(format!("info node {} state", spring).as_bytes()) {
+			Ok(m) => m,
+			Err(_) => return,
+		};
+
+		let answered = self.transport.request_node(&probe, node).is_ok();
+		let now = self.clock.now();
+		round.checked.push(String::from(spring));
+
+		let health = self.health.entry(String::from(spring)).or_default();
+		health.last_checked = Some(now);
+
+		if answered {
+			health.failures = 0;
+			health.last_seen = Some(now);
+
+			if node.state() == NodeState::Unresponsive && self.mark(node, NodeState::Enabled) {
+				round.restored.push(String::from(spring));
+			}
+
+			return
+		}
+
+		health.failures = health.failures.saturating_add(1);
+		round.failed.push(String::from(spring));
+
+		if health.failures >= self.threshold && node.state() == NodeState::Enabled && self.mark(node, NodeState::Unresponsive) {
+			round.unresponsive.push(String::from(spring));
+		}
+	}
+
+	fn mark(&self, node: &Node, state: NodeState) -> bool {
+		let mut node = node.clone();
+		node.update_state(state);
+		self.nio.gsn_node_update_state(&node).is_ok()
+	}
+}
@@ -3,27 +3,31 @@
  * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
  */
 use std::str;
-use std::str::FromStr;
-use std::net::{SocketAddr};
-use std::i64;
+use std::net::{SocketAddr, IpAddr, Ipv4Addr, Ipv6Addr};
+use std::io;
+use std::fmt;
+use std::error;
+use std::time::{Duration, Instant};
+#[cfg(any(feature = "tls", feature = "crypto"))]
+use std::sync::Arc;
+#[cfg(feature = "tls")]
+use std::sync::OnceLock;
+#[cfg(feature = "tls")]
+use std::convert::TryFrom;
 
-use protocol::{ProtocolObject, Message};
-use node::Node;
-use enums::{Failure};
+use crate::protocol::{ProtocolObject, Message, Port};
+use crate::node::Node;
+use crate::enums::{Failure, ParseFailure};
+use crate::formats::canonical_ip;
+#[cfg(feature = "crypto")]
+use crate::crypto::{self, CryptoFailure, NodeKey, SIGNATURE_HEADER, TIMESTAMP_HEADER};
 
 use std::io::prelude::*;
-use std::net::{TcpStream};
+use std::net::{TcpStream, ToSocketAddrs};
 
 
 pub struct HttpWrapper;
 
-// ToDo: Make the chunked encoding handler nicer
-// ToDo: Handle case where HTTP header is larger than initial buffer 
-// ToDo *2:
-//   Handle all the standard and de-facto headers here
-//   - Forwarded:
-//   - X-Real-IP:
-
 impl HttpWrapper {
 
 	/// Takes a Message and wraps it in an HTTP request and 
@@ -99,13 +103,25 @@ Content-Length: {}\r\n\r\n", host, bytes.len()
 	/// * `host` - The host of the target node
 	/// * `post` - The host of the target node
 	pub fn wrap_request(bytes: &[u8], host: &str, path: &str) -> Vec<u8> {
+		HttpWrapper::wrap_request_with(bytes, host, path, &Headers::new())
+	}
+	
+	/// Takes bytes and wrap in HTTP POST request with extra
+	/// header fields
+	pub fn wrap_request_with(bytes: &[u8], host: &str, path: &str, extra: &Headers) -> Vec<u8> {
+		HttpWrapper::wrap_request_as(bytes, host, path, MessageEncoding::Text, extra)
+	}
+	
+	/// Takes bytes of a message in an encoding and wrap in HTTP
+	/// POST request with extra header fields
+	pub fn wrap_request_as(bytes: &[u8], host: &str, path: &str, encoding: MessageEncoding, extra: &Headers) -> Vec<u8> {
 
 		let header : String = format!(
 "POST /{} HTTP/1.1\r
 Host: {}\r
 User-Agent: SpringPrim/0.3\r
-Content-Type: text/plain\r
-Content-Length: {}\r\n\r\n", path, host, bytes.len()
+Content-Type: {}\r
+{}Content-Length: {}\r\n\r\n", path, host, encoding.content_type(), extra, bytes.len()
 		);
 		
 		let mut v = Vec::new();
@@ -114,6 +130,66 @@ Content-Length: {}\r\n\r\n", path, host, bytes.len()
 		v
 	}
 	
+	/// Takes bytes and wrap in HTTP POST request signed with
+	/// the key of this node
+	#[cfg(feature = "crypto")]
+	pub fn wrap_signed_request(bytes: &[u8], host: &str, path: &str, key: &NodeKey) -> Vec<u8> {
+		let mut extra = Headers::new();
+		HttpWrapper::sign_request(&mut extra, bytes, path, key);
+		HttpWrapper::wrap_request_with(bytes, host, path, &extra)
+	}
+	
+	/// Add the signature of a request body to `extra`, with
+	/// the time it was signed
+	#[cfg(feature = "crypto")]
+	fn sign_request(extra: &mut Headers, bytes: &[u8], path: &str, key: &NodeKey) {
+		let now = crypto::timestamp();
+		extra.add(TIMESTAMP_HEADER, &now.to_string());
+		extra.add(SIGNATURE_HEADER, &key.sign_line(&format!("POST /{}", path), now, bytes));
+	}
+	
+	/// Verify the signature of an HTTP request or response
+	/// against the public key block of the node that sent it
+	///
+	/// The signature covers the request line or status code,
+	/// the time it was signed and the body, up to its
+	/// `Content-Length`. Stale signatures are rejected.
+	#[cfg(feature = "crypto")]
+	pub fn verify_signature(bytes: &[u8], key: &str) -> Result<(), CryptoFailure> {
+		let split = match find(bytes, b"\r\n\r\n") {
+			Some(i) => i,
+			None => return Err(CryptoFailure::MissingSignature),
+		};
+		
+		let head = match str::from_utf8(&bytes[..split]) {
+			Ok(h) => h,
+			Err(_) => return Err(CryptoFailure::MissingSignature),
+		};
+		let headers = Headers::parse_lenient(head);
+		
+		let (signature, timestamp) = match (headers.get(SIGNATURE_HEADER), headers.get(TIMESTAMP_HEADER)) {
+			(Some(s), Some(t)) => (s, t),
+			_ => return Err(CryptoFailure::MissingSignature),
+		};
+		
+		// `POST /spring HTTP/1.1` is signed as `POST /spring`
+		// and `HTTP/1.1 200 OK` as `200`
+		let start : Vec<&str> = head.lines().next().unwrap_or("").split_whitespace().collect();
+		let line = match start.as_slice() {
+			[version, code, ..] if version.starts_with("HTTP/") => code.to_string(),
+			[method, target, ..] => format!("{} {}", method, target),
+			_ => return Err(CryptoFailure::VerificationFailed),
+		};
+		
+		let body = &bytes[split+4..];
+		let body = match headers.content_length() {
+			Some(Ok(len)) if len <= body.len() => &body[..len],
+			_ => body,
+		};
+		
+		crypto::verify_line(key, &line, timestamp, body, signature)
+	}
+	
 	pub fn unwrap_response(bytes: &[u8]) -> Option<(Vec<u8>,Vec<u8>)> {
 		let s = match str::from_utf8(bytes) {
 			Ok(s) => s,
@@ -141,13 +217,42 @@ Content-Length: {}\r\n\r\n", path, host, bytes.len()
 	///
 	/// * `msg` - The Packet to serialise for HTTP service layer	
 	pub fn serialise_response(msg: &Message) -> Vec<u8> {
-		let serial = msg.to_bytes();
+		HttpWrapper::serialise_response_with(msg, &Headers::new())
+	}
+	
+	/// Takes a Message and wraps it in an HTTP response with
+	/// extra header fields
+	pub fn serialise_response_with(msg: &Message, extra: &Headers) -> Vec<u8> {
+		HttpWrapper::serialise_response_as(msg, MessageEncoding::Text, extra)
+	}
+	
+	/// Takes a Message and wraps it in an HTTP response in the
+	/// encoding the client accepts
+	///
+	/// # Arguments
+	///
+	/// * `msg` - The response message
+	/// * `request` - The header fields of the request
+	pub fn serialise_response_for(msg: &Message, request: &Headers) -> Vec<u8> {
+		HttpWrapper::serialise_response_as(msg, MessageEncoding::negotiate(request), &Headers::new())
+	}
+	
+	/// Takes a Message and wraps it in an HTTP response in an
+	/// encoding with extra header fields
+	///
+	/// A message that cannot be written in the encoding is sent
+	/// as text
+	pub fn serialise_response_as(msg: &Message, encoding: MessageEncoding, extra: &Headers) -> Vec<u8> {
+		let (encoding, serial) = match encoding.encode(msg) {
+			Ok(s) => (encoding, s),
+			Err(_) => (MessageEncoding::Text, msg.to_bytes()),
+		};
 		let header : String = format!(
 "HTTP/1.1 200 OK\r
 Server: SpringDVS/0.1\r
-Content-Type: text/plain\r
+Content-Type: {}\r
 Connection: Closed\r
-Content-Length: {}\r\n\r\n", serial.len()
+{}Content-Length: {}\r\n\r\n", encoding.content_type(), extra, serial.len()
 		);
 		
 		let mut v = Vec::new();
@@ -156,6 +261,17 @@ Content-Length: {}\r\n\r\n", serial.len()
 		v
 	}
 	
+	/// Takes a Message and wraps it in an HTTP response signed
+	/// with the key of this node
+	#[cfg(feature = "crypto")]
+	pub fn serialise_signed_response(msg: &Message, key: &NodeKey) -> Vec<u8> {
+		let now = crypto::timestamp();
+		let mut extra = Headers::new();
+		extra.add(TIMESTAMP_HEADER, &now.to_string());
+		extra.add(SIGNATURE_HEADER, &key.sign_line("200", now, &msg.to_bytes()));
+		HttpWrapper::serialise_response_with(msg, &extra)
+	}
+	
 	/// Takes the bytes of a packet, encodes it in an hexadecimal string
 	/// wrapped in an HTTP response and returns a vector for bytes
 	///
@@ -183,24 +299,39 @@ Content-Length: {}\r\n\r\n", bytes.len()
 	/// # Arguments
 	///
 	/// * `bytes` - A Vector of u8 bytes consisting of the entire request	
+	///
+	/// The address is rewritten to the forwarded address if the
+	/// request came through a proxy on the loopback interface
 	pub fn deserialise_request(bytes: Vec<u8>, address: &mut SocketAddr) -> Result<Message,Failure> {
+		HttpWrapper::deserialise_request_with(bytes, address, &TrustedProxies::loopback())
+	}
+	
+	/// Takes an HTTP service layer request and returns the
+	/// message encoded within, rewriting the address to the
+	/// forwarded address if the request came through one of
+	/// `proxies`
+	pub fn deserialise_request_with(bytes: Vec<u8>, address: &mut SocketAddr, proxies: &TrustedProxies) -> Result<Message,Failure> {
 		
 		let s = match String::from_utf8(bytes) {
 			Ok(s) => s,
 			Err(_) => return Err(Failure::InvalidBytes)
 		};
 		
-		let atoms : Vec<&str> = s.split("\r\n\r\n").collect();
+		let atoms : Vec<&str> = s.splitn(2, "\r\n\r\n").collect();
 		
 		if atoms.len() != 2 { return Err(Failure::InvalidFormat) }
+		let headers = Headers::parse_lenient(atoms[0]);
+		
 		// rewrite address incase of proxy forwarding
+		let ip = proxies.client_address(address.ip(), &headers);
+		address.set_ip(ip);
 		
-		match HttpWrapper::extract_forwarded(atoms[0]) {
-			Some(addr) => *address = SocketAddr::from_str(&format!("{}:80", addr)).unwrap(),
-			_ => { }
-		}
+		let body = match headers.content_length() {
+			Some(Ok(len)) if len <= atoms[1].len() => &atoms[1].as_bytes()[..len],
+			_ => atoms[1].as_bytes(),
+		};
 		
-		let m = match Message::from_bytes(atoms[1].trim().as_bytes()) {
+		let m = match MessageEncoding::of(&headers).decode(body) {
 			Ok(m) => m,
 			Err(_) => return Err(Failure::InvalidConversion)
 		};
@@ -227,31 +358,10 @@ Content-Length: {}\r\n\r\n", bytes.len()
 		}
 	}
 	
-	fn extract_forwarded(block: &str) -> Option<String> {
-		let headers : Vec<&str> = block.split("\n").collect();
-		for header in headers {
-			let atoms : Vec<&str> = header.split(":").collect();
-			
-			// Todo: *2
-			if atoms[0] == "X-Forwarded-For" && atoms.len() > 1 {
-				return Some(String::from(atoms[1].trim()));
-			}
-		} 
-		
-		None
-	}
-	
+	/// Get the first value of a header in a block of header
+	/// lines, ignoring the case of the name
 	pub fn extract_header(search: &str, block: &str) -> Option<String> {
-		let headers : Vec<&str> = block.split("\n").collect();
-		for header in headers {
-			let atoms : Vec<&str> = header.split(":").collect();
-			
-			if atoms[0] == search && atoms.len() > 1 {
-				return Some(String::from(atoms[1].trim()));
-			}
-		} 
-		
-		None		
+		Headers::parse_lenient(block).get(search).map(String::from)
 	}
 	
 	pub fn content_len(bytes: &[u8]) -> Option<usize> {
@@ -261,174 +371,1578 @@ Content-Length: {}\r\n\r\n", bytes.len()
 			Err(_) => return None,
 		};
 		
-		match HttpWrapper::extract_header("Content-Length", block) {
-			Some(v) => match v.parse::<usize>() {
-				Ok(u) => Some(u),
-				Err(_) => None
-			},
-			None => None
+		Headers::parse_lenient(block).content_length()?.ok()
+	}
+}
+
+/// How a message is encoded in the body of a request or
+/// response
+///
+/// Messages are sent as `text/plain` in the text format of
+/// the protocol. With the `json` feature they can be sent as
+/// `application/json` in the JSON form described in the
+/// `protocol` module, for clients such as browsers that find
+/// the text format awkward to produce.
+///
+/// The encoding of a request is taken from its `Content-Type`
+/// and a response is sent in the encoding chosen from the
+/// `Accept` header of the request.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MessageEncoding {
+	Text,
+	#[cfg(feature = "json")]
+	Json,
+}
+
+impl MessageEncoding {
+	pub fn content_type(&self) -> &'static str {
+		match *self {
+			MessageEncoding::Text => "text/plain",
+			#[cfg(feature = "json")]
+			MessageEncoding::Json => "application/json",
+		}
+	}
+
+	/// The encoding of a media type, ignoring any parameters
+	pub fn from_content_type(value: &str) -> Option<MessageEncoding> {
+		let media = value.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+
+		match media.as_str() {
+			"text/plain" => Some(MessageEncoding::Text),
+			#[cfg(feature = "json")]
+			"application/json" => Some(MessageEncoding::Json),
+			_ => None,
+		}
+	}
+
+	/// The encoding of a body from its `Content-Type`, which
+	/// is text if the type is missing or unknown
+	pub fn of(headers: &Headers) -> MessageEncoding {
+		headers.get("Content-Type")
+			.and_then(MessageEncoding::from_content_type)
+			.unwrap_or(MessageEncoding::Text)
+	}
+
+	/// Choose the encoding of a response from the `Accept`
+	/// header of the request
+	///
+	/// The encoding with the highest quality is chosen, taking
+	/// the quality of a type from its most specific range.
+	/// Text is chosen on a tie, when there is no `Accept`
+	/// header and when nothing acceptable is supported.
+	#[cfg_attr(not(feature = "json"), allow(unused_variables))]
+	pub fn negotiate(request: &Headers) -> MessageEncoding {
+		#[cfg(feature = "json")]
+		{
+			let ranges = request.get_list("Accept");
+			if quality("application/json", &ranges) > quality("text/plain", &ranges) {
+				return MessageEncoding::Json
+			}
+		}
+
+		MessageEncoding::Text
+	}
+
+	/// Encode a message, failing with `InvalidConversion` if it
+	/// cannot be written in this encoding
+	pub fn encode(&self, msg: &Message) -> Result<Vec<u8>, Failure> {
+		match *self {
+			MessageEncoding::Text => Ok(msg.to_bytes()),
+			#[cfg(feature = "json")]
+			MessageEncoding::Json => serde_json::to_vec(msg).map_err(|_| Failure::InvalidConversion),
+		}
+	}
+
+	pub fn decode(&self, bytes: &[u8]) -> Result<Message, ParseFailure> {
+		match *self {
+			MessageEncoding::Text => Message::from_bytes(bytes.trim_ascii()),
+			#[cfg(feature = "json")]
+			MessageEncoding::Json => serde_json::from_slice(bytes).map_err(|_| ParseFailure::InvalidContentFormat),
 		}
 	}
 }
 
-pub struct Outbound;
+/// The quality given to a media type by the ranges of an
+/// `Accept` header, from the most specific range matching it
+#[cfg(feature = "json")]
+fn quality(media: &str, ranges: &[&str]) -> u16 {
+	let kind = media.split('/').next().unwrap_or("");
+	let mut best : Option<(u8, u16)> = None;
 
-impl Outbound {
-	pub fn request(bytes: &[u8], address: &str, host: &str, path: &str) -> Option<Vec<u8>> {
-		
-		let addr = format!("{}:{}", address, 80);
-		let msg = HttpWrapper::wrap_request(bytes, host, path);
-		
-		let mut stream = match TcpStream::connect(addr.as_str()) {
-			Ok(s) => s,
-			Err(_) => return None
+	for range in ranges {
+		let mut params = range.split(';');
+		let name = params.next().unwrap_or("").trim().to_ascii_lowercase();
+
+		let specificity = if name == media {
+			2
+		} else if name.strip_suffix("/*") == Some(kind) {
+			1
+		} else if name == "*/*" {
+			0
+		} else {
+			continue
 		};
-		
-		
 
-		stream.write(msg.as_slice()).unwrap();
+		// Qualities are held in thousandths
+		let mut q = Some(1000);
+		for p in params {
+			if let Some((k, v)) = p.split_once('=') {
+				if k.trim().eq_ignore_ascii_case("q") {
+					q = v.trim().parse::<f32>().ok()
+						.filter(|q| (0.0..=1.0).contains(q))
+						.map(|q| (q * 1000.0).round() as u16);
+				}
+			}
+		}
 
-		let mut buf = [0;4096];
-		let size = match stream.read(&mut buf) {
-					Ok(s) => s,
-					Err(_) => 0
+		let q = match q {
+			Some(q) => q,
+			None => continue,
 		};
 
-		
+		if best.is_none_or(|(s, _)| specificity > s) {
+			best = Some((specificity, q));
+		}
+	}
 
-		if size == 0 { return None }
-		let (hdrbuf, msgbuf) = match HttpWrapper::unwrap_response(&buf[0..size]) {
-			Some(r) => r,
-			None => return None
-		};
+	best.map_or(0, |(_, q)| q)
+}
+
+/// The header fields of an HTTP request or response
+///
+/// Names are matched without regard to case and a name may
+/// appear more than once. Values are kept whole, so a value
+/// containing colons, such as an IPv6 address, is intact.
+///
+/// # Example
+///
+/// ```
+/// use spring_dvs::http::Headers;
+///
+/// let h = Headers::parse("Content-Length: 3\r\nVia: a\r\nvia: b").unwrap();
+/// assert_eq!(h.get("content-length"), Some("3"));
+/// assert_eq!(h.get_all("VIA"), vec!["a", "b"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Headers {
+	fields: Vec<(String,String)>,
+}
+
+impl Headers {
+	pub fn new() -> Headers {
+		Headers {
+			fields: Vec::new(),
+		}
+	}
+	
+	/// Parse header lines, separated by `\r\n` or `\n`
+	///
+	/// Fails if a line is not a `Name: value` field
+	pub fn parse(block: &str) -> Option<Headers> {
+		let mut headers = Headers::new();
+		if block.is_empty() {
+			return Some(headers)
+		}
 		
-		let hdrstr = String::from_utf8(hdrbuf.clone()).unwrap();
+		for line in block.split('\n') {
+			let line = line.trim_end_matches('\r');
+			let (name, value) = split_field(line)?;
+			headers.add(name, value);
+		}
 		
-		match HttpWrapper::extract_header("Content-Length", &hdrstr) {
-			Some(_) => Outbound::transfer_single(&hdrbuf, msgbuf, &mut stream),
-			None => {
-				match HttpWrapper::extract_header("Transfer-Encoding", &hdrstr) {
-					Some(_) => Outbound::transfer_chunked(msgbuf, &mut stream),
-					None => None
-				}
+		Some(headers)
+	}
+	
+	/// Parse header lines, skipping any line that is not a
+	/// field, such as a request or status line
+	pub fn parse_lenient(block: &str) -> Headers {
+		let mut headers = Headers::new();
+		for line in block.split('\n') {
+			if let Some((name, value)) = split_field(line.trim_end_matches('\r')) {
+				headers.add(name, value);
 			}
 		}
-			
-
+		
+		headers
+	}
+	
+	/// Add a field, keeping any existing fields of the same name
+	///
+	/// Carriage returns and line feeds are removed from the
+	/// name and value, so a field cannot be made to end early
+	/// and start another
+	pub fn add(&mut self, name: &str, value: &str) {
+		self.fields.push((strip_line_breaks(name), strip_line_breaks(value)));
+	}
+	
+	/// Replace all fields of a name with a single value
+	pub fn set(&mut self, name: &str, value: &str) {
+		self.remove(name);
+		self.add(name, value);
+	}
+	
+	pub fn remove(&mut self, name: &str) {
+		self.fields.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+	}
+	
+	/// The first value of the named field
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v)
+	}
+	
+	/// Every value of the named field, in order
+	pub fn get_all(&self, name: &str) -> Vec<&str> {
+		self.iter().filter(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v).collect()
+	}
+	
+	/// The comma-separated elements of every value of the
+	/// named field, in order
+	pub fn get_list(&self, name: &str) -> Vec<&str> {
+		self.get_all(name).into_iter()
+			.flat_map(|v| v.split(','))
+			.map(|v| v.trim())
+			.filter(|v| !v.is_empty())
+			.collect()
+	}
+	
+	pub fn contains(&self, name: &str) -> bool {
+		self.get(name).is_some()
 	}
 	
-	fn transfer_single(hdrbuf: &Vec<u8>, mut msgbuf: Vec<u8>, stream: &mut TcpStream) -> Option<Vec<u8>> {
+	/// The `Content-Length`, or an error if it is not a number
+	/// or is given more than once with different values
+	pub fn content_length(&self) -> Option<Result<usize, Failure>> {
+		let values = self.get_list("Content-Length");
+		let first = values.first()?;
 		
-		match HttpWrapper::content_len(hdrbuf.as_slice()) {
-			Some(conlen) => {
-				let metalen = hdrbuf.len() + 4; // 4 bytes = \r\n\r\n
-				if (metalen + conlen) > 4096 {
-					let diff = conlen - (4096-metalen);
-					let mut vbuf = Vec::new();
-					vbuf.resize(diff, 0);
-					match stream.read(&mut vbuf.as_mut_slice()) {
-						Ok(s) => s,
-						Err(_) =>  0
-					};
-					msgbuf.append(&mut vbuf);
-				}
-				
-				Some(msgbuf)
-			}
-			_ => { Some(msgbuf) }
+		if values.iter().any(|v| v != first) {
+			return Some(Err(Failure::InvalidFormat))
 		}
 		
-					
+		Some(first.parse::<usize>().map_err(|_| Failure::InvalidConversion))
+	}
+	
+	/// Fields as `(name, value)` pairs in the order received
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.fields.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+	}
+	
+	pub fn len(&self) -> usize {
+		self.fields.len()
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.fields.is_empty()
+	}
+}
+
+impl fmt::Display for Headers {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (k, v) in self.iter() {
+			write!(f, "{}: {}\r\n", k, v)?;
+		}
+		Ok(())
+	}
+}
+
+fn strip_line_breaks(s: &str) -> String {
+	s.chars().filter(|c| *c != '\r' && *c != '\n').collect()
+}
+
+/// Split a `Name: value` field, trimming whitespace around both
+fn split_field(line: &str) -> Option<(&str, &str)> {
+	let i = line.find(':')?;
+	let name = line[..i].trim();
+	
+	if name.is_empty() || name.contains(char::is_whitespace) {
+		return None
+	}
+	
+	Some((name, line[i+1..].trim()))
+}
+
+/// The reasons an outbound request can fail
+#[derive(Debug)]
+pub enum OutboundError {
+	/// The connection to the node could not be made
+	Connect(io::Error),
+	
+	/// Connecting, sending or receiving took too long
+	Timeout,
+	
+	/// Sending the request or receiving the response failed
+	Io(io::Error),
+	
+	/// The connection closed before the response was complete
+	Closed,
+	
+	/// The HTTP response header is malformed or unsupported
+	MalformedHeader,
+	
+	/// A chunk of a chunked response has an invalid size line
+	InvalidChunk,
+	
+	/// The response body is not a valid message
+	Parse(ParseFailure),
+	
+	/// The TLS handshake failed, or TLS is not available
+	Tls(io::Error),
+	
+	/// A DVSP response frame is malformed or too large
+	MalformedFrame,
+	
+	/// The response body is larger than the client's limit
+	BodyTooLarge,
+	
+	/// The node answered with an HTTP status other than 2xx,
+	/// so the body is not a message
+	Status(u16),
+}
+
+impl fmt::Display for OutboundError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			OutboundError::Connect(ref e) => write!(f, "connection failed: {}", e),
+			OutboundError::Timeout => write!(f, "timed out"),
+			OutboundError::Io(ref e) => write!(f, "i/o error: {}", e),
+			OutboundError::Closed => write!(f, "connection closed before response was complete"),
+			OutboundError::MalformedHeader => write!(f, "malformed HTTP response header"),
+			OutboundError::InvalidChunk => write!(f, "invalid chunk in HTTP response"),
+			OutboundError::Parse(ref e) => write!(f, "invalid response message: {:?}", e),
+			OutboundError::Tls(ref e) => write!(f, "TLS failed: {}", e),
+			OutboundError::MalformedFrame => write!(f, "malformed DVSP response frame"),
+			OutboundError::BodyTooLarge => write!(f, "response body too large"),
+			OutboundError::Status(code) => write!(f, "HTTP status {}", code),
+		}
+	}
+}
+
+impl OutboundError {
+	/// Whether the failure is down to the network rather than
+	/// the protocol, so the request may succeed if retried
+	pub fn is_transient(&self) -> bool {
+		match *self {
+			OutboundError::Connect(_) |
+			OutboundError::Timeout |
+			OutboundError::Io(_) |
+			OutboundError::Closed => true,
+			OutboundError::Status(code) => code >= 500,
+			OutboundError::MalformedHeader |
+			OutboundError::InvalidChunk |
+			OutboundError::Parse(_) |
+			OutboundError::Tls(_) |
+			OutboundError::MalformedFrame |
+			OutboundError::BodyTooLarge => false,
+		}
+	}
+}
+
+impl error::Error for OutboundError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match *self {
+			OutboundError::Connect(ref e) => Some(e),
+			OutboundError::Io(ref e) => Some(e),
+			OutboundError::Tls(ref e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for OutboundError {
+	fn from(e: io::Error) -> OutboundError {
+		match e.kind() {
+			io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => OutboundError::Timeout,
+			io::ErrorKind::UnexpectedEof => OutboundError::Closed,
+			_ => OutboundError::Io(e),
+		}
+	}
+}
+
+impl From<ParseFailure> for OutboundError {
+	fn from(e: ParseFailure) -> OutboundError {
+		OutboundError::Parse(e)
+	}
+}
+
+/// The largest response header, or all the trailer fields of
+/// a chunked response together, that will be accepted
+pub const MAX_HEADER: usize = 16384;
+
+/// The default limit on the size of a response body
+pub const MAX_RESPONSE_BODY: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+	Header,
+	Sized(usize),
+	ChunkSize,
+	ChunkData(usize),
+	ChunkEnd,
+	Trailer,
+	ToClose,
+	Done,
+}
+
+/// Incremental parser for an HTTP/1.1 response
+///
+/// Bytes are fed in as they arrive from the connection, in
+/// pieces of any size. The header may be split across reads
+/// and the body may be sized by `Content-Length`, sent with
+/// chunked transfer encoding (chunk extensions and trailers
+/// are accepted and discarded) or run until the connection
+/// closes. The body is kept as raw bytes, up to
+/// `MAX_RESPONSE_BODY` of them unless another limit is set.
+///
+/// # Example
+///
+/// ```
+/// use spring_dvs::http::ResponseParser;
+///
+/// let mut p = ResponseParser::new();
+/// assert!(!p.feed(b"HTTP/1.1 200 OK\r\nContent-Le").unwrap());
+/// assert!(p.feed(b"ngth: 3\r\n\r\n200").unwrap());
+/// assert_eq!(p.body(), b"200");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResponseParser {
+	stage: Stage,
+	buf: Vec<u8>,
+	status: u16,
+	headers: Headers,
+	trailer_len: usize,
+	body: Vec<u8>,
+	max_body: usize,
+}
+
+impl Default for ResponseParser {
+	fn default() -> ResponseParser {
+		ResponseParser::new()
+	}
+}
+
+impl ResponseParser {
+	pub fn new() -> ResponseParser {
+		ResponseParser {
+			stage: Stage::Header,
+			buf: Vec::new(),
+			status: 0,
+			headers: Headers::new(),
+			trailer_len: 0,
+			body: Vec::new(),
+			max_body: MAX_RESPONSE_BODY,
+		}
+	}
+	
+	/// Fail with `OutboundError::BodyTooLarge` once the body
+	/// is larger than `limit`
+	pub fn with_max_body(mut self, limit: usize) -> ResponseParser {
+		self.max_body = limit;
+		self
 	}
 	
-	fn transfer_chunked(msgbuf: Vec<u8>, stream: &mut TcpStream) -> Option<Vec<u8>> {
+	/// Feed the next bytes read from the connection
+	///
+	/// Returns true once the response is complete. Any bytes
+	/// after the end of the response are ignored.
+	pub fn feed(&mut self, bytes: &[u8]) -> Result<bool, OutboundError> {
+		if self.stage == Stage::Done {
+			return Ok(true)
+		}
+		
+		self.buf.extend_from_slice(bytes);
 		
-		let mut buflen = msgbuf.len();
 		let mut index = 0;
-		let mut response = str::from_utf8(msgbuf.as_slice()).unwrap().to_string();
+		let r = self.advance(&mut index);
+		self.buf.drain(..index);
+		r?;
 		
+		Ok(self.stage == Stage::Done)
+	}
+	
+	/// Signal that the connection has closed
+	///
+	/// This completes a response that runs until the connection
+	/// closes; any other incomplete response fails with `Closed`
+	pub fn finish(&mut self) -> Result<(), OutboundError> {
+		match self.stage {
+			Stage::Done => Ok(()),
+			Stage::ToClose => {
+				self.stage = Stage::Done;
+				Ok(())
+			},
+			_ => Err(OutboundError::Closed),
+		}
+	}
+	
+	pub fn is_complete(&self) -> bool {
+		self.stage == Stage::Done
+	}
+	
+	/// The status code, or 0 if the header is not complete
+	pub fn status(&self) -> u16 {
+		self.status
+	}
+	
+	/// The first value of the named header, ignoring case
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.get(name)
+	}
+	
+	/// The headers, once the header is complete
+	pub fn headers(&self) -> &Headers {
+		&self.headers
+	}
+	
+	/// The body received so far
+	pub fn body(&self) -> &[u8] {
+		&self.body
+	}
+	
+	pub fn into_body(self) -> Vec<u8> {
+		self.body
+	}
+	
+	/// Parse the body as a message in the encoding of the
+	/// `Content-Type`, failing with `OutboundError::Status` if
+	/// the status is not 2xx
+	pub fn message(&self) -> Result<Message, OutboundError> {
+		if !(200..300).contains(&self.status) {
+			return Err(OutboundError::Status(self.status))
+		}
 		
-		let mut aggregate = String::new();
+		Ok(MessageEncoding::of(&self.headers).decode(&self.body)?)
+	}
+	
+	fn advance(&mut self, index: &mut usize) -> Result<(), OutboundError> {
 		loop {
-			
-			let r = response.clone();
-			let buf : Vec<&str> = r.splitn(2, "\r\n").collect();
-			
-			if buf[0].trim() == "0" {
-					break
-			}
-
-			index += buf[0].len() + 2; // include \r\n bytes
-			
-			
-			let chunk_size = i64::from_str_radix(buf[0], 16).unwrap() as usize;
-			let buflen_req = index + chunk_size;
-			
-			let chunkbuf = if buflen_req > buflen + 2 {
-				
-				// We haven't pulled the whole chunk from the socket
-				// so read the chunk and read to next chunk size
-				let diff = (buflen_req - buflen)+2;
+			let rest = &self.buf[*index..];
+			match self.stage {
+				Stage::Header => {
+					let end = match find(rest, b"\r\n\r\n") {
+						Some(i) => i,
+						None if rest.len() > MAX_HEADER => return Err(OutboundError::MalformedHeader),
+						None => return Ok(()),
+					};
+					
+					let block = match str::from_utf8(&rest[..end]) {
+						Ok(b) => b.to_string(),
+						Err(_) => return Err(OutboundError::MalformedHeader),
+					};
+					
+					*index += end + 4;
+					self.parse_header(&block)?;
+				},
 				
-				let mut v : Vec<u8> = Vec::new();
-				v.resize(diff, b'\0');
-				let _ = stream.read_exact(&mut v.as_mut());
-				let mut bufstr = String::from(buf[1]);
-				 
-				bufstr.push_str(str::from_utf8(v.as_slice()).unwrap());
+				Stage::Sized(remaining) => {
+					let n = remaining.min(rest.len());
+					self.body.extend_from_slice(&rest[..n]);
+					*index += n;
+					
+					if remaining - n > 0 {
+						self.stage = Stage::Sized(remaining - n);
+						return Ok(())
+					}
+					
+					self.stage = Stage::Done;
+				},
 				
-				buflen += diff;
+				Stage::ChunkSize => {
+					let eol = match find(rest, b"\r\n") {
+						Some(i) => i,
+						None if rest.len() > MAX_HEADER => return Err(OutboundError::InvalidChunk),
+						None => return Ok(()),
+					};
+					
+					let size = chunk_size(&rest[..eol])?;
+					*index += eol + 2;
+					
+					if size > self.max_body - self.body.len() {
+						return Err(OutboundError::BodyTooLarge)
+					}
+					
+					self.stage = match size {
+						0 => Stage::Trailer,
+						s => Stage::ChunkData(s),
+					};
+				},
 				
-				v.clear();
+				Stage::ChunkData(remaining) => {
+					let n = remaining.min(rest.len());
+					self.body.extend_from_slice(&rest[..n]);
+					*index += n;
+					
+					if remaining - n > 0 {
+						self.stage = Stage::ChunkData(remaining - n);
+						return Ok(())
+					}
+					
+					self.stage = Stage::ChunkEnd;
+				},
 				
+				Stage::ChunkEnd => {
+					if rest.len() < 2 {
+						return Ok(())
+					}
+					
+					if &rest[..2] != b"\r\n" {
+						return Err(OutboundError::InvalidChunk)
+					}
+					
+					*index += 2;
+					self.stage = Stage::ChunkSize;
+				},
 				
-				let mut b = [b'\0'];
-				loop {
-					let _ =stream.read_exact(&mut b);
-					v.push(b[0]);
+				Stage::Trailer => {
+					// Trailer fields are discarded up to the empty line,
+					// and together are held to the limit of a header
+					let eol = match find(rest, b"\r\n") {
+						Some(i) => i,
+						None if self.trailer_len + rest.len() > MAX_HEADER => return Err(OutboundError::MalformedHeader),
+						None => return Ok(()),
+					};
 					
-					if b[0] == b'\r' {
-						let _ = stream.read_exact(&mut b);
-						v.push(b[0]);
-						if b[0] == b'\n' { break; }
+					self.trailer_len += eol + 2;
+					if self.trailer_len > MAX_HEADER {
+						return Err(OutboundError::MalformedHeader)
 					}
-				}
+					
+					*index += eol + 2;
+					if eol == 0 {
+						self.stage = Stage::Done;
+					}
+				},
 				
-				bufstr.push_str(str::from_utf8(v.as_slice()).unwrap());
-				buflen += v.len();
-				bufstr.clone()
+				Stage::ToClose => {
+					if rest.len() > self.max_body - self.body.len() {
+						return Err(OutboundError::BodyTooLarge)
+					}
+					
+					self.body.extend_from_slice(rest);
+					*index += rest.len();
+					return Ok(())
+				},
 				
-			} else {
-				String::from(buf[1])
-			};
-
-			let (value,rest) = chunkbuf.split_at(chunk_size);
-			
-			response = rest.trim_left().to_string();
-			index += chunk_size+2;
-			
-			aggregate.push_str(value);
+				Stage::Done => return Ok(()),
+			}
+		}
+	}
+	
+	fn parse_header(&mut self, block: &str) -> Result<(), OutboundError> {
+		let (status_line, rest) = match block.find("\r\n") {
+			Some(i) => (&block[..i], &block[i+2..]),
+			None => (block, ""),
+		};
+		
+		let mut atoms = status_line.splitn(3, ' ');
+		
+		match atoms.next() {
+			Some(v) if v.starts_with("HTTP/1.") => {},
+			_ => return Err(OutboundError::MalformedHeader),
+		}
+		
+		self.status = match atoms.next().map(|c| c.parse::<u16>()) {
+			Some(Ok(c)) if (100..1000).contains(&c) => c,
+			_ => return Err(OutboundError::MalformedHeader),
+		};
+		
+		self.headers = match Headers::parse(rest) {
+			Some(h) => h,
+			None => return Err(OutboundError::MalformedHeader),
+		};
+		
+		// Interim responses are followed by the real response
+		if self.status < 200 {
+			self.status = 0;
+			self.headers = Headers::new();
+			return Ok(())
+		}
+		
+		if self.status == 204 || self.status == 304 {
+			self.stage = Stage::Done;
+			return Ok(())
+		}
+		
+		let chunked = match self.header("Transfer-Encoding") {
+			Some(te) => te.split(',').any(|t| t.trim().eq_ignore_ascii_case("chunked")),
+			None => false,
+		};
+		
+		// Transfer-Encoding takes precedence over Content-Length
+		self.stage = if chunked {
+			Stage::ChunkSize
+		} else if self.header("Transfer-Encoding").is_some() {
+			Stage::ToClose
+		} else if let Some(len) = self.headers.content_length() {
+			match len {
+				Ok(0) => Stage::Done,
+				Ok(l) if l > self.max_body => return Err(OutboundError::BodyTooLarge),
+				Ok(l) => Stage::Sized(l),
+				Err(_) => return Err(OutboundError::MalformedHeader),
+			}
+		} else {
+			Stage::ToClose
+		};
+		
+		Ok(())
+	}
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+	haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Parse a chunk size line, ignoring any chunk extensions
+fn chunk_size(line: &[u8]) -> Result<usize, OutboundError> {
+	let line = match str::from_utf8(line) {
+		Ok(l) => l,
+		Err(_) => return Err(OutboundError::InvalidChunk),
+	};
+	
+	let size = line.split(';').next().unwrap_or("").trim();
+	match usize::from_str_radix(size, 16) {
+		Ok(s) if !size.starts_with('+') => Ok(s),
+		_ => Err(OutboundError::InvalidChunk),
+	}
+}
+
+/// Proxies whose forwarding headers are trusted
+///
+/// A request that arrives from a trusted proxy is attributed
+/// to the client named in its forwarding headers, in order of
+/// preference `Forwarded` (RFC 7239), `X-Forwarded-For` and
+/// `X-Real-IP`. The hops in the headers are walked from the
+/// nearest outwards, skipping further trusted proxies, so a
+/// client cannot spoof its address by sending its own header
+/// through a proxy that appends to it.
+///
+/// # Example
+///
+/// ```
+/// use spring_dvs::http::{Headers, TrustedProxies};
+///
+/// let mut proxies = TrustedProxies::none();
+/// proxies.add("10.0.0.0/8").unwrap();
+///
+/// let mut headers = Headers::new();
+/// headers.add("X-Forwarded-For", "192.0.2.1, 10.0.0.7");
+/// let ip = proxies.client_address("10.0.0.1".parse().unwrap(), &headers);
+/// assert_eq!(ip, "192.0.2.1".parse::<std::net::IpAddr>().unwrap());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+	nets: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+	/// Trust no proxies; the peer is always the client
+	pub fn none() -> TrustedProxies {
+		TrustedProxies {
+			nets: Vec::new(),
+		}
+	}
+	
+	/// Trust proxies on the loopback interface
+	pub fn loopback() -> TrustedProxies {
+		TrustedProxies {
+			nets: vec![
+				(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 0)), 8),
+				(IpAddr::V6(Ipv6Addr::LOCALHOST), 128),
+			],
+		}
+	}
+	
+	/// Trust an address, or a network in CIDR notation such
+	/// as `10.0.0.0/8` or `fd00::/8`
+	pub fn add(&mut self, net: &str) -> Result<(), Failure> {
+		let (addr, prefix) = match net.find('/') {
+			Some(i) => (&net[..i], Some(&net[i+1..])),
+			None => (net, None),
+		};
+		
+		let addr : IpAddr = match addr.parse() {
+			Ok(a) => a,
+			Err(_) => return Err(Failure::InvalidFormat),
+		};
+		
+		let max = if addr.is_ipv4() { 32 } else { 128 };
+		let prefix = match prefix.map(|p| p.parse::<u8>()) {
+			None => max,
+			Some(Ok(p)) if p <= max => p,
+			Some(_) => return Err(Failure::InvalidFormat),
+		};
+		
+		self.nets.push((addr, prefix));
+		Ok(())
+	}
+	
+	pub fn contains(&self, ip: IpAddr) -> bool {
+		let ip = canonical_ip(ip);
+		self.nets.iter().any(|&(net, prefix)| in_network(ip, net, prefix))
+	}
+	
+	/// The effective address of the client that sent a request
+	///
+	/// # Arguments
+	///
+	/// * `peer` - The address the connection came from
+	/// * `headers` - The headers of the request
+	pub fn client_address(&self, peer: IpAddr, headers: &Headers) -> IpAddr {
+		if !self.contains(peer) {
+			return canonical_ip(peer)
 		}
 		
-		 
-		Some(Vec::from(aggregate.as_bytes()))
+		let hops = forwarded_hops(headers);
+		
+		let mut client = peer;
+		for hop in hops.iter().rev() {
+			client = match *hop {
+				Some(ip) => ip,
+				// An obfuscated or unknown hop ends the chain
+				None => return client,
+			};
+			
+			if !self.contains(client) {
+				break
+			}
+		}
+		
+		client
+	}
+}
+
+fn in_network(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+	match (ip, net) {
+		(IpAddr::V4(a), IpAddr::V4(n)) => {
+			let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+			u32::from(a) & mask == u32::from(n) & mask
+		},
+		(IpAddr::V6(a), IpAddr::V6(n)) => {
+			let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+			u128::from(a) & mask == u128::from(n) & mask
+		},
+		_ => false,
+	}
+}
+
+/// The client and proxy addresses in the forwarding headers,
+/// furthest first. Hops that are not addresses are `None`.
+fn forwarded_hops(headers: &Headers) -> Vec<Option<IpAddr>> {
+	let forwarded = headers.get_list("Forwarded");
+	if !forwarded.is_empty() {
+		return forwarded.iter()
+			.map(|element| {
+				element.split(';')
+					.filter_map(|pair| {
+						let i = pair.find('=')?;
+						if pair[..i].trim().eq_ignore_ascii_case("for") {
+							Some(pair[i+1..].trim())
+						} else {
+							None
+						}
+					})
+					.next()
+					.and_then(parse_hop)
+			})
+			.collect()
+	}
+	
+	let xff = headers.get_list("X-Forwarded-For");
+	if !xff.is_empty() {
+		return xff.into_iter().map(parse_hop).collect()
+	}
+	
+	match headers.get("X-Real-IP") {
+		Some(ip) => vec![parse_hop(ip.trim())],
+		None => Vec::new(),
+	}
+}
+
+/// Parse a forwarded node such as `192.0.2.1`, `"192.0.2.1:80"`,
+/// `2001:db8::1` or `"[2001:db8::1]:4711"`
+fn parse_hop(node: &str) -> Option<IpAddr> {
+	let node = node.trim_matches('"');
+	
+	if let Some(rest) = node.strip_prefix('[') {
+		let end = rest.find(']')?;
+		return rest[..end].parse::<Ipv6Addr>().ok().map(|ip| canonical_ip(IpAddr::V6(ip)))
+	}
+	
+	if let Ok(ip) = node.parse::<IpAddr>() {
+		return Some(canonical_ip(ip))
 	}
-	pub fn request_node(message: &Message, node: &Node) -> Option<Message> {
-		 
+	
+	// IPv4 with a port
+	match node.rfind(':') {
+		Some(i) => node[..i].parse::<Ipv4Addr>().ok().map(IpAddr::V4),
+		None => None,
+	}
+}
+
+/// The reasons reading a request can fail
+#[derive(Debug)]
+pub enum RequestError {
+	/// Receiving the request failed
+	Io(io::Error),
+	
+	/// The connection closed before the request was complete
+	Closed,
+	
+	/// The request line or header is malformed
+	MalformedHeader,
+	
+	/// The header is larger than `MAX_HEADER`
+	HeaderTooLarge,
+	
+	/// The request has no `Content-Length`
+	LengthRequired,
+	
+	/// The body is larger than the reader's limit
+	BodyTooLarge,
+	
+	/// The request body is not a valid message
+	Parse(ParseFailure),
+}
+
+impl fmt::Display for RequestError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			RequestError::Io(ref e) => write!(f, "i/o error: {}", e),
+			RequestError::Closed => write!(f, "connection closed before request was complete"),
+			RequestError::MalformedHeader => write!(f, "malformed HTTP request header"),
+			RequestError::HeaderTooLarge => write!(f, "HTTP request header too large"),
+			RequestError::LengthRequired => write!(f, "request has no Content-Length"),
+			RequestError::BodyTooLarge => write!(f, "request body too large"),
+			RequestError::Parse(ref e) => write!(f, "invalid request message: {:?}", e),
+		}
+	}
+}
+
+impl error::Error for RequestError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match *self {
+			RequestError::Io(ref e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for RequestError {
+	fn from(e: io::Error) -> RequestError {
+		match e.kind() {
+			io::ErrorKind::UnexpectedEof => RequestError::Closed,
+			_ => RequestError::Io(e),
+		}
+	}
+}
+
+/// A message received in an HTTP request
+#[derive(Debug)]
+pub struct Request {
+	pub method: String,
+	pub path: String,
+	pub headers: Headers,
+	
+	/// The body exactly as received
+	pub body: Vec<u8>,
+	pub message: Message,
+	
+	/// The address of the client, which is the forwarded
+	/// address if the request came through a trusted proxy
+	pub address: SocketAddr,
+}
+
+impl Request {
+	/// The first value of the named header, ignoring case
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.get(name)
+	}
+	
+	/// The encoding the message was sent in
+	pub fn encoding(&self) -> MessageEncoding {
+		MessageEncoding::of(&self.headers)
+	}
+	
+	/// The encoding the client accepts for the response
+	pub fn response_encoding(&self) -> MessageEncoding {
+		MessageEncoding::negotiate(&self.headers)
+	}
+	
+	/// Wrap a response message in an HTTP response in the
+	/// encoding the client accepts
+	pub fn respond(&self, msg: &Message) -> Vec<u8> {
+		HttpWrapper::serialise_response_for(msg, &self.headers)
+	}
+	
+	/// Verify the signature of the request against the public
+	/// key block of the node that sent it, rejecting stale
+	/// signatures
+	#[cfg(feature = "crypto")]
+	pub fn verify(&self, key: &str) -> Result<(), CryptoFailure> {
+		match (self.header(SIGNATURE_HEADER), self.header(TIMESTAMP_HEADER)) {
+			(Some(signature), Some(timestamp)) => {
+				let line = self.request_line();
+				crypto::verify_line(key, &line, timestamp, &self.body, signature)
+			},
+			_ => Err(CryptoFailure::MissingSignature),
+		}
+	}
+	
+	/// The method and target of the request, as `POST /spring`
+	pub fn request_line(&self) -> String {
+		format!("{} {}", self.method, self.path)
+	}
+}
+
+/// The default limit on the size of a request body
+pub const MAX_BODY: usize = 65536;
+
+/// Reads requests from nodes off a stream
+///
+/// The header is read up to the blank line and the body is
+/// then read to exactly `Content-Length` bytes, so a body
+/// that itself contains blank lines is read intact.
+///
+/// # Example
+///
+/// ```
+/// use spring_dvs::http::RequestReader;
+///
+/// let mut stream = &b"POST /spring/ HTTP/1.1\r\nContent-Length: 12\r\n\r\ninfo network"[..];
+/// let r = RequestReader::new().read(&mut stream, "127.0.0.1:55000".parse().unwrap()).unwrap();
+/// assert_eq!(r.path, "/spring/");
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestReader {
+	max_body: usize,
+	proxies: TrustedProxies,
+}
+
+impl Default for RequestReader {
+	fn default() -> RequestReader {
+		RequestReader::new()
+	}
+}
+
+impl RequestReader {
+	/// A reader accepting bodies of up to `MAX_BODY` bytes and
+	/// trusting forwarding headers from loopback proxies only
+	pub fn new() -> RequestReader {
+		RequestReader {
+			max_body: MAX_BODY,
+			proxies: TrustedProxies::loopback(),
+		}
+	}
+	
+	pub fn with_max_body(mut self, limit: usize) -> RequestReader {
+		self.max_body = limit;
+		self
+	}
+	
+	/// Trust forwarding headers from these proxies
+	pub fn with_trusted_proxies(mut self, proxies: TrustedProxies) -> RequestReader {
+		self.proxies = proxies;
+		self
+	}
+	
+	/// Read one request from a stream
+	///
+	/// # Arguments
+	///
+	/// * `stream` - The connection from the client
+	/// * `peer` - The address the connection came from
+	pub fn read<R: Read>(&self, stream: &mut R, peer: SocketAddr) -> Result<Request, RequestError> {
+		let mut buf : Vec<u8> = Vec::new();
+		let mut chunk = [0; 4096];
+		
+		let split = loop {
+			if let Some(i) = find(&buf, b"\r\n\r\n") {
+				break i
+			}
+			
+			if buf.len() > MAX_HEADER {
+				return Err(RequestError::HeaderTooLarge)
+			}
+			
+			match stream.read(&mut chunk) {
+				Ok(0) => return Err(RequestError::Closed),
+				Ok(n) => buf.extend_from_slice(&chunk[..n]),
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(RequestError::from(e)),
+			}
+		};
+		
+		if split > MAX_HEADER {
+			return Err(RequestError::HeaderTooLarge)
+		}
+		
+		let mut body = buf.split_off(split + 4);
+		let block = match str::from_utf8(&buf[..split]) {
+			Ok(b) => b,
+			Err(_) => return Err(RequestError::MalformedHeader),
+		};
+		
+		let (request_line, rest) = match block.find("\r\n") {
+			Some(i) => (&block[..i], &block[i+2..]),
+			None => (block, ""),
+		};
+		
+		let atoms : Vec<&str> = request_line.split(' ').collect();
+		if atoms.len() != 3 || !atoms[2].starts_with("HTTP/1.") {
+			return Err(RequestError::MalformedHeader)
+		}
+		
+		let headers = match Headers::parse(rest) {
+			Some(h) => h,
+			None => return Err(RequestError::MalformedHeader),
+		};
+		
+		let len = match headers.content_length() {
+			Some(Ok(l)) => l,
+			Some(Err(_)) => return Err(RequestError::MalformedHeader),
+			None => return Err(RequestError::LengthRequired),
+		};
+		
+		if len > self.max_body {
+			return Err(RequestError::BodyTooLarge)
+		}
+		
+		if body.len() < len {
+			let have = body.len();
+			body.resize(len, 0);
+			stream.read_exact(&mut body[have..])?;
+		}
+		body.truncate(len);
+		
+		let message = match MessageEncoding::of(&headers).decode(&body) {
+			Ok(m) => m,
+			Err(e) => return Err(RequestError::Parse(e)),
+		};
+		
+		let ip = self.proxies.client_address(peer.ip(), &headers);
+		let address = SocketAddr::new(ip, peer.port());
+		
+		Ok(Request {
+			method: atoms[0].to_string(),
+			path: atoms[1].to_string(),
+			headers,
+			body,
+			message,
+			address,
+		})
+	}
+}
+
+/// Where an outbound request is sent
+///
+/// By default requests go to port 80 over plain HTTP. An
+/// endpoint built from a `Node` follows the scheme and port of
+/// the node's hostfield, so `https://spring.example.tld:8443`
+/// is reached over TLS on port 8443.
+#[derive(Clone, Debug)]
+pub struct Endpoint {
+	address: String,
+	port: u16,
+	host: String,
+	path: String,
+	tls: bool,
+	encoding: MessageEncoding,
+	#[cfg(feature = "tls")]
+	tls_config: Option<Arc<rustls::ClientConfig>>,
+	#[cfg(feature = "crypto")]
+	signing_key: Option<Arc<NodeKey>>,
+}
+
+impl Endpoint {
+	/// Plain HTTP to `address` on port 80
+	///
+	/// # Arguments
+	///
+	/// * `address` - The address to connect to, IPv6 with or
+	///   without brackets
+	/// * `host` - The hostname of the node
+	/// * `path` - The path of the request, without leading `/`
+	pub fn new(address: &str, host: &str, path: &str) -> Endpoint {
+		Endpoint {
+			address: String::from(unbracket(address)),
+			port: Port::Http.number(),
+			host: String::from(host),
+			path: String::from(path),
+			tls: false,
+			encoding: MessageEncoding::Text,
+			#[cfg(feature = "tls")]
+			tls_config: None,
+			#[cfg(feature = "crypto")]
+			signing_key: None,
+		}
+	}
+	
+	/// The spring endpoint of a node
+	///
+	/// A node without an address is reached by its hostname
+	pub fn from_node(node: &Node) -> Endpoint {
 		let path = match node.hostpath() {
 			"" => "spring".to_string(),
 			s => format!("{}/spring", s)
 		};
 		
-		let response : Vec<u8> = match Outbound::request(message.to_bytes().as_slice(), node.address(), node.hostname(), &path) {
-			Some(r) => r,
-			None => return None
+		let default = if node.is_tls() { Port::Https } else { Port::Http };
+		
+		let address = match node.address() {
+			Some(a) => a.to_string(),
+			None => String::from(node.hostname()),
+		};
+		
+		let mut e = Endpoint::new(&address, node.hostname(), &path);
+		e.tls = node.is_tls();
+		e.port = node.port().unwrap_or_else(|| default.number());
+		e
+	}
+	
+	pub fn with_port(mut self, port: u16) -> Endpoint {
+		self.port = port;
+		self
+	}
+	
+	/// Connect over TLS, verifying the node's certificate
+	/// against `host`
+	pub fn with_tls(mut self, tls: bool) -> Endpoint {
+		self.tls = tls;
+		self
+	}
+	
+	/// Use this TLS configuration instead of one trusting
+	/// the webpki root certificates
+	#[cfg(feature = "tls")]
+	pub fn with_tls_config(mut self, config: Arc<rustls::ClientConfig>) -> Endpoint {
+		self.tls_config = Some(config);
+		self
+	}
+	
+	/// Send messages in this encoding and accept responses
+	/// in it
+	pub fn with_encoding(mut self, encoding: MessageEncoding) -> Endpoint {
+		self.encoding = encoding;
+		self
+	}
+	
+	/// Sign requests with the key of this node
+	#[cfg(feature = "crypto")]
+	pub fn with_signing_key(mut self, key: Arc<NodeKey>) -> Endpoint {
+		self.signing_key = Some(key);
+		self
+	}
+	
+	pub fn address(&self) -> &str {
+		&self.address
+	}
+	
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+	
+	pub fn host(&self) -> &str {
+		&self.host
+	}
+	
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+	
+	pub fn is_tls(&self) -> bool {
+		self.tls
+	}
+	
+	pub fn encoding(&self) -> MessageEncoding {
+		self.encoding
+	}
+	
+	/// Wrap bytes in an HTTP request to this endpoint
+	pub fn wrap(&self, bytes: &[u8]) -> Vec<u8> {
+		#[cfg(feature = "crypto")]
+		{
+			if let Some(ref key) = self.signing_key {
+				return HttpWrapper::wrap_signed_request(bytes, &self.host_header(), &self.path, key)
+			}
+		}
+		
+		HttpWrapper::wrap_request(bytes, &self.host_header(), &self.path)
+	}
+	
+	/// Wrap a message in an HTTP request to this endpoint in
+	/// the encoding of the endpoint, or as text if it cannot
+	/// be written in that encoding
+	pub fn wrap_message(&self, msg: &Message) -> Vec<u8> {
+		let bytes = match self.encoding.encode(msg) {
+			Ok(b) if self.encoding != MessageEncoding::Text => b,
+			_ => return self.wrap(&msg.to_bytes()),
 		};
+		let mut extra = Headers::new();
+		extra.add("Accept", self.encoding.content_type());
 		
+		#[cfg(feature = "crypto")]
+		{
+			if let Some(ref key) = self.signing_key {
+				HttpWrapper::sign_request(&mut extra, &bytes, &self.path, key);
+			}
+		}
 		
-		match Message::from_bytes(response.as_slice()) {
-			Ok(m) => Some(m),
-			Err(_) => None
+		HttpWrapper::wrap_request_as(&bytes, &self.host_header(), &self.path, self.encoding, &extra)
+	}
+	
+	/// The value of the Host header, which carries the port
+	/// if it is not the default for the scheme
+	///
+	/// An IPv6 literal host is put in brackets
+	pub fn host_header(&self) -> String {
+		let host = match self.host.parse::<Ipv6Addr>() {
+			Ok(_) => format!("[{}]", self.host),
+			Err(_) => self.host.clone(),
+		};
+		
+		let default = if self.tls { Port::Https } else { Port::Http };
+		if self.port == default.number() {
+			host
+		} else {
+			format!("{}:{}", host, self.port)
 		}
-	}	
-}
\ No newline at end of file
+	}
+}
+
+/// An address without the brackets around an IPv6 literal
+fn unbracket(address: &str) -> &str {
+	address.strip_prefix('[').and_then(|a| a.strip_suffix(']')).unwrap_or(address)
+}
+
+/// The TLS configuration used when an endpoint has none,
+/// trusting the webpki root certificates
+#[cfg(feature = "tls")]
+fn default_tls_config() -> Arc<rustls::ClientConfig> {
+	static CONFIG: OnceLock<Arc<rustls::ClientConfig>> = OnceLock::new();
+	
+	CONFIG.get_or_init(|| {
+		let mut roots = rustls::RootCertStore::empty();
+		roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+		
+		let provider = Arc::new(rustls::crypto::ring::default_provider());
+		let config = rustls::ClientConfig::builder_with_provider(provider)
+			.with_safe_default_protocol_versions()
+			.expect("default protocol versions are supported")
+			.with_root_certificates(roots)
+			.with_no_client_auth();
+		
+		Arc::new(config)
+	}).clone()
+}
+
+/// Errors during the handshake are certificate or protocol
+/// failures unless the connection itself failed
+#[cfg(feature = "tls")]
+fn handshake_failure(e: io::Error) -> OutboundError {
+	match e.kind() {
+		io::ErrorKind::InvalidData => OutboundError::Tls(e),
+		_ => OutboundError::from(e),
+	}
+}
+
+/// Blocking outbound requests to nodes
+///
+/// Connecting, sending the request and reading the whole
+/// response are each bounded by a timeout, so a node that
+/// accepts a connection but never answers cannot hold up the
+/// caller indefinitely.
+#[derive(Clone, Debug)]
+pub struct Outbound {
+	connect_timeout: Duration,
+	write_timeout: Duration,
+	read_timeout: Duration,
+	max_body: usize,
+}
+
+/// Connect to the first address of `address` that answers
+/// on `port` within `limit`
+pub(crate) fn connect_timeout(address: &str, port: u16, limit: Duration) -> Result<TcpStream, OutboundError> {
+	let addrs = match (address, port).to_socket_addrs() {
+		Ok(a) => a,
+		Err(e) => return Err(OutboundError::Connect(e)),
+	};
+	
+	let mut last = io::Error::new(io::ErrorKind::AddrNotAvailable, "no address to connect to");
+	for a in addrs {
+		match TcpStream::connect_timeout(&a, limit) {
+			Ok(s) => return Ok(s),
+			Err(e) => last = e,
+		}
+	}
+	
+	match last.kind() {
+		io::ErrorKind::TimedOut => Err(OutboundError::Timeout),
+		_ => Err(OutboundError::Connect(last)),
+	}
+}
+
+impl Default for Outbound {
+	fn default() -> Outbound {
+		Outbound::new()
+	}
+}
+
+impl Outbound {
+	/// Outbound requests with a five second connect timeout,
+	/// ten seconds each to send the request and read the
+	/// response, and response bodies of up to
+	/// `MAX_RESPONSE_BODY` bytes
+	pub fn new() -> Outbound {
+		Outbound {
+			connect_timeout: Duration::from_secs(5),
+			write_timeout: Duration::from_secs(10),
+			read_timeout: Duration::from_secs(10),
+			max_body: MAX_RESPONSE_BODY,
+		}
+	}
+	
+	pub fn with_connect_timeout(mut self, limit: Duration) -> Outbound {
+		self.connect_timeout = limit;
+		self
+	}
+	
+	/// Fail requests that take longer than `limit` to send
+	pub fn with_write_timeout(mut self, limit: Duration) -> Outbound {
+		self.write_timeout = limit;
+		self
+	}
+	
+	/// Fail requests whose whole response, including any TLS
+	/// handshake, takes longer than `limit` to read
+	pub fn with_read_timeout(mut self, limit: Duration) -> Outbound {
+		self.read_timeout = limit;
+		self
+	}
+	
+	/// Fail with `OutboundError::BodyTooLarge` on a response
+	/// body larger than `limit`
+	pub fn with_max_body(mut self, limit: usize) -> Outbound {
+		self.max_body = limit;
+		self
+	}
+	
+	/// Send bytes to port 80 of `address` in an HTTP request
+	/// and return the body of the response
+	pub fn request(&self, bytes: &[u8], address: &str, host: &str, path: &str) -> Result<Vec<u8>, OutboundError> {
+		self.request_endpoint(bytes, &Endpoint::new(address, host, path))
+	}
+	
+	/// Send bytes to an endpoint in an HTTP request and return
+	/// the body of the response
+	pub fn request_endpoint(&self, bytes: &[u8], endpoint: &Endpoint) -> Result<Vec<u8>, OutboundError> {
+		self.exchange(&endpoint.wrap(bytes), endpoint).map(ResponseParser::into_body)
+	}
+	
+	/// Connect to the first address of the endpoint that
+	/// answers within the connect timeout
+	fn connect(&self, endpoint: &Endpoint) -> Result<TcpStream, OutboundError> {
+		connect_timeout(&endpoint.address, endpoint.port, self.connect_timeout)
+	}
+	
+	/// Send a complete HTTP request to an endpoint and read
+	/// the response
+	fn exchange(&self, msg: &[u8], endpoint: &Endpoint) -> Result<ResponseParser, OutboundError> {
+		let mut stream = self.connect(endpoint)?;
+		stream.set_write_timeout(Some(self.write_timeout))?;
+		stream.set_read_timeout(Some(self.read_timeout))?;
+		let socket = stream.try_clone()?;
+		
+		if endpoint.tls {
+			return self.request_tls(stream, &socket, msg, endpoint)
+		}
+		
+		stream.write_all(msg)?;
+
+		self.read_parser(&mut stream, Some(&socket))
+	}
+	
+	#[cfg(feature = "tls")]
+	fn request_tls(&self, stream: TcpStream, socket: &TcpStream, msg: &[u8], endpoint: &Endpoint) -> Result<ResponseParser, OutboundError> {
+		let config = match endpoint.tls_config {
+			Some(ref c) => c.clone(),
+			None => default_tls_config(),
+		};
+		
+		let name = match rustls::pki_types::ServerName::try_from(unbracket(&endpoint.host).to_string()) {
+			Ok(n) => n,
+			Err(e) => return Err(OutboundError::Tls(io::Error::new(io::ErrorKind::InvalidInput, e))),
+		};
+		
+		let conn = match rustls::ClientConnection::new(config, name) {
+			Ok(c) => c,
+			Err(e) => return Err(OutboundError::Tls(io::Error::new(io::ErrorKind::InvalidData, e))),
+		};
+		
+		let mut tls = rustls::StreamOwned::new(conn, stream);
+		while tls.conn.is_handshaking() {
+			tls.conn.complete_io(&mut tls.sock).map_err(handshake_failure)?;
+		}
+		
+		tls.write_all(msg)?;
+		self.read_parser(&mut tls, Some(socket))
+	}
+	
+	#[cfg(not(feature = "tls"))]
+	fn request_tls(&self, _stream: TcpStream, _socket: &TcpStream, _msg: &[u8], _endpoint: &Endpoint) -> Result<ResponseParser, OutboundError> {
+		Err(OutboundError::Tls(io::Error::new(io::ErrorKind::Unsupported, "built without the tls feature")))
+	}
+	
+	/// Read an HTTP response from a stream and return the body
+	pub fn read_response<R: Read>(&self, stream: &mut R) -> Result<Vec<u8>, OutboundError> {
+		self.read_parser(stream, None).map(ResponseParser::into_body)
+	}
+	
+	/// Read a response, failing once the read timeout has
+	/// passed in total if `socket` is the stream's socket
+	fn read_parser<R: Read>(&self, stream: &mut R, socket: Option<&TcpStream>) -> Result<ResponseParser, OutboundError> {
+		let mut parser = ResponseParser::new().with_max_body(self.max_body);
+		let mut buf = [0;4096];
+		let deadline = Instant::now() + self.read_timeout;
+		
+		loop {
+			if let Some(s) = socket {
+				let left = deadline.saturating_duration_since(Instant::now());
+				if left.is_zero() {
+					return Err(OutboundError::Timeout)
+				}
+				s.set_read_timeout(Some(left))?;
+			}
+			
+			let size = match stream.read(&mut buf) {
+				Ok(s) => s,
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(OutboundError::from(e)),
+			};
+			
+			if size == 0 {
+				parser.finish()?;
+				break
+			}
+			
+			if parser.feed(&buf[..size])? {
+				break
+			}
+		}
+		
+		Ok(parser)
+	}
+	
+	/// Send a message to a node and parse its response
+	pub fn request_node(&self, message: &Message, node: &Node) -> Result<Message, OutboundError> {
+		self.request_message(message, &Endpoint::from_node(node))
+	}
+	
+	/// Send a message to an endpoint and parse its response
+	///
+	/// The message is sent in the encoding of the endpoint and
+	/// the response is read in the encoding of its
+	/// `Content-Type`. A response with a status other than 2xx
+	/// fails with `OutboundError::Status`.
+	pub fn request_message(&self, message: &Message, endpoint: &Endpoint) -> Result<Message, OutboundError> {
+		self.exchange(&endpoint.wrap_message(message), endpoint)?.message()
+	}
+}
@@ -0,0 +1,162 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `http_async`
+//!
+//! An asynchronous equivalent of `http::Outbound`, running on
+//! tokio. Enabled with the `async` feature.
+//!
+//! Connecting is bounded by one timeout and the rest of the
+//! exchange, from sending the request to reading the whole
+//! response, by another, so a gateway fanning out to many
+//! nodes is not held up by one that is unresponsive or that
+//! trickles its response.
+
+use std::io;
+use std::time::Duration;
+use std::future::Future;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, timeout_at, Instant};
+
+use crate::http::{Endpoint, OutboundError, ResponseParser};
+use crate::node::Node;
+use crate::protocol::Message;
+
+/// Asynchronous outbound requests to nodes
+#[derive(Clone, Debug)]
+pub struct AsyncOutbound {
+	port: u16,
+	connect_timeout: Duration,
+	read_timeout: Duration,
+}
+
+impl Default for AsyncOutbound {
+	fn default() -> AsyncOutbound {
+		AsyncOutbound::new()
+	}
+}
+
+async fn timed<F, T>(deadline: Instant, f: F) -> Result<T, OutboundError>
+	where F: Future<Output = std::io::Result<T>> {
+	match timeout_at(deadline, f).await {
+		Ok(Ok(r)) => Ok(r),
+		Ok(Err(e)) => Err(OutboundError::from(e)),
+		Err(_) => Err(OutboundError::Timeout),
+	}
+}
+
+fn tls_unsupported() -> OutboundError {
+	OutboundError::Tls(io::Error::new(io::ErrorKind::Unsupported, "TLS is not supported by AsyncOutbound"))
+}
+
+impl AsyncOutbound {
+	/// Outbound requests on the HTTP port with a five second
+	/// connect timeout and ten seconds to send the request and
+	/// read the response
+	pub fn new() -> AsyncOutbound {
+		AsyncOutbound {
+			port: 80,
+			connect_timeout: Duration::from_secs(5),
+			read_timeout: Duration::from_secs(10),
+		}
+	}
+
+	pub fn with_port(mut self, port: u16) -> AsyncOutbound {
+		self.port = port;
+		self
+	}
+
+	pub fn with_connect_timeout(mut self, limit: Duration) -> AsyncOutbound {
+		self.connect_timeout = limit;
+		self
+	}
+
+	/// Fail requests whose exchange, from sending the request
+	/// to reading the whole response, takes longer than `limit`
+	pub fn with_read_timeout(mut self, limit: Duration) -> AsyncOutbound {
+		self.read_timeout = limit;
+		self
+	}
+
+	/// Send bytes to a node in an HTTP request and return the
+	/// body of the response
+	pub async fn request(&self, bytes: &[u8], address: &str, host: &str, path: &str) -> Result<Vec<u8>, OutboundError> {
+		let endpoint = Endpoint::new(address, host, path).with_port(self.port);
+		self.request_endpoint(bytes, &endpoint).await
+	}
+
+	/// Send bytes to an endpoint in an HTTP request and return
+	/// the body of the response
+	///
+	/// TLS is not yet supported by the asynchronous client, so
+	/// a TLS endpoint fails with `OutboundError::Tls`
+	pub async fn request_endpoint(&self, bytes: &[u8], endpoint: &Endpoint) -> Result<Vec<u8>, OutboundError> {
+		self.exchange(&endpoint.wrap(bytes), endpoint).await.map(ResponseParser::into_body)
+	}
+
+	/// Send a complete HTTP request to an endpoint and read
+	/// the response
+	async fn exchange(&self, msg: &[u8], endpoint: &Endpoint) -> Result<ResponseParser, OutboundError> {
+		if endpoint.is_tls() {
+			return Err(tls_unsupported())
+		}
+
+		let connect = TcpStream::connect((endpoint.address(), endpoint.port()));
+		let mut stream = match timeout(self.connect_timeout, connect).await {
+			Ok(Ok(s)) => s,
+			Ok(Err(e)) => return Err(OutboundError::Connect(e)),
+			Err(_) => return Err(OutboundError::Timeout),
+		};
+
+		let deadline = Instant::now() + self.read_timeout;
+		timed(deadline, stream.write_all(msg)).await?;
+
+		self.read_response(&mut stream, deadline).await
+	}
+
+	/// Send a message to a node and parse its response
+	///
+	/// The node's port is used if its hostfield has one,
+	/// otherwise the default port of its scheme, as with
+	/// `Outbound::request_node`. TLS nodes fail with
+	/// `OutboundError::Tls` before anything is sent.
+	pub async fn request_node(&self, message: &Message, node: &Node) -> Result<Message, OutboundError> {
+		if node.is_tls() {
+			return Err(tls_unsupported())
+		}
+
+		self.request_message(message, &Endpoint::from_node(node)).await
+	}
+
+	/// Send a message to an endpoint in its encoding and parse
+	/// the response in the encoding of its `Content-Type`
+	///
+	/// A response with a status other than 2xx fails with
+	/// `OutboundError::Status`
+	pub async fn request_message(&self, message: &Message, endpoint: &Endpoint) -> Result<Message, OutboundError> {
+		self.exchange(&endpoint.wrap_message(message), endpoint).await?.message()
+	}
+
+	async fn read_response<S>(&self, stream: &mut S, deadline: Instant) -> Result<ResponseParser, OutboundError>
+		where S: AsyncRead + Unpin {
+		let mut parser = ResponseParser::new();
+		let mut buf = [0; 4096];
+
+		loop {
+			match timed(deadline, stream.read(&mut buf)).await? {
+				0 => {
+					parser.finish()?;
+					break
+				},
+				n => if parser.feed(&buf[..n])? {
+					break
+				},
+			}
+		}
+
+		Ok(parser)
+	}
+}
@@ -2,15 +2,25 @@
  * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
  * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
  */
-extern crate regex;
 
-
-//pub mod serialise;
+pub mod serialise;
 pub mod enums;
 pub mod spaces;
 pub mod node;
+pub mod names;
 pub mod uri;
 #[macro_use]
 pub mod formats;
 pub mod protocol;
-pub mod http;
\ No newline at end of file
+pub mod wire;
+pub mod http;
+pub mod stream;
+pub mod dvsp;
+#[cfg(feature = "async")]
+pub mod http_async;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+pub mod token;
+pub mod resolve;
+pub mod dispatch;
+pub mod health;
\ No newline at end of file
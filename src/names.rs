@@ -0,0 +1,162 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `names`
+//!
+//! Validators for the names that appear in the protocol.
+//!
+//! Springnames and the labels of hostnames and URI routes follow
+//! the label rules of RFC 1123: one to 63 letters, digits and
+//! hyphens, neither starting nor ending with a hyphen. A
+//! hostname is up to 253 characters of labels joined by dots.
+//!
+//! The validators only look at the bytes of the name, so they
+//! are cheap enough to run on every node of a large network.
+//!
+//! With the `idna` feature, names may also be given in Unicode.
+//! They are stored and sent in their ASCII form, in which each
+//! label is encoded as punycode, and `to_unicode` recovers the
+//! Unicode form for display.
+//!
+//! # Example
+//!
+//! ```
+//! use spring_dvs::names;
+//!
+//! assert!(names::is_springname("foo-bar"));
+//! assert!(names::is_hostname("spring.example.tld"));
+//! assert!(names::is_hostfield("https://spring.example.tld:8443/node"));
+//!
+//! assert!(!names::is_springname("-foo"));
+//! assert!(!names::is_hostname("foo..tld"));
+//! ```
+
+use std::borrow::Cow;
+use std::net::Ipv6Addr;
+
+/// The longest label of a hostname
+pub const MAX_LABEL: usize = 63;
+
+/// The longest hostname
+pub const MAX_HOSTNAME: usize = 253;
+
+/// Whether `s` is a single RFC 1123 label
+pub fn is_label(s: &str) -> bool {
+	let b = s.as_bytes();
+
+	match (b.first(), b.last()) {
+		(Some(&first), Some(&last)) => {
+			b.len() <= MAX_LABEL
+				&& first != b'-' && last != b'-'
+				&& b.iter().all(|c| c.is_ascii_alphanumeric() || *c == b'-')
+		},
+		_ => false,
+	}
+}
+
+/// Whether `s` is a springname, which is a single label
+pub fn is_springname(s: &str) -> bool {
+	is_label(s)
+}
+
+/// Whether `s` is a hostname of labels joined by dots
+pub fn is_hostname(s: &str) -> bool {
+	s.len() <= MAX_HOSTNAME && s.split('.').all(is_label)
+}
+
+/// Whether `s` is the path of a node on its host, without the
+/// leading `/`
+///
+/// The path is made of the unreserved characters of RFC 3986
+/// and `/`. It may be empty.
+pub fn is_hostpath(s: &str) -> bool {
+	s.bytes().all(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'.' | b'_' | b'~' | b'/'))
+}
+
+/// Whether `s` is a port number other than zero
+pub fn is_port(s: &str) -> bool {
+	!s.is_empty() && s.len() <= 5
+		&& s.bytes().all(|c| c.is_ascii_digit())
+		&& matches!(s.parse::<u16>(), Ok(p) if p > 0)
+}
+
+/// Whether `s` is the hostfield of a node
+///
+/// A hostfield is a hostname, or an IPv6 literal in brackets,
+/// optionally preceded by `http://` or `https://` and followed
+/// by a port and a hostpath:
+///
+/// `https://spring.example.tld:8443/node`
+pub fn is_hostfield(s: &str) -> bool {
+	let s = s.strip_prefix("https://").or_else(|| s.strip_prefix("http://")).unwrap_or(s);
+
+	let (authority, path) = match s.find('/') {
+		Some(i) => (&s[..i], &s[i+1..]),
+		None => (s, ""),
+	};
+
+	if !is_hostpath(path) {
+		return false
+	}
+
+	let port = match authority.strip_prefix('[') {
+		Some(rest) => match rest.split_once(']') {
+			Some((literal, port)) if literal.parse::<Ipv6Addr>().is_ok() => port,
+			_ => return false,
+		},
+		None => {
+			let (host, port) = match authority.find(':') {
+				Some(i) => authority.split_at(i),
+				None => (authority, ""),
+			};
+
+			if !is_hostname(host) {
+				return false
+			}
+			port
+		},
+	};
+
+	port.is_empty() || port.strip_prefix(':').is_some_and(is_port)
+}
+
+/// The ASCII form of a name, in which it is stored and sent
+///
+/// Names are lowercased. With the `idna` feature a name
+/// containing Unicode is mapped under UTS #46 and each label
+/// is encoded as punycode; this fails if the name cannot be
+/// encoded. Without the feature, names containing Unicode are
+/// returned as they are.
+///
+/// The result still has to be validated.
+pub fn to_ascii(name: &str) -> Option<Cow<'_, str>> {
+	if name.is_ascii() {
+		return match name.bytes().any(|c| c.is_ascii_uppercase()) {
+			true => Some(Cow::Owned(name.to_ascii_lowercase())),
+			false => Some(Cow::Borrowed(name)),
+		}
+	}
+
+	#[cfg(feature = "idna")]
+	return idna::domain_to_ascii(name).ok().map(Cow::Owned);
+
+	#[cfg(not(feature = "idna"))]
+	Some(Cow::Borrowed(name))
+}
+
+/// The Unicode form of a name for display
+///
+/// With the `idna` feature, labels encoded as punycode are
+/// decoded. A name that does not decode, and every name
+/// without the feature, is returned as it is.
+pub fn to_unicode(name: &str) -> Cow<'_, str> {
+	#[cfg(feature = "idna")]
+	if name.contains("xn--") {
+		if let (s, Ok(())) = idna::domain_to_unicode(name) {
+			return Cow::Owned(s)
+		}
+	}
+
+	Cow::Borrowed(name)
+}
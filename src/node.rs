@@ -2,18 +2,28 @@
  * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
  * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
  */
-pub use protocol::*;
+pub use crate::protocol::*;
+use crate::formats::canonical_ip;
+use crate::names::to_ascii;
 
 
 
 
 
+/// A node registered in a netspace
+///
+/// With the `serde` feature a node is an object of all its
+/// fields: `springname`, `hostname`, `hostpath`, `address`,
+/// `port`, `tls`, `service`, `state`, `role` and `key`.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
 	springname: String,
 	hostname: String,
 	hostpath: String,
-	address: String,
+	address: Option<IpAddr>,
+	port: Option<u16>,
+	tls: bool,
 	
 	service: NodeService,
 	state: NodeState,
@@ -35,21 +45,64 @@ pub fn nodevec_quadvec(v: Vec<Node>) -> Vec<NodeQuadFmt> {
 	out
 }
 
+/// Split the scheme and port from the host part of a hostfield
+///
+/// `https://host:port` gives `(host, Some(port), true)`. A
+/// port that is not a number is left as part of the host.
+fn split_authority(host: &str) -> (&str, Option<u16>, bool) {
+	let (host, tls) = if let Some(h) = host.strip_prefix("https://") {
+		(h, true)
+	} else if let Some(h) = host.strip_prefix("http://") {
+		(h, false)
+	} else {
+		(host, false)
+	};
+	
+	match host.rfind(':') {
+		Some(i) => match host[i+1..].parse::<u16>() {
+			Ok(p) => (&host[..i], Some(p), tls),
+			Err(_) => (host, None, tls),
+		},
+		None => (host, None, tls),
+	}
+}
+
 impl Node {
-	pub fn new( spring: &str, host: &str, address: &str, service: NodeService, state: NodeState, role: NodeRole, key: &str ) -> Self {
+	/// Create a node
+	///
+	/// The `host` is the node's hostfield, `hostname/hostpath`.
+	/// It may begin with `https://` for a node served over TLS
+	/// and the hostname may carry a port, as in
+	/// `https://spring.example.tld:8443/node`.
+	///
+	/// The `address` is `None` for a node whose address is not
+	/// known.
+	///
+	/// A Unicode springname is held in its ASCII form, as the
+	/// formats give it, so a node is stored and found under
+	/// one spelling.
+	pub fn new( spring: &str, host: &str, address: Option<IpAddr>, service: NodeService, state: NodeState, role: NodeRole, key: &str ) -> Self {
 		
+		let scheme_len = match host.find("://") {
+			Some(i) => i + 3,
+			None => 0,
+		};
 		
-		let (hostname,res) = match host.find("/") {
+		let (authority,res) = match host[scheme_len..].find("/") {
 			None => (host, "/"),
-			Some(p) => host.split_at(p)
+			Some(p) => host.split_at(scheme_len + p)
 		};
 		
+		let (hostname, port, tls) = split_authority(authority);
+		
 		Node {
-			springname: String::from(spring),
+			springname: to_ascii(spring).map_or_else(|| String::from(spring), |s| s.into_owned()),
 			hostname: String::from(hostname),
 			hostpath: String::from(&res[1..]),
 			
-			address: String::from(address),
+			address: address.map(canonical_ip),
+			port,
+			tls,
 			service: service,
 			state: state,
 			role: role,
@@ -59,11 +112,11 @@ impl Node {
 			
 	}
 	
-	pub fn from_registration(reg: &ContentRegistration, address: &str) -> Self {
+	pub fn from_registration(reg: &ContentRegistration, address: IpAddr) -> Self {
 		Node::new(
 			&reg.ndouble.spring,
 			&reg.ndouble.host,
-			&String::from(address),
+			Some(address),
 			reg.service,
 			NodeState::Disabled,
 			reg.role,
@@ -72,32 +125,30 @@ impl Node {
 	}
 	
 	pub fn from_str(s: &str) -> Result<Node,ParseFailure> {
-		match  s.find(":") {
-			Some(_) =>  {
-					let t : NodeInfoFmt = try!(NodeInfoFmt::from_str(s));
-					return Ok(Node::new(&t.spring, &t.host, &t.address, t.service,t.state, t.role,""))
-				},
-			_ => { }
-		};
+		let first = s.split(",").next().unwrap_or("");
+		if first.contains(':') {
+			let t : NodeInfoFmt = NodeInfoFmt::from_str(s)?;
+			return Ok(Node::new(&t.spring, &t.host, t.address, t.service,t.state, t.role,""))
+		}
 		
 		let  v : Vec<&str> = s.split(",").collect();
 		Ok(
 			match v.len() {
 				1 => {
-					let t : NodeSingleFmt = try!(NodeSingleFmt::from_str(s));
-					Node::new(&t.spring, "","0.0.0.0",NodeService::Undefined,NodeState::Unspecified, NodeRole::Undefined,"")
+					let t : NodeSingleFmt = NodeSingleFmt::from_str(s)?;
+					Node::new(&t.spring, "", None,NodeService::Undefined,NodeState::Unspecified, NodeRole::Undefined,"")
 				},
 				2 => {
-					let t : NodeDoubleFmt = try!(NodeDoubleFmt::from_str(s));
-					Node::new(&t.spring, &t.host,"0.0.0.0",NodeService::Undefined,NodeState::Unspecified, NodeRole::Undefined,"")
+					let t : NodeDoubleFmt = NodeDoubleFmt::from_str(s)?;
+					Node::new(&t.spring, &t.host, None,NodeService::Undefined,NodeState::Unspecified, NodeRole::Undefined,"")
 				},
 				3 => {
-					let t : NodeTripleFmt = try!(NodeTripleFmt::from_str(s));
-					Node::new(&t.spring, &t.host, &t.address,NodeService::Undefined,NodeState::Unspecified, NodeRole::Undefined,"")
+					let t : NodeTripleFmt = NodeTripleFmt::from_str(s)?;
+					Node::new(&t.spring, &t.host, Some(t.address),NodeService::Undefined,NodeState::Unspecified, NodeRole::Undefined,"")
 				},
 				4 => {
-					let t : NodeQuadFmt = try!(NodeQuadFmt::from_str(s));
-					Node::new(&t.spring, &t.host, &t.address, t.service,NodeState::Unspecified, NodeRole::Undefined,"")
+					let t : NodeQuadFmt = NodeQuadFmt::from_str(s)?;
+					Node::new(&t.spring, &t.host, Some(t.address), t.service,NodeState::Unspecified, NodeRole::Undefined,"")
 				},
 				
 				_ => return Err(ParseFailure::ConversionError)
@@ -106,7 +157,7 @@ impl Node {
 	}
 	
 	pub fn from_node_single(n: &NodeSingleFmt) -> Self {
-		Node::new(&n.spring, "", "", NodeService::Undefined, NodeState::Unspecified, NodeRole::Undefined,"")
+		Node::new(&n.spring, "", None, NodeService::Undefined, NodeState::Unspecified, NodeRole::Undefined,"")
 	}
 
 	pub fn to_node_single(&self) -> Option<NodeSingleFmt> {
@@ -118,7 +169,7 @@ impl Node {
 	}
 
 	pub fn from_node_double(n: &NodeDoubleFmt) -> Self {
-		Node::new(&n.spring, &n.host, "", NodeService::Undefined, NodeState::Unspecified, NodeRole::Undefined,"")
+		Node::new(&n.spring, &n.host, None, NodeService::Undefined, NodeState::Unspecified, NodeRole::Undefined,"")
 	}
 
 	pub fn to_node_double(&self) -> Option<NodeDoubleFmt> {
@@ -133,35 +184,35 @@ impl Node {
 	}
 	
 	pub fn from_node_triple(n: &NodeTripleFmt) -> Self {
-		Node::new(&n.spring, &n.host, &n.address, NodeService::Undefined, NodeState::Unspecified, NodeRole::Undefined,"")
+		Node::new(&n.spring, &n.host, Some(n.address), NodeService::Undefined, NodeState::Unspecified, NodeRole::Undefined,"")
 	}
 
 	pub fn to_node_triple(&self) -> Option<NodeTripleFmt> {
 		if self.springname.is_empty() { return None }
 		if self.hostname.is_empty() { return None }
-		if self.address.is_empty() { return None }
+		let address = self.address?;
 		
 		Some(NodeTripleFmt { 
 			spring: self.springname.clone(),
 			host: self.hostfield(),
-			address: self.address.clone(),
+			address,
 		})
 	}
 
 	pub fn from_node_quad(n: &NodeQuadFmt) -> Self {
-		Node::new(&n.spring, &n.host, &n.address, n.service, NodeState::Unspecified, NodeRole::Undefined,"")
+		Node::new(&n.spring, &n.host, Some(n.address), n.service, NodeState::Unspecified, NodeRole::Undefined,"")
 	}
 
 	pub fn to_node_quad(&self) -> Option<NodeQuadFmt> {
 
 		if self.springname.is_empty() { return None }
 		if self.hostname.is_empty() { return None }
-		if self.address.is_empty() { return None }
+		let address = self.address?;
 
 		Some(NodeQuadFmt { 
 			spring: self.springname.clone(),
 			host: self.hostfield(),
-			address: self.address.clone(),
+			address,
 			service: self.service,
 		})
 	}
@@ -170,12 +221,12 @@ impl Node {
 		
 		if self.springname.is_empty() { return None }
 		if self.hostname.is_empty() { return None }
-		if self.address.is_empty() { return None }
+		let address = self.address?;
 
 		Some(NodeInfoFmt {
 			spring: self.springname.clone(),
 			host: self.hostfield(),
-			address: self.address.clone(),
+			address: Some(address),
 			service: self.service,
 			state: self.state,
 			role: self.role,
@@ -189,7 +240,7 @@ impl Node {
 		match property {
 			
 			NodeProperty::Hostname => { info.host = self.hostfield() },
-			NodeProperty::Address => { info.address = self.address.clone() },
+			NodeProperty::Address => { info.address = self.address },
 			NodeProperty::Service(_) => { info.service = self.service },
 			NodeProperty::Role(_) => { info.role = self.role },
 			NodeProperty::State(_) => { info.state = self.state },
@@ -205,12 +256,32 @@ impl Node {
 	
 	pub fn hostfield(&self) -> String {
 		if self.hostpath.len() == 0  {
-			self.hostname.to_string()
+			self.authority()
 		} else {
-			format!("{}/{}", self.hostname, self.hostpath)
+			format!("{}/{}", self.authority(), self.hostpath)
 		} 
 	}
 	
+	/// The hostname with the scheme and port, if either is set
+	pub fn authority(&self) -> String {
+		let scheme = if self.tls { "https://" } else { "" };
+		match self.port {
+			Some(p) => format!("{}{}:{}", scheme, self.hostname, p),
+			None => format!("{}{}", scheme, self.hostname),
+		}
+	}
+	
+	/// The port the node's service is on, if it is not the
+	/// default for its scheme
+	pub fn port(&self) -> Option<u16> {
+		self.port
+	}
+	
+	/// Whether the node's service is reached over TLS
+	pub fn is_tls(&self) -> bool {
+		self.tls
+	}
+	
 	pub fn springname(&self) -> &str {
 		self.springname.as_ref()
 	}
@@ -219,8 +290,8 @@ impl Node {
 		self.hostname.as_ref()
 	}
 	
-	pub fn address(&self) -> &str {
-		self.address.as_ref()
+	pub fn address(&self) -> Option<IpAddr> {
+		self.address
 	}
 	
 	pub fn service(&self) -> NodeService {
@@ -260,8 +331,8 @@ impl Node {
 		self.key = String::from(key)
 	}
 	
-	pub fn update_address(&mut self, address: &str) {
-		self.address = address.to_string()
+	pub fn update_address(&mut self, address: Option<IpAddr>) {
+		self.address = address.map(canonical_ip)
 	}
 	
 	pub fn update_hostname(&mut self, hostname: &str) {
@@ -271,4 +342,12 @@ impl Node {
 	pub fn update_hostpash(&mut self, hostpath: &str) {
 		self.hostpath = hostpath.to_string()
 	}
+	
+	pub fn update_port(&mut self, port: Option<u16>) {
+		self.port = port
+	}
+	
+	pub fn update_tls(&mut self, tls: bool) {
+		self.tls = tls
+	}
 }
\ No newline at end of file
@@ -18,25 +18,52 @@
 //! 
 //! When a message is constructed -- it must be in a completely
 //! valid state; aggresive failure when parsing is necessary.
+//!
+//! # Serde
+//!
+//! With the `serde` feature the messages and their content
+//! implement `Serialize` and `Deserialize`. The JSON shapes are
+//! stable:
+//!
+//! * Roles, services, states and response codes are strings
+//!   of their `Display` form, such as `"org"` or `"200"`
+//! * URIs are strings of their `Display` form
+//! * `MessageContent`, `ResponseContent` and `InfoContent` are
+//!   `{"type": ..., "value": ...}` with the variant name in
+//!   snake case, and no value for variants that have none
+//! * `NodeProperty` is `{"name": ..., "value": ...}`
+//! * Everything else is an object of its public fields
+//!
+//! ```json
+//! {
+//!   "cmd": "update",
+//!   "content": {
+//!     "type": "update",
+//!     "value": {
+//!       "spring": "foo",
+//!       "property": { "name": "state", "value": "enabled" }
+//!     }
+//!   }
+//! }
+//! ```
 
 
 
 // ToDo:
-//  - resolve
 //  - request
  
 use std::str;
 use std::fmt;
 pub use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
-pub use enums::{ParseFailure,NodeRole,Response,NodeService,NodeState};
+pub use crate::enums::{ParseFailure,NodeRole,Response,NodeService,NodeState};
 
 
-pub use formats::{NodeSingleFmt,NodeDoubleFmt,NodeTripleFmt,NodeQuadFmt,NodeInfoFmt};
-use uri::Uri;
+pub use crate::formats::{NodeSingleFmt,NodeDoubleFmt,NodeTripleFmt,NodeQuadFmt,NodeInfoFmt};
+use crate::uri::Uri;
 
 pub type Ipv4 = [u8;4];
-pub type Ipv6 = [u8;6];
+pub type Ipv6 = [u8;16];
 pub type Bytes = Vec<u8>;
 
 #[macro_export]
@@ -74,26 +101,39 @@ pub fn ipaddr_str(addr: IpAddr) -> String {
 	}
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Port {
 	Dvsp,
 	Http,
+	Https,
 	Stream,
 }
 
+impl Port {
+	pub fn number(&self) -> u16 {
+		match *self {
+			Port::Dvsp => 55301,
+			Port::Http => 80,
+			Port::Https => 443,
+			Port::Stream => 55300,
+		}
+	}
+}
+
 impl fmt::Display for Port {
 
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		match self {
-			&Port::Dvsp => write!(f, "55301"),
-			&Port::Http => write!(f, "80"),
-			&Port::Stream => write!(f, "55300"),
-		}
+		write!(f, "{}", self.number())
 	}
 }	
 
 
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum CmdType {
 	Register, Unregister,
 	Info, Update,
@@ -134,6 +174,8 @@ impl fmt::Display for CmdType {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "name", content = "value", rename_all = "lowercase"))]
 pub enum NodeProperty {
 	All,
 	Hostname,
@@ -204,6 +246,8 @@ macro_rules!  msg_info_network{($e: expr) => (match msg_info!($e).info { InfoCon
 
 /// Variant defining first level content of the message
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value", rename_all = "snake_case"))]
 pub enum MessageContent {
 	/// There is no body of content
 	Empty,
@@ -272,6 +316,8 @@ impl fmt::Display for MessageContent {
 
 /// Variant defining second level info content
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value", rename_all = "lowercase"))]
 pub enum InfoContent {
 	Node(ContentNodeProperty),
 	Network
@@ -288,6 +334,8 @@ impl fmt::Display for InfoContent {
 
 /// Variant defining second level response content
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value", rename_all = "snake_case"))]
 pub enum ResponseContent {
 	/// There is no body of content
 	Empty,
@@ -304,23 +352,42 @@ pub enum ResponseContent {
 	/// Contains a service response
 	ServiceText(ContentServiceText),
 	
-	/// Header for a multipart service response
+	/// Contains a multipart service response
 	ServiceMulti(ContentServiceMulti),
+	
+	/// Contains the result of resolving a URI
+	Resolution(ContentResolution),
 }
 
 impl fmt::Display for ResponseContent {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		match self {
-			&ResponseContent::Empty => write!(f, ""),
-			&ResponseContent::NodeSingle(ref s) => write!(f, "{}", s),
-			&ResponseContent::Network(ref s) => write!(f, "{}", s),
-			&ResponseContent::NodeInfo(ref s) => write!(f, "{}", s),
-			&ResponseContent::ServiceText(ref s) => write!(f, "{}", s),
-			&ResponseContent::ServiceMulti(_) => write!(f, ""),
+		match *self {
+			ResponseContent::Empty => write!(f, ""),
+			ResponseContent::NodeSingle(ref s) => write!(f, "{}", s),
+			ResponseContent::Network(ref s) => write!(f, "{}", s),
+			ResponseContent::NodeInfo(ref s) => write!(f, "{}", s),
+			ResponseContent::ServiceText(ref s) => write!(f, "{}", s),
+			ResponseContent::ServiceMulti(ref s) => write!(f, "{}", s),
+			ResponseContent::Resolution(ref s) => write!(f, "{}", s),
 			
 		}
 	}
 }
+
+impl ResponseContent {
+	/// The type written before the content in a response, or
+	/// `None` if the content is not written
+	fn type_tag(&self) -> Option<&'static str> {
+		match *self {
+			ResponseContent::Network(_) => Some("network"),
+			ResponseContent::NodeInfo(_) => Some("node"),
+			ResponseContent::ServiceText(_) => Some("service/text"),
+			ResponseContent::ServiceMulti(_) => Some("service/multi"),
+			ResponseContent::Resolution(_) => Some("resolve"),
+			_ => None,
+		}
+	}
+}
 #[macro_export]
 macro_rules!  msg_response_nodeinfo{($e: expr) => (match msg_response!($e).content { ResponseContent::NodeInfo(ref r) => r, _ => panic!("msg_response_nodeinfo -- Unexpected value: {:?}", $e) }) }
 #[macro_export]
@@ -328,6 +395,10 @@ macro_rules!  msg_response_network{($e: expr) => (match msg_response!($e).conten
 #[macro_export]
 macro_rules!  msg_response_single{($e: expr) => (match msg_response!($e).content { ResponseContent::NodeSingle(ref r) => r, _ => panic!("msg_response_single -- Unexpected value: {:?}", $e) }) }
 #[macro_export]
+macro_rules!  msg_response_resolution{($e: expr) => (match msg_response!($e).content { ResponseContent::Resolution(ref r) => r, _ => panic!("msg_response_resolution -- Unexpected value: {:?}", $e) }) }
+#[macro_export]
+macro_rules!  msg_response_servicemulti{($e: expr) => (match msg_response!($e).content { ResponseContent::ServiceMulti(ref r) => r, _ => panic!("msg_response_servicemulti -- Unexpected value: {:?}", $e) }) }
+#[macro_export]
 macro_rules!  msg_response_servicetext{($e: expr) => (match msg_response!($e).content { ResponseContent::ServiceText(ref r) => r, _ => panic!("msg_response_service -- Unexpected value: {:?}", $e) }) }
 /// Empty content type
 pub struct Empty;
@@ -341,6 +412,7 @@ pub trait ProtocolObject : Sized {
 
 /// Representing a single message within the protocol
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
 	/// The command held in the message
 	pub cmd: CmdType,
@@ -382,13 +454,13 @@ impl Message {
 	fn parse_content(bytes: &[u8], mtype: CmdType) -> Result<MessageContent, ParseFailure> {
 		
 		match mtype {
-			CmdType::Register => Ok(MessageContent::Registration(try!(ContentRegistration::from_bytes(&bytes)))),
-			CmdType::Unregister => Ok(MessageContent::NodeSingle(try!(ContentNodeSingle::from_bytes(&bytes)))),
-			CmdType::Response => Ok(MessageContent::Response(try!(ContentResponse::from_bytes(&bytes)))),
-			CmdType::Info => Ok(MessageContent::Info(try!(ContentInfoRequest::from_bytes(&bytes)))),
-			CmdType::Update => Ok(MessageContent::Update(try!(ContentNodeProperty::from_bytes(&bytes)))),
-			CmdType::Resolve => Ok(MessageContent::Resolve(try!(ContentUri::from_bytes(&bytes)))),
-			CmdType::Service => Ok(MessageContent::Service(try!(ContentUri::from_bytes(&bytes)))),
+			CmdType::Register => Ok(MessageContent::Registration(ContentRegistration::from_bytes(bytes)?)),
+			CmdType::Unregister => Ok(MessageContent::NodeSingle(ContentNodeSingle::from_bytes(bytes)?)),
+			CmdType::Response => Ok(MessageContent::Response(ContentResponse::from_bytes(bytes)?)),
+			CmdType::Info => Ok(MessageContent::Info(ContentInfoRequest::from_bytes(bytes)?)),
+			CmdType::Update => Ok(MessageContent::Update(ContentNodeProperty::from_bytes(bytes)?)),
+			CmdType::Resolve => Ok(MessageContent::Resolve(ContentUri::from_bytes(bytes)?)),
+			CmdType::Service => Ok(MessageContent::Service(ContentUri::from_bytes(bytes)?)),
 		}
 		
 	}
@@ -397,14 +469,14 @@ impl Message {
 impl ProtocolObject for Message {
 	fn from_bytes(bytes: &[u8]) -> Result<Self, ParseFailure> {
 
-		let (index, cmd) = try!(Message::next(bytes));
+		let (index, cmd) = Message::next(bytes)?;
 		let mtype = match CmdType::from_str(cmd) {
 			Some(c) => c, None => return Err(ParseFailure::InvalidCommand) 
 		};
 		
 		let content = match mtype {
-			CmdType::Response => try!(Message::parse_content(&bytes, mtype)),
-			_ => try!(Message::parse_content(&bytes[index..], mtype)),
+			CmdType::Response => Message::parse_content(bytes, mtype)?,
+			_ => Message::parse_content(&bytes[index..], mtype)?,
 		};
 
 		Ok(Message{
@@ -429,6 +501,7 @@ impl ProtocolObject for Message {
 }
 
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentRegistration {
 	pub ndouble: NodeDoubleFmt,
 	pub role: NodeRole,
@@ -471,7 +544,7 @@ impl ProtocolObject for ContentRegistration {
 		
 		Ok(
 			ContentRegistration {
-				ndouble: try!(NodeDoubleFmt::from_str(parts[0])),
+				ndouble: NodeDoubleFmt::from_str(parts[0])?,
 				role: role,
 				service: service,
 				key: key,
@@ -492,6 +565,7 @@ impl fmt::Display for ContentRegistration {
 }
 
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentNodeTriple {
 	pub ntriple: NodeTripleFmt
 }
@@ -510,7 +584,7 @@ impl ProtocolObject for ContentNodeTriple {
 		let s = utf8_from!(bytes);
 		
 		Ok( ContentNodeTriple { 
-			ntriple: try!(NodeTripleFmt::from_str(s))	 
+			ntriple: NodeTripleFmt::from_str(s)?	 
 			} )
 	}
 
@@ -520,6 +594,7 @@ impl ProtocolObject for ContentNodeTriple {
 }
 
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentNodeSingle {
 	pub nsingle: NodeSingleFmt
 }
@@ -541,7 +616,7 @@ impl ProtocolObject for ContentNodeSingle {
 		};
 		
 		Ok( ContentNodeSingle { 
-			nsingle: try!(NodeSingleFmt::from_str(s))	 
+			nsingle: NodeSingleFmt::from_str(s)?	 
 			} )
 	}
 
@@ -558,6 +633,7 @@ impl fmt::Display for ContentNodeSingle {
 }
 
 #[derive(Clone,Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentNetwork {
 	pub network: Vec<NodeQuadFmt>
 }
@@ -580,7 +656,7 @@ impl ProtocolObject for ContentNetwork {
 		let mut v: Vec<NodeQuadFmt> = Vec::new();
 		for sq in parts {
 			if sq.len() == 0 { continue }
-			v.push(try!(NodeQuadFmt::from_str(sq)))
+			v.push(NodeQuadFmt::from_str(sq)?)
 		}
 		
 		Ok(ContentNetwork {
@@ -606,6 +682,7 @@ impl fmt::Display for ContentNetwork {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentNodeInfo {
 	pub info: NodeInfoFmt,
 	
@@ -626,7 +703,7 @@ impl ProtocolObject for ContentNodeInfo {
 		let s = utf8_from!(bytes);
 		
 		Ok(ContentNodeInfo {
-			info: try!(NodeInfoFmt::from_str(s))
+			info: NodeInfoFmt::from_str(s)?
 		})
 	}
 
@@ -642,6 +719,7 @@ impl fmt::Display for ContentNodeInfo {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentResponse {
 	pub code: Response,
 	pub len: u32,
@@ -653,21 +731,50 @@ impl ContentResponse {
 		format!("{}", self)
 	}
 	
-	pub fn new_service_text(msg: &str) -> ContentResponse {
+	/// The length of the content as it is written in a response,
+	/// which counts its type and the space after it
+	fn content_len(content: &ResponseContent) -> u32 {
+		match content.type_tag() {
+			Some(t) => (t.len() + 1 + content.to_string().len()) as u32,
+			None => 0,
+		}
+	}
+	
+	fn new_ok(content: ResponseContent) -> ContentResponse {
 		ContentResponse {
 			code: Response::Ok,
-			len: msg.len() as u32,
-			content: ResponseContent::ServiceText(ContentServiceText::new(msg))
+			len: ContentResponse::content_len(&content),
+			content
 		}
 	}
 	
+	pub fn new_service_text(msg: &str) -> ContentResponse {
+		ContentResponse::new_ok(ResponseContent::ServiceText(ContentServiceText::new(msg)))
+	}
+	
+	pub fn new_service_multi(multi: ContentServiceMulti) -> ContentResponse {
+		ContentResponse::new_ok(ResponseContent::ServiceMulti(multi))
+	}
+	
 	pub fn new_empty_response(code: Response) -> ContentResponse {
 		ContentResponse {
 			code: code,
 			len: 0,
 			content: ResponseContent::Empty
 		}
-	}	
+	}
+	
+	pub fn new_node_info(info: NodeInfoFmt) -> ContentResponse {
+		ContentResponse::new_ok(ResponseContent::NodeInfo(ContentNodeInfo::new(info)))
+	}
+	
+	pub fn new_network(network: Vec<NodeQuadFmt>) -> ContentResponse {
+		ContentResponse::new_ok(ResponseContent::Network(ContentNetwork { network }))
+	}
+	
+	pub fn new_resolution(resolution: ContentResolution) -> ContentResponse {
+		ContentResponse::new_ok(ResponseContent::Resolution(resolution))
+	}
 }
 
 pub fn generate_response_empty_code(code: Response) -> Message {
@@ -677,6 +784,27 @@ pub fn generate_response_empty_code(code: Response) -> Message {
 	)	
 }
 
+pub fn generate_response_node_info(info: NodeInfoFmt) -> Message {
+	Message::new(
+		CmdType::Response,
+		MessageContent::Response(ContentResponse::new_node_info(info))
+	)
+}
+
+pub fn generate_response_network(network: Vec<NodeQuadFmt>) -> Message {
+	Message::new(
+		CmdType::Response,
+		MessageContent::Response(ContentResponse::new_network(network))
+	)
+}
+
+pub fn generate_response_resolution(resolution: ContentResolution) -> Message {
+	Message::new(
+		CmdType::Response,
+		MessageContent::Response(ContentResponse::new_resolution(resolution))
+	)
+}
+
 impl ProtocolObject for ContentResponse {
 	fn from_bytes(bytes: &[u8]) -> Result<Self, ParseFailure> {
 		
@@ -685,13 +813,13 @@ impl ProtocolObject for ContentResponse {
 		let s = utf8_from!(bytes);
 		
 
-		let code = opt_parsefail!(Response::from_str(&s[0..3]));
+		let code = opt_parsefail!(Response::from_str(opt_parsefail!(s.get(0..3))));
 		let mut len : u32 = 0;
 		let mut content = ResponseContent::Empty;
 		
 		if s.len() > 3 {
 
-			let st = String::from(&s[4..]);
+			let st = String::from(opt_parsefail!(s.get(4..)));
 			let index = opt_parsefail!(st.find(" "));
 			
 
@@ -705,9 +833,11 @@ impl ProtocolObject for ContentResponse {
 			
 
 			content = match t {
-				"network" => ResponseContent::Network(try!(ContentNetwork::from_bytes(&r[1..].as_bytes()))),
-				"node" => ResponseContent::NodeInfo(try!(ContentNodeInfo::from_bytes(&r[1..].as_bytes()))),
-				"service/text" => ResponseContent::ServiceText(try!(ContentServiceText::from_bytes(&r[1..].as_bytes()))),
+				"network" => ResponseContent::Network(ContentNetwork::from_bytes(&r.as_bytes()[1..])?),
+				"node" => ResponseContent::NodeInfo(ContentNodeInfo::from_bytes(&r.as_bytes()[1..])?),
+				"service/text" => ResponseContent::ServiceText(ContentServiceText::from_bytes(&r.as_bytes()[1..])?),
+				"service/multi" => ResponseContent::ServiceMulti(ContentServiceMulti::from_bytes(&r.as_bytes()[1..])?),
+				"resolve" => ResponseContent::Resolution(ContentResolution::from_bytes(&r.as_bytes()[1..])?),
 				_ => return Err(ParseFailure::InvalidContentFormat),
 			}
 		}
@@ -727,29 +857,15 @@ impl ProtocolObject for ContentResponse {
 
 impl fmt::Display for ContentResponse {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		let d = self.content.clone();
-		
-		match d {
-			ResponseContent::Network(s) => {
-				let content = format!("{}", s);
-				write!(f, "{} {} network {}", self.code, (content.len()+8), content)
-			},
-			ResponseContent::NodeInfo(s) => {
-				let content = format!("{}", s);
-				write!(f, "{} {} node {}", self.code, (content.len()+5), content)
-			},
-			ResponseContent::ServiceText(s) => {
-				let content = format!("{}", s);
-				write!(f, "{} {} service/text {}", self.code, (content.len()+13), content)
-			},
-			_ =>  write!(f, "{}", self.code),
-			 
+		match self.content.type_tag() {
+			Some(t) => write!(f, "{} {} {} {}", self.code, ContentResponse::content_len(&self.content), t, self.content),
+			None => write!(f, "{}", self.code),
 		}
-		
 	}
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentInfoRequest {
 	pub info: InfoContent,
 }
@@ -779,7 +895,7 @@ impl ProtocolObject for ContentInfoRequest {
 			
 			let info = match t {
 				"network" => InfoContent::Network,
-				"node" => InfoContent::Node(try!(ContentNodeProperty::from_bytes(nx.as_bytes()))),
+				"node" => InfoContent::Node(ContentNodeProperty::from_bytes(nx.as_bytes())?),
 				_ => return Err(ParseFailure::InvalidContentFormat)
 			};
 			Ok(
@@ -806,6 +922,7 @@ impl fmt::Display for ContentInfoRequest {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentNodeProperty {
 	pub spring: String,
 	pub property: NodeProperty,
@@ -860,6 +977,7 @@ impl fmt::Display for ContentNodeProperty {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentUri {
 	pub uri: Uri
 }
@@ -907,6 +1025,7 @@ pub fn generate_message_service(uri: &str) -> Option<Message> {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentServiceText {
 	pub content: String,
 }
@@ -946,11 +1065,250 @@ pub fn generate_response_service_text(msg: &str) -> Message {
 	)
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct ContentServiceMulti;
+/// A service response aggregated from several nodes
+///
+/// Each part is sent as a `Chunk` coded service text
+/// response, with the sequence terminated by `Eot`. The
+/// length field of each chunk delimits its content so the
+/// parts can contain any text.
+///
+/// Text Format: 201 len service/text text 201 len service/text text 202
+#[derive(Clone, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentServiceMulti {
+	pub parts: Vec<ContentServiceText>,
+}
 
 impl ContentServiceMulti {
 	pub fn new() -> ContentServiceMulti {
-		ContentServiceMulti {}
+		ContentServiceMulti {
+			parts: Vec::new()
+		}
+	}
+	
+	/// Parse a single chunk at the start of `bytes`, returning
+	/// the part and the number of bytes consumed
+	fn next_chunk(bytes: &[u8]) -> Result<(ContentServiceText, usize), ParseFailure> {
+		let (i, code) = Message::next(bytes)?;
+		if Response::from_str(code) != Some(Response::Chunk) {
+			return Err(ParseFailure::InvalidContentFormat)
+		}
+		
+		let (j, len) = Message::next(&bytes[i..])?;
+		let len : usize = res_parsefail!(len.parse());
+		
+		let start = i + j;
+		let end = opt_parsefail!(start.checked_add(len));
+		if end > bytes.len() {
+			return Err(ParseFailure::InvalidContentFormat)
+		}
+		
+		let chunk = &bytes[start..end];
+		if !chunk.starts_with(b"service/text ") {
+			return Err(ParseFailure::InvalidContentFormat)
+		}
+		
+		Ok((ContentServiceText::from_bytes(&chunk[13..])?, end))
+	}
+}
+
+impl ProtocolObject for ContentServiceMulti {
+	fn from_bytes(bytes: &[u8]) -> Result<Self, ParseFailure> {
+		let mut parts = Vec::new();
+		let mut index = 0;
+		
+		loop {
+			while index < bytes.len() && bytes[index] == b' ' {
+				index += 1;
+			}
+			
+			let rest = &bytes[index..];
+			if rest.starts_with(b"202") && rest[3..].iter().all(|b| b.is_ascii_whitespace()) {
+				break
+			}
+			
+			if rest.is_empty() {
+				return Err(ParseFailure::InvalidContentFormat)
+			}
+			
+			let (part, used) = ContentServiceMulti::next_chunk(rest)?;
+			parts.push(part);
+			index += used;
+		}
+		
+		Ok(ContentServiceMulti {
+			parts
+		})
 	}
-}
\ No newline at end of file
+	
+	fn to_bytes(&self) -> Vec<u8> {
+		Vec::from(self.to_string().as_bytes())
+	}
+}
+
+impl fmt::Display for ContentServiceMulti {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for part in &self.parts {
+			write!(f, "{} {} service/text {} ", Response::Chunk, part.content.len()+13, part)?;
+		}
+		
+		write!(f, "{}", Response::Eot)
+	}
+}
+
+/// Assembles a multipart service response from the service
+/// responses of several nodes
+#[derive(Clone, Debug, Default)]
+pub struct ServiceMultiBuilder {
+	multi: ContentServiceMulti,
+}
+
+impl ServiceMultiBuilder {
+	pub fn new() -> ServiceMultiBuilder {
+		ServiceMultiBuilder {
+			multi: ContentServiceMulti::new()
+		}
+	}
+	
+	/// Add a single part
+	pub fn add(&mut self, part: ContentServiceText) -> &mut ServiceMultiBuilder {
+		self.multi.parts.push(part);
+		self
+	}
+	
+	/// Add the service content of a node's response
+	///
+	/// Text parts are added and multipart responses have all
+	/// their parts added. Returns false if the response has
+	/// no service content, in which case nothing is added.
+	pub fn add_response(&mut self, response: &ContentResponse) -> bool {
+		if response.code != Response::Ok {
+			return false
+		}
+		
+		match response.content {
+			ResponseContent::ServiceText(ref t) => {
+				self.multi.parts.push(t.clone());
+				true
+			},
+			ResponseContent::ServiceMulti(ref m) => {
+				self.multi.parts.extend(m.parts.iter().cloned());
+				true
+			},
+			_ => false
+		}
+	}
+	
+	/// The number of parts added so far
+	pub fn len(&self) -> usize {
+		self.multi.parts.len()
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.multi.parts.is_empty()
+	}
+	
+	pub fn build(&self) -> ContentServiceMulti {
+		self.multi.clone()
+	}
+	
+	/// Build the complete response content
+	pub fn build_response(&self) -> ContentResponse {
+		ContentResponse::new_service_multi(self.build())
+	}
+}
+
+pub fn generate_response_service_multi(parts: Vec<ContentServiceText>) -> Message {
+	Message::new(
+		CmdType::Response,
+		MessageContent::Response(ContentResponse::new_service_multi(ContentServiceMulti { parts }))
+	)
+}
+
+/// Whether a resolution is the requested node or a referral
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ResolutionKind {
+	/// The nodes are the target of the URI
+	Node,
+	
+	/// The nodes are geosub root nodes that can continue
+	/// the resolution
+	Referral,
+}
+
+impl fmt::Display for ResolutionKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ResolutionKind::Node => write!(f, "node"),
+			ResolutionKind::Referral => write!(f, "referral"),
+		}
+	}
+}
+
+/// The result of resolving a URI
+///
+/// Text Format: kind spring,host,address,service;...
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentResolution {
+	pub kind: ResolutionKind,
+	pub network: Vec<NodeQuadFmt>,
+}
+
+impl ContentResolution {
+	pub fn new(kind: ResolutionKind, network: Vec<NodeQuadFmt>) -> ContentResolution {
+		ContentResolution {
+			kind,
+			network,
+		}
+	}
+}
+
+impl ProtocolObject for ContentResolution {
+	fn from_bytes(bytes: &[u8]) -> Result<Self, ParseFailure> {
+		
+		if bytes.is_empty() { return Err(ParseFailure::InvalidContentFormat) }
+		
+		let s = utf8_from!(bytes);
+		
+		let (k, r) = match s.find(' ') {
+			Some(i) => (&s[..i], &s[i+1..]),
+			None => (s, "")
+		};
+		
+		let kind = match k {
+			"node" => ResolutionKind::Node,
+			"referral" => ResolutionKind::Referral,
+			_ => return Err(ParseFailure::InvalidContentFormat)
+		};
+		
+		let mut v: Vec<NodeQuadFmt> = Vec::new();
+		for sq in r.split(';') {
+			if sq.is_empty() { continue }
+			v.push(NodeQuadFmt::from_str(sq)?)
+		}
+		
+		Ok(ContentResolution {
+			kind,
+			network: v
+		})
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		Vec::from(self.to_string().as_bytes())
+	}
+}
+
+impl fmt::Display for ContentResolution {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} ", self.kind)?;
+		
+		for n in &self.network {
+			write!(f, "{};", n)?;
+		}
+		
+		Ok(())
+	}
+}
@@ -0,0 +1,113 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `resolve`
+//!
+//! Resolves the GSN route of a `spring://` URI against a
+//! `Netspace`.
+//!
+//! A route such as `spring://cci.esusx.uk` is read from the
+//! most specific label outwards: `cci` is the springname of
+//! the node, `esusx` is the geosub it is registered in and
+//! `uk` is the GTN.
+//!
+//! * If the geosub is the one served by the local netspace,
+//!   the springname is resolved against the local GSN.
+//! * If the geosub is elsewhere, the springname is resolved
+//!   against the known root nodes of that geosub. Failing that
+//!   the root nodes of the geosub are returned as a referral
+//!   so the requester can continue the resolution there.
+//! * A route that names only a geosub within a GTN, such as
+//!   `spring://esusx.uk`, resolves to a referral to the
+//!   geosub's root nodes.
+
+use crate::node::{Node, nodevec_quadvec};
+use crate::protocol::{ContentResolution, ResolutionKind};
+use crate::spaces::{Netspace, NetspaceFailure};
+use crate::uri::Uri;
+
+/// The nodes a URI resolved to
+#[derive(Debug, Clone)]
+pub struct Resolution {
+	pub kind: ResolutionKind,
+	pub nodes: Vec<Node>,
+}
+
+impl Resolution {
+	fn node(n: Node) -> Resolution {
+		Resolution {
+			kind: ResolutionKind::Node,
+			nodes: vec![n],
+		}
+	}
+
+	fn referral(nodes: Vec<Node>) -> Result<Resolution, NetspaceFailure> {
+		if nodes.is_empty() {
+			return Err(NetspaceFailure::NodeNotFound)
+		}
+
+		Ok(Resolution {
+			kind: ResolutionKind::Referral,
+			nodes,
+		})
+	}
+
+	/// Convert into the protocol content for a response
+	pub fn to_content(&self) -> ContentResolution {
+		ContentResolution::new(self.kind, nodevec_quadvec(self.nodes.clone()))
+	}
+}
+
+/// Resolves URIs against a netspace serving the geosub `geosub`
+pub struct Resolver<'a, N: Netspace + 'a> {
+	nio: &'a N,
+	geosub: String,
+}
+
+impl<'a, N: Netspace + 'a> Resolver<'a, N> {
+	pub fn new(nio: &'a N, geosub: &str) -> Resolver<'a, N> {
+		Resolver {
+			nio,
+			geosub: String::from(geosub),
+		}
+	}
+
+	/// Resolve the route of a URI
+	///
+	/// Fails with `NodeNotFound` when the route cannot be
+	/// resolved or referred any further, including routes with
+	/// more than a springname and geosub before the GTN
+	pub fn resolve(&self, uri: &Uri) -> Result<Resolution, NetspaceFailure> {
+		let route = uri.route();
+
+		// Drop the GTN from the route
+		let labels = match route.last() {
+			Some(l) if !uri.gtn().is_empty() && l == uri.gtn() => &route[..route.len()-1],
+			_ => &route[..],
+		};
+
+		match labels.len() {
+			0 => Err(NetspaceFailure::NodeNotFound),
+			1 if !uri.gtn().is_empty() => {
+				Resolution::referral(self.nio.gtn_geosub_root_nodes(&labels[0]))
+			},
+			1 => self.resolve_local(&labels[0]),
+			2 if labels[1] == self.geosub => self.resolve_local(&labels[0]),
+			2 => self.resolve_remote(&labels[0], &labels[1]),
+			_ => Err(NetspaceFailure::NodeNotFound),
+		}
+	}
+
+	fn resolve_local(&self, spring: &str) -> Result<Resolution, NetspaceFailure> {
+		self.nio.gsn_node_by_springname(spring).map(Resolution::node)
+	}
+
+	fn resolve_remote(&self, spring: &str, gsn: &str) -> Result<Resolution, NetspaceFailure> {
+		match self.nio.gtn_geosub_node_by_springname(spring, gsn) {
+			Ok(n) => Ok(Resolution::node(n)),
+			Err(NetspaceFailure::NodeNotFound) => Resolution::referral(self.nio.gtn_geosub_root_nodes(gsn)),
+			Err(e) => Err(e),
+		}
+	}
+}
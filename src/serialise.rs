@@ -2,8 +2,7 @@
  * Author: 	Charlie Fyvie-Gauld (cfg@zunautica.org)
  * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
  */
-use std::mem::transmute;
-use ::enums::Failure;
+use crate::enums::Failure;
 
 pub trait NetSerial : Sized {
 	fn serialise(&self) -> Vec<u8>;
@@ -12,25 +11,23 @@ pub trait NetSerial : Sized {
 }
 
 pub fn push_bytes(v: &mut Vec<u8>, bytes: &[u8]) {
-	for b in bytes {
-		v.push(*b)
-	}
+	v.extend_from_slice(bytes)
 }
 
 pub fn u32_transmute_be_arr(a: &[u8]) -> u32 {
-	unsafe { transmute::<[u8;4], u32>([a[3], a[2], a[1], a[0]]) } 
+	u32::from_be_bytes(byte_slice_4array(a))
 }
 
 pub fn u32_transmute_le_arr(a: &[u8]) -> u32 {
-	unsafe { transmute::<[u8;4], u32>([a[0], a[1], a[2], a[3]]) } 
+	u32::from_le_bytes(byte_slice_4array(a))
 }
 
 pub fn array_transmute_be_u32(d: u32) -> [u8;4] {
-	unsafe { transmute(d.to_be()) }
+	d.to_be_bytes()
 }
 
 pub fn array_transmute_le_u32(d: u32) -> [u8;4] {
-	unsafe { transmute(d.to_le()) }
+	d.to_le_bytes()
 }
 
 
@@ -39,34 +36,31 @@ pub fn byte_slice_4array(a: &[u8]) -> [u8;4] {
 }
 
 pub fn deserialise_bool(byte: u8) -> bool {
-	match byte {
-		0 => false,
-		_ => true
-	}
+	byte != 0
 }
 
 pub fn hex_str_to_byte(src: &[u8]) -> Option<u8> {
-	
+
 	let mut val = 0;
 	let mut factor = 16;
-	for i in 0 .. 2 {
-		val +=  match src[i] {
-			v @	48 ... 57 => (v - 48) * factor,
-			v @ 65 ... 70 => (v - 55) * factor,
-			v @ 97 ... 102 => (v - 87) * factor,
-			_ => return None,   
+	for b in &src[..2] {
+		val +=  match *b {
+			v @ 48 ..= 57 => (v - 48) * factor,
+			v @ 65 ..= 70 => (v - 55) * factor,
+			v @ 97 ..= 102 => (v - 87) * factor,
+			_ => return None,
 		};
 		 factor >>= 4;
 	}
 
 	Some(val)
-	
+
 }
 
-pub fn bin_to_hex(src: &Vec<u8>) -> String {
+pub fn bin_to_hex(src: &[u8]) -> String {
 	let mut s = String::new();
 	for byte in src {
 		s.push_str( format!("{:0>2x}", byte).as_ref() )
 	}
 	s
-}
\ No newline at end of file
+}
@@ -3,9 +3,18 @@
  * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
  */
 
-pub use protocol::*;
-pub use ::node::Node;
-use ::enums::{Success};
+pub use crate::protocol::*;
+pub use crate::node::Node;
+use crate::enums::{Success};
+
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+pub mod conformance;
+
+pub use self::memory::MemoryNetspace;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::{SqliteNetspace, SCHEMA_VERSION};
 
 #[derive(Debug,PartialEq)]
 pub enum NetspaceFailure {
@@ -15,9 +24,17 @@ pub enum NetspaceFailure {
 }
 
 
+/// A store of the nodes of a geosub and the root nodes of
+/// other geosubs
+///
+/// Springnames are stored in the ASCII form that `Node` and
+/// the formats hold them in, and may be looked up in either
+/// form.
 pub trait Netspace {
 	fn gsn_nodes(&self) -> Vec<Node>;
-	fn gsn_nodes_by_address(&self, address: &str) -> Vec<Node>;
+	fn gsn_nodes_by_address(&self, address: IpAddr) -> Vec<Node>;
+	/// Nodes whose role is exactly `types`, so hybrid nodes
+	/// are only listed for `NodeRole::Hybrid`
 	fn gsn_nodes_by_type(&self, types: NodeRole) -> Vec<Node>;
 	fn gsn_nodes_by_state(&self, state: NodeState) -> Vec<Node>;
 	
@@ -44,8 +61,9 @@ pub trait Netspace {
 	fn gtn_geosub_unregister_node(&self, node: &Node, gsn: &str) -> Result<Success,NetspaceFailure>;
 	
 	fn gsn_check_token(&self, token: &str) -> bool;
+	fn gsn_token(&self, token: &str) -> Option<String>;
 	fn gsn_add_token(&self, token: &str, springname: &str);
-	fn gsn_remove_token(&self, token: &str);
+	fn gsn_remove_token(&self, token: &str) -> bool;
 	fn gsn_remove_token_by_springname(&self, springname: &str);
 	fn gsn_tokens(&self) -> Vec<(String,String)>;
 	fn gsn_token_by_springname(&self, springname: &str) -> Vec<(String,String)>;
@@ -0,0 +1,359 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `spaces::conformance`
+//!
+//! A suite of checks that any `Netspace` implementation
+//! is expected to pass. Each check takes a freshly constructed
+//! and empty netspace and panics on the first deviation, so
+//! the checks can be called directly from a `#[test]`.
+//!
+//! A backend can run the whole suite with `run`, passing in
+//! a closure that constructs an empty netspace:
+//!
+//! ```
+//! use spring_dvs::spaces::MemoryNetspace;
+//! use spring_dvs::spaces::conformance;
+//!
+//! conformance::run(|| MemoryNetspace::new());
+//! ```
+
+use crate::node::Node;
+use crate::protocol::{IpAddr, NodeRole, NodeService, NodeState};
+use super::{Netspace, NetspaceFailure};
+
+/// Run every conformance check, each against a fresh netspace
+pub fn run<N, F>(factory: F) where N: Netspace, F: Fn() -> N {
+	gsn_register(&factory());
+	gsn_register_duplicate(&factory());
+	gsn_register_endpoint(&factory());
+	gsn_unregister(&factory());
+	gsn_unregister_missing(&factory());
+	gsn_lookup(&factory());
+	gsn_filters(&factory());
+	gsn_update(&factory());
+	gsn_update_missing(&factory());
+	gtn_register(&factory());
+	gtn_register_duplicate(&factory());
+	gtn_unregister(&factory());
+	gtn_update(&factory());
+	tokens(&factory());
+}
+
+fn ip(address: &str) -> IpAddr {
+	address.parse().unwrap()
+}
+
+fn node(spring: &str, host: &str, address: &str, state: NodeState, role: NodeRole) -> Node {
+	Node::new(spring, host, Some(ip(address)), NodeService::Http, state, role, "")
+}
+
+fn springnames(v: &[Node]) -> Vec<String> {
+	let mut names : Vec<String> = v.iter().map(|n| String::from(n.springname())).collect();
+	names.sort();
+	names
+}
+
+/// Registered nodes are listed and retrievable with all fields intact
+pub fn gsn_register<N: Netspace>(nio: &N) {
+	assert!(nio.gsn_nodes().is_empty(), "netspace is not empty");
+
+	let n = Node::new("foo", "foo.example.tld/spring", Some(ip("192.168.1.2")), NodeService::Http, NodeState::Enabled, NodeRole::Org, "KEY");
+	assert_eq!(nio.gsn_node_register(&n), Ok(crate::enums::Success::Ok));
+
+	assert_eq!(springnames(&nio.gsn_nodes()), vec!["foo"]);
+
+	let r = nio.gsn_node_by_springname("foo").expect("registered node not found");
+	assert_eq!(r.springname(), "foo");
+	assert_eq!(r.hostname(), "foo.example.tld");
+	assert_eq!(r.hostpath(), "spring");
+	assert_eq!(r.address(), Some(ip("192.168.1.2")));
+	assert_eq!(r.service(), NodeService::Http);
+	assert_eq!(r.state(), NodeState::Enabled);
+	assert_eq!(r.role(), NodeRole::Org);
+	assert_eq!(r.key(), "KEY");
+
+	// Names are held in one spelling whatever case they are given in
+	assert_eq!(nio.gsn_node_by_springname("FOO").expect("lookup is case sensitive").springname(), "foo");
+	let upper = Node::new("Foo", "foo.example.tld/spring", None, NodeService::Http, NodeState::Enabled, NodeRole::Org, "KEY");
+	assert_eq!(nio.gsn_node_register(&upper), Err(NetspaceFailure::DuplicateNode));
+
+	// A node without an address stays without one
+	let n = Node::new("bar", "bar.example.tld", None, NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	assert!(nio.gsn_node_register(&n).is_ok());
+	assert_eq!(nio.gsn_node_by_springname("bar").expect("registered node not found").address(), None);
+}
+
+/// Registering a springname a second time fails with `DuplicateNode`
+pub fn gsn_register_duplicate<N: Netspace>(nio: &N) {
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Org);
+	assert!(nio.gsn_node_register(&n).is_ok());
+
+	let d = node("foo", "bar.tld", "192.168.1.3", NodeState::Enabled, NodeRole::Org);
+	assert_eq!(nio.gsn_node_register(&d), Err(NetspaceFailure::DuplicateNode));
+	assert_eq!(nio.gsn_nodes().len(), 1);
+	assert_eq!(nio.gsn_node_by_springname("foo").unwrap().hostname(), "foo.tld");
+}
+
+/// Unregistered nodes are removed from the GSN
+pub fn gsn_unregister<N: Netspace>(nio: &N) {
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Org);
+	let m = node("bar", "bar.tld", "192.168.1.3", NodeState::Enabled, NodeRole::Org);
+	assert!(nio.gsn_node_register(&n).is_ok());
+	assert!(nio.gsn_node_register(&m).is_ok());
+
+	assert!(nio.gsn_node_unregister(&n).is_ok());
+	assert_eq!(springnames(&nio.gsn_nodes()), vec!["bar"]);
+	assert_eq!(nio.gsn_node_by_springname("foo").err(), Some(NetspaceFailure::NodeNotFound));
+}
+
+/// Unregistering an unknown node fails with `NodeNotFound`
+pub fn gsn_unregister_missing<N: Netspace>(nio: &N) {
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Org);
+	assert_eq!(nio.gsn_node_unregister(&n), Err(NetspaceFailure::NodeNotFound));
+}
+
+/// Nodes can be looked up by springname and hostname
+pub fn gsn_lookup<N: Netspace>(nio: &N) {
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Org);
+	assert!(nio.gsn_node_register(&n).is_ok());
+
+	assert_eq!(nio.gsn_node_by_hostname("foo.tld").unwrap().springname(), "foo");
+	assert_eq!(nio.gsn_node_by_springname("bar").err(), Some(NetspaceFailure::NodeNotFound));
+	assert_eq!(nio.gsn_node_by_hostname("bar.tld").err(), Some(NetspaceFailure::NodeNotFound));
+}
+
+/// Nodes can be filtered on address, role and state
+pub fn gsn_filters<N: Netspace>(nio: &N) {
+	let nodes = vec![
+		node("alpha", "alpha.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Org),
+		node("beta", "beta.tld", "192.168.1.2", NodeState::Disabled, NodeRole::Hub),
+		node("gamma", "gamma.tld", "192.168.1.3", NodeState::Unresponsive, NodeRole::Hybrid),
+		node("delta", "delta.tld", "192.168.1.4", NodeState::Enabled, NodeRole::Org),
+	];
+
+	for n in &nodes {
+		assert!(nio.gsn_node_register(n).is_ok());
+	}
+
+	assert_eq!(springnames(&nio.gsn_nodes()), vec!["alpha", "beta", "delta", "gamma"]);
+
+	assert_eq!(springnames(&nio.gsn_nodes_by_address(ip("192.168.1.2"))), vec!["alpha", "beta"]);
+	assert!(nio.gsn_nodes_by_address(ip("10.0.0.1")).is_empty());
+
+	assert_eq!(springnames(&nio.gsn_nodes_by_state(NodeState::Enabled)), vec!["alpha", "delta"]);
+	assert_eq!(springnames(&nio.gsn_nodes_by_state(NodeState::Unresponsive)), vec!["gamma"]);
+
+	assert_eq!(springnames(&nio.gsn_nodes_by_type(NodeRole::Org)), vec!["alpha", "delta"]);
+	assert_eq!(springnames(&nio.gsn_nodes_by_type(NodeRole::Hub)), vec!["beta"]);
+	assert_eq!(springnames(&nio.gsn_nodes_by_type(NodeRole::Hybrid)), vec!["gamma"]);
+}
+
+/// The scheme and port of a hostfield are kept, in the GSN and the GTN
+pub fn gsn_register_endpoint<N: Netspace>(nio: &N) {
+	let n = node("foo", "https://foo.tld:8443/node", "192.168.1.2", NodeState::Enabled, NodeRole::Hub);
+	assert!(nio.gsn_node_register(&n).is_ok());
+	assert!(nio.gtn_geosub_register_node(&n, "esusx").is_ok());
+
+	let r = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(r.hostname(), "foo.tld");
+	assert_eq!(r.port(), Some(8443));
+	assert!(r.is_tls());
+	assert_eq!(r.hostfield(), "https://foo.tld:8443/node");
+
+	let r = nio.gtn_geosub_node_by_springname("foo", "esusx").unwrap();
+	assert_eq!(r.hostfield(), "https://foo.tld:8443/node");
+
+	let mut n = node("bar", "bar.tld", "192.168.1.3", NodeState::Enabled, NodeRole::Org);
+	assert!(nio.gsn_node_register(&n).is_ok());
+	assert_eq!(nio.gsn_node_by_springname("bar").unwrap().port(), None);
+
+	n.update_port(Some(8080));
+	assert!(nio.gsn_node_update_hostname(&n).is_ok());
+	assert_eq!(nio.gsn_node_by_springname("bar").unwrap().hostfield(), "bar.tld:8080");
+}
+
+/// Each update method changes only its own property
+pub fn gsn_update<N: Netspace>(nio: &N) {
+	let mut n = node("foo", "foo.tld", "192.168.1.2", NodeState::Disabled, NodeRole::Org);
+	assert!(nio.gsn_node_register(&n).is_ok());
+
+	n.update_state(NodeState::Enabled);
+	n.update_role(NodeRole::Hub);
+	n.update_service(NodeService::Dvsp);
+	n.update_address(Some(ip("192.168.1.9")));
+	n.update_hostname("bar.tld");
+	n.update_hostpash("node");
+
+	assert!(nio.gsn_node_update_state(&n).is_ok());
+	let r = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(r.state(), NodeState::Enabled);
+	assert_eq!(r.role(), NodeRole::Org);
+
+	assert!(nio.gsn_node_update_role(&n).is_ok());
+	let r = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(r.role(), NodeRole::Hub);
+	assert_eq!(r.service(), NodeService::Http);
+
+	assert!(nio.gsn_node_update_service(&n).is_ok());
+	let r = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(r.service(), NodeService::Dvsp);
+	assert_eq!(r.address(), Some(ip("192.168.1.2")));
+
+	assert!(nio.gsn_node_update_address(&n).is_ok());
+	let r = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(r.address(), Some(ip("192.168.1.9")));
+	assert_eq!(r.hostname(), "foo.tld");
+
+	assert!(nio.gsn_node_update_hostname(&n).is_ok());
+	let r = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(r.hostname(), "bar.tld");
+	assert_eq!(r.hostpath(), "node");
+	assert_eq!(r.hostfield(), "bar.tld/node");
+}
+
+/// Updating an unknown node fails with `NodeNotFound`
+pub fn gsn_update_missing<N: Netspace>(nio: &N) {
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Org);
+
+	assert_eq!(nio.gsn_node_update_state(&n), Err(NetspaceFailure::NodeNotFound));
+	assert_eq!(nio.gsn_node_update_service(&n), Err(NetspaceFailure::NodeNotFound));
+	assert_eq!(nio.gsn_node_update_role(&n), Err(NetspaceFailure::NodeNotFound));
+	assert_eq!(nio.gsn_node_update_hostname(&n), Err(NetspaceFailure::NodeNotFound));
+	assert_eq!(nio.gsn_node_update_address(&n), Err(NetspaceFailure::NodeNotFound));
+	assert!(nio.gsn_nodes().is_empty());
+}
+
+/// Geosub root nodes are registered per geosub
+pub fn gtn_register<N: Netspace>(nio: &N) {
+	assert!(nio.gtn_geosubs().is_empty());
+	assert!(nio.gtn_root_nodes().is_empty());
+
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Hub);
+	let m = node("bar", "bar.tld", "192.168.1.3", NodeState::Enabled, NodeRole::Hub);
+
+	assert!(nio.gtn_geosub_register_node(&n, "esusx").is_ok());
+	assert!(nio.gtn_geosub_register_node(&m, "esusx").is_ok());
+	assert!(nio.gtn_geosub_register_node(&n, "wsusx").is_ok());
+
+	let mut geosubs = nio.gtn_geosubs();
+	geosubs.sort();
+	assert_eq!(geosubs, vec!["esusx", "wsusx"]);
+
+	assert_eq!(springnames(&nio.gtn_geosub_root_nodes("esusx")), vec!["bar", "foo"]);
+	assert_eq!(springnames(&nio.gtn_geosub_root_nodes("wsusx")), vec!["foo"]);
+	assert!(nio.gtn_geosub_root_nodes("ksusx").is_empty());
+	assert_eq!(springnames(&nio.gtn_root_nodes()), vec!["bar", "foo"]);
+
+	let r = nio.gtn_geosub_node_by_springname("bar", "esusx").expect("root node not found");
+	assert_eq!(r.hostname(), "bar.tld");
+	assert_eq!(r.address(), Some(ip("192.168.1.3")));
+	assert_eq!(nio.gtn_geosub_node_by_springname("bar", "wsusx").err(), Some(NetspaceFailure::NodeNotFound));
+	assert_eq!(nio.gtn_geosub_node_by_springname("bar", "ksusx").err(), Some(NetspaceFailure::NodeNotFound));
+
+	// The GTN is separate from the local GSN
+	assert!(nio.gsn_nodes().is_empty());
+}
+
+/// Registering a root node twice in a geosub fails with `DuplicateNode`
+pub fn gtn_register_duplicate<N: Netspace>(nio: &N) {
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Hub);
+	assert!(nio.gtn_geosub_register_node(&n, "esusx").is_ok());
+	assert_eq!(nio.gtn_geosub_register_node(&n, "esusx"), Err(NetspaceFailure::DuplicateNode));
+	assert_eq!(nio.gtn_geosub_root_nodes("esusx").len(), 1);
+}
+
+/// Root nodes are removed from a single geosub
+pub fn gtn_unregister<N: Netspace>(nio: &N) {
+	let n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Hub);
+	let m = node("bar", "bar.tld", "192.168.1.3", NodeState::Enabled, NodeRole::Hub);
+
+	assert!(nio.gtn_geosub_register_node(&n, "esusx").is_ok());
+	assert!(nio.gtn_geosub_register_node(&m, "esusx").is_ok());
+	assert!(nio.gtn_geosub_register_node(&n, "wsusx").is_ok());
+
+	assert!(nio.gtn_geosub_unregister_node(&n, "esusx").is_ok());
+	assert_eq!(springnames(&nio.gtn_geosub_root_nodes("esusx")), vec!["bar"]);
+	assert_eq!(springnames(&nio.gtn_geosub_root_nodes("wsusx")), vec!["foo"]);
+
+	assert_eq!(nio.gtn_geosub_unregister_node(&n, "esusx"), Err(NetspaceFailure::NodeNotFound));
+	assert_eq!(nio.gtn_geosub_unregister_node(&n, "ksusx"), Err(NetspaceFailure::NodeNotFound));
+}
+
+/// Updates to a GSN node reach its copies in the GTN but not
+/// other root nodes of the same springname
+pub fn gtn_update<N: Netspace>(nio: &N) {
+	let mut n = node("foo", "foo.tld", "192.168.1.2", NodeState::Enabled, NodeRole::Hub);
+	let other = node("foo", "other.tld", "192.168.2.2", NodeState::Enabled, NodeRole::Hub);
+	assert!(nio.gsn_node_register(&n).is_ok());
+	assert!(nio.gtn_geosub_register_node(&n, "esusx").is_ok());
+	assert!(nio.gtn_geosub_register_node(&other, "wsusx").is_ok());
+
+	n.update_state(NodeState::Unresponsive);
+	n.update_service(NodeService::Dvsp);
+	assert!(nio.gsn_node_update_state(&n).is_ok());
+	assert!(nio.gsn_node_update_service(&n).is_ok());
+
+	let copy = nio.gtn_geosub_node_by_springname("foo", "esusx").unwrap();
+	assert_eq!(copy.state(), NodeState::Unresponsive);
+	assert_eq!(copy.service(), NodeService::Dvsp);
+
+	let other = nio.gtn_geosub_node_by_springname("foo", "wsusx").unwrap();
+	assert_eq!(other.state(), NodeState::Enabled);
+	assert_eq!(other.service(), NodeService::Http);
+
+	// The copy still follows the node once its hostname changes
+	n.update_hostname("moved.tld");
+	assert!(nio.gsn_node_update_hostname(&n).is_ok());
+	n.update_address(Some(ip("192.168.1.9")));
+	assert!(nio.gsn_node_update_address(&n).is_ok());
+
+	let copy = nio.gtn_geosub_node_by_springname("foo", "esusx").unwrap();
+	assert_eq!(copy.hostname(), "moved.tld");
+	assert_eq!(copy.address(), Some(ip("192.168.1.9")));
+	assert_eq!(nio.gtn_geosub_node_by_springname("foo", "wsusx").unwrap().hostname(), "other.tld");
+}
+
+/// Tokens are stored against springnames and can be removed
+/// once, with removal reporting whether the token was there
+pub fn tokens<N: Netspace>(nio: &N) {
+	assert!(nio.gsn_tokens().is_empty());
+	assert!(!nio.gsn_check_token("abc"));
+
+	nio.gsn_add_token("abc", "foo");
+	nio.gsn_add_token("def", "foo");
+	nio.gsn_add_token("ghi", "bar");
+
+	assert!(nio.gsn_check_token("abc"));
+	assert!(nio.gsn_check_token("ghi"));
+	assert!(!nio.gsn_check_token("xyz"));
+
+	assert_eq!(nio.gsn_token("abc"), Some(String::from("foo")));
+	assert_eq!(nio.gsn_token("xyz"), None);
+
+	let mut all = nio.gsn_tokens();
+	all.sort();
+	assert_eq!(all, vec![
+		(String::from("abc"), String::from("foo")),
+		(String::from("def"), String::from("foo")),
+		(String::from("ghi"), String::from("bar")),
+	]);
+
+	let mut owned = nio.gsn_token_by_springname("foo");
+	owned.sort();
+	assert_eq!(owned, vec![
+		(String::from("abc"), String::from("foo")),
+		(String::from("def"), String::from("foo")),
+	]);
+
+	assert!(nio.gsn_remove_token("abc"));
+	assert!(!nio.gsn_remove_token("abc"));
+	assert!(!nio.gsn_check_token("abc"));
+	assert!(nio.gsn_check_token("def"));
+
+	nio.gsn_remove_token_by_springname("foo");
+	assert!(!nio.gsn_check_token("def"));
+	assert!(nio.gsn_token_by_springname("foo").is_empty());
+	assert_eq!(nio.gsn_tokens(), vec![(String::from("ghi"), String::from("bar"))]);
+}
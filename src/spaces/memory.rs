@@ -0,0 +1,310 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `spaces::memory`
+//!
+//! A reference implementation of `Netspace` that holds the
+//! whole netspace in memory. It is not persistent, but it
+//! implements the same semantics that a database backed
+//! netspace is expected to have, so it is suitable for
+//! testing node code and as a baseline for the conformance
+//! suite.
+
+use std::sync::{Mutex, MutexGuard};
+
+use crate::enums::Success;
+use crate::node::Node;
+use crate::formats::canonical_ip;
+use crate::names::to_ascii;
+use crate::protocol::{IpAddr, NodeRole, NodeState};
+use super::{Netspace, NetspaceFailure};
+
+struct Geosub {
+	name: String,
+	nodes: Vec<Node>,
+}
+
+struct State {
+	nodes: Vec<Node>,
+	geosubs: Vec<Geosub>,
+	tokens: Vec<(String,String)>,
+}
+
+/// Netspace held entirely in memory
+///
+/// Nodes are keyed on their springname, both in the GSN and
+/// in each of the GTN geosubs. Interior mutability is used
+/// since the `Netspace` trait works on shared references.
+///
+/// A node registered in both is held as two copies, and
+/// updates to the GSN node are applied to both.
+pub struct MemoryNetspace {
+	state: Mutex<State>,
+}
+
+impl MemoryNetspace {
+	pub fn new() -> MemoryNetspace {
+		MemoryNetspace {
+			state: Mutex::new(State {
+				nodes: Vec::new(),
+				geosubs: Vec::new(),
+				tokens: Vec::new(),
+			})
+		}
+	}
+
+	fn lock(&self) -> MutexGuard<'_, State> {
+		match self.state.lock() {
+			Ok(guard) => guard,
+			Err(poisoned) => poisoned.into_inner(),
+		}
+	}
+
+	/// Apply an update to the stored copy of a node and to
+	/// its copies in the GTN, which are the root nodes of the
+	/// same springname and hostname
+	fn update<F>(&self, node: &Node, f: F) -> Result<Success,NetspaceFailure>
+		where F: Fn(&mut Node, &Node) {
+		let mut state = self.lock();
+		let state = &mut *state;
+
+		let hostname = match state.nodes.iter_mut().find(|n| n.springname() == node.springname()) {
+			Some(n) => {
+				let hostname = String::from(n.hostname());
+				f(n, node);
+				hostname
+			},
+			None => return Err(NetspaceFailure::NodeNotFound)
+		};
+
+		for geosub in &mut state.geosubs {
+			for n in geosub.nodes.iter_mut().filter(|n| n.springname() == node.springname() && n.hostname() == hostname) {
+				f(n, node);
+			}
+		}
+
+		Ok(Success::Ok)
+	}
+}
+
+impl Default for MemoryNetspace {
+	fn default() -> MemoryNetspace {
+		MemoryNetspace::new()
+	}
+}
+
+impl Netspace for MemoryNetspace {
+	fn gsn_nodes(&self) -> Vec<Node> {
+		self.lock().nodes.clone()
+	}
+
+	fn gsn_nodes_by_address(&self, address: IpAddr) -> Vec<Node> {
+		let address = canonical_ip(address);
+		self.lock().nodes.iter()
+			.filter(|n| n.address() == Some(address))
+			.cloned()
+			.collect()
+	}
+
+	fn gsn_nodes_by_type(&self, types: NodeRole) -> Vec<Node> {
+		self.lock().nodes.iter()
+			.filter(|n| n.role() == types)
+			.cloned()
+			.collect()
+	}
+
+	fn gsn_nodes_by_state(&self, state: NodeState) -> Vec<Node> {
+		self.lock().nodes.iter()
+			.filter(|n| n.state() == state)
+			.cloned()
+			.collect()
+	}
+
+	fn gsn_node_by_springname(&self, name: &str) -> Result<Node,NetspaceFailure> {
+		let name = match to_ascii(name) {
+			Some(n) => n,
+			None => return Err(NetspaceFailure::NodeNotFound)
+		};
+		match self.lock().nodes.iter().find(|n| n.springname() == name) {
+			Some(n) => Ok(n.clone()),
+			None => Err(NetspaceFailure::NodeNotFound)
+		}
+	}
+
+	fn gsn_node_by_hostname(&self, name: &str) -> Result<Node,NetspaceFailure> {
+		match self.lock().nodes.iter().find(|n| n.hostname() == name) {
+			Some(n) => Ok(n.clone()),
+			None => Err(NetspaceFailure::NodeNotFound)
+		}
+	}
+
+	fn gtn_root_nodes(&self) -> Vec<Node> {
+		let state = self.lock();
+		let mut v : Vec<Node> = Vec::new();
+		for gsn in &state.geosubs {
+			for n in &gsn.nodes {
+				if v.iter().any(|r| r.springname() == n.springname()) { continue }
+				v.push(n.clone())
+			}
+		}
+		v
+	}
+
+	fn gtn_geosubs(&self) -> Vec<String> {
+		self.lock().geosubs.iter()
+			.map(|g| g.name.clone())
+			.collect()
+	}
+
+	fn gsn_node_register(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		let mut state = self.lock();
+		if state.nodes.iter().any(|n| n.springname() == node.springname()) {
+			return Err(NetspaceFailure::DuplicateNode)
+		}
+
+		state.nodes.push(node.clone());
+		Ok(Success::Ok)
+	}
+
+	fn gsn_node_unregister(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		let mut state = self.lock();
+		match state.nodes.iter().position(|n| n.springname() == node.springname()) {
+			Some(i) => {
+				state.nodes.remove(i);
+				Ok(Success::Ok)
+			},
+			None => Err(NetspaceFailure::NodeNotFound)
+		}
+	}
+
+	fn gsn_node_update_state(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update(node, |n, u| n.update_state(u.state()))
+	}
+
+	fn gsn_node_update_service(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update(node, |n, u| n.update_service(u.service()))
+	}
+
+	fn gsn_node_update_role(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update(node, |n, u| n.update_role(u.role()))
+	}
+
+	fn gsn_node_update_hostname(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update(node, |n, u| {
+			n.update_hostname(u.hostname());
+			n.update_hostpash(u.hostpath());
+			n.update_port(u.port());
+			n.update_tls(u.is_tls());
+		})
+	}
+
+	fn gsn_node_update_address(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update(node, |n, u| n.update_address(u.address()))
+	}
+
+	fn gtn_geosub_root_nodes(&self, gsn: &str) -> Vec<Node> {
+		match self.lock().geosubs.iter().find(|g| g.name == gsn) {
+			Some(g) => g.nodes.clone(),
+			None => Vec::new()
+		}
+	}
+
+	fn gtn_geosub_node_by_springname(&self, name: &str, gsn: &str) -> Result<Node,NetspaceFailure> {
+		let (name, gsn) = match (to_ascii(name), to_ascii(gsn)) {
+			(Some(n), Some(g)) => (n, g),
+			_ => return Err(NetspaceFailure::NodeNotFound)
+		};
+		let state = self.lock();
+		let geosub = match state.geosubs.iter().find(|g| g.name == gsn) {
+			Some(g) => g,
+			None => return Err(NetspaceFailure::NodeNotFound)
+		};
+
+		match geosub.nodes.iter().find(|n| n.springname() == name) {
+			Some(n) => Ok(n.clone()),
+			None => Err(NetspaceFailure::NodeNotFound)
+		}
+	}
+
+	fn gtn_geosub_register_node(&self, node: &Node, gsn: &str) -> Result<Success,NetspaceFailure> {
+		let mut state = self.lock();
+
+		if !state.geosubs.iter().any(|g| g.name == gsn) {
+			state.geosubs.push(Geosub { name: String::from(gsn), nodes: Vec::new() });
+		}
+
+		let geosub = match state.geosubs.iter_mut().find(|g| g.name == gsn) {
+			Some(g) => g,
+			None => return Err(NetspaceFailure::DatabaseError)
+		};
+
+		if geosub.nodes.iter().any(|n| n.springname() == node.springname()) {
+			return Err(NetspaceFailure::DuplicateNode)
+		}
+
+		geosub.nodes.push(node.clone());
+		Ok(Success::Ok)
+	}
+
+	/// A geosub is dropped from the GTN when its last root
+	/// node is unregistered
+	fn gtn_geosub_unregister_node(&self, node: &Node, gsn: &str) -> Result<Success,NetspaceFailure> {
+		let mut state = self.lock();
+
+		let gi = match state.geosubs.iter().position(|g| g.name == gsn) {
+			Some(i) => i,
+			None => return Err(NetspaceFailure::NodeNotFound)
+		};
+
+		let ni = match state.geosubs[gi].nodes.iter().position(|n| n.springname() == node.springname()) {
+			Some(i) => i,
+			None => return Err(NetspaceFailure::NodeNotFound)
+		};
+
+		state.geosubs[gi].nodes.remove(ni);
+		if state.geosubs[gi].nodes.is_empty() {
+			state.geosubs.remove(gi);
+		}
+
+		Ok(Success::Ok)
+	}
+
+	fn gsn_check_token(&self, token: &str) -> bool {
+		self.lock().tokens.iter().any(|(t, _)| t == token)
+	}
+
+	fn gsn_token(&self, token: &str) -> Option<String> {
+		self.lock().tokens.iter()
+			.find(|(t, _)| t == token)
+			.map(|(_, s)| s.clone())
+	}
+
+	fn gsn_add_token(&self, token: &str, springname: &str) {
+		let mut state = self.lock();
+		state.tokens.retain(|(t, _)| t != token);
+		state.tokens.push((String::from(token), String::from(springname)));
+	}
+
+	fn gsn_remove_token(&self, token: &str) -> bool {
+		let mut state = self.lock();
+		let before = state.tokens.len();
+		state.tokens.retain(|(t, _)| t != token);
+		state.tokens.len() != before
+	}
+
+	fn gsn_remove_token_by_springname(&self, springname: &str) {
+		self.lock().tokens.retain(|(_, s)| s != springname);
+	}
+
+	fn gsn_tokens(&self) -> Vec<(String,String)> {
+		self.lock().tokens.clone()
+	}
+
+	fn gsn_token_by_springname(&self, springname: &str) -> Vec<(String,String)> {
+		self.lock().tokens.iter()
+			.filter(|(_, s)| s == springname)
+			.cloned()
+			.collect()
+	}
+}
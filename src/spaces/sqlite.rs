@@ -0,0 +1,394 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `spaces::sqlite`
+//!
+//! A `Netspace` persisted in an SQLite database. Enabled
+//! with the `sqlite` feature.
+//!
+//! The schema is versioned through the database's
+//! `user_version` pragma. Opening a database applies any
+//! migrations it has not yet seen, so an existing netspace
+//! is upgraded in place.
+//!
+//! Every store error is reported as
+//! `NetspaceFailure::DatabaseError`; methods that return a
+//! list of nodes return an empty list instead.
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{Connection, Row, ToSql, ErrorCode};
+
+use crate::enums::Success;
+use crate::node::Node;
+use crate::formats::{canonical_address, canonical_ip};
+use crate::names::to_ascii;
+use crate::protocol::{IpAddr, NodeRole, NodeService, NodeState};
+use super::{Netspace, NetspaceFailure};
+
+/// Schema migrations, applied in order. The schema version
+/// of a database is the number of migrations applied to it.
+const MIGRATIONS: &[&str] = &[
+	// 1: GSN nodes, GTN geosub root nodes and registration tokens
+	"CREATE TABLE geosub_netspace (
+		springname TEXT PRIMARY KEY NOT NULL,
+		hostname TEXT NOT NULL,
+		hostpath TEXT NOT NULL,
+		address TEXT,
+		service INTEGER NOT NULL,
+		state INTEGER NOT NULL,
+		role INTEGER NOT NULL,
+		key TEXT NOT NULL,
+		port INTEGER,
+		tls INTEGER NOT NULL DEFAULT 0
+	);
+	CREATE TABLE geotop_netspace (
+		springname TEXT NOT NULL,
+		geosub TEXT NOT NULL,
+		hostname TEXT NOT NULL,
+		hostpath TEXT NOT NULL,
+		address TEXT,
+		service INTEGER NOT NULL,
+		state INTEGER NOT NULL,
+		role INTEGER NOT NULL,
+		key TEXT NOT NULL,
+		port INTEGER,
+		tls INTEGER NOT NULL DEFAULT 0,
+		PRIMARY KEY (springname, geosub)
+	);
+	CREATE TABLE geosub_tokens (
+		token TEXT PRIMARY KEY NOT NULL,
+		springname TEXT NOT NULL
+	);",
+];
+
+/// The schema version created by this version of the crate
+pub const SCHEMA_VERSION: i64 = 1;
+
+const NODE_COLUMNS: &str = "springname, hostname, hostpath, address, service, state, role, key, port, tls";
+
+/// Netspace stored in an SQLite database
+pub struct SqliteNetspace {
+	db: Mutex<Connection>,
+}
+
+macro_rules! db_try {
+	($res:expr) => (
+		match $res {
+			Ok(r) => r,
+			Err(_) => return Err(NetspaceFailure::DatabaseError),
+		}
+	)
+}
+
+/// The address of a node as it is stored, `NULL` if the node
+/// has none
+fn address_text(node: &Node) -> Option<String> {
+	node.address().map(|a| a.to_string())
+}
+
+fn node_from_row(row: &Row) -> rusqlite::Result<Node> {
+	let spring : String = row.get(0)?;
+	let hostname : String = row.get(1)?;
+	let hostpath : String = row.get(2)?;
+	let address : Option<String> = row.get(3)?;
+	let key : String = row.get(7)?;
+	let port : Option<u16> = row.get(8)?;
+	let tls : bool = row.get(9)?;
+
+	let mut node = Node::new(
+		&spring, "", address.as_deref().and_then(canonical_address),
+		NodeService::from_i64(row.get(4)?),
+		NodeState::from_i64(row.get(5)?),
+		NodeRole::from_i64(row.get(6)?),
+		&key
+	);
+
+	node.update_hostname(&hostname);
+	node.update_hostpash(&hostpath);
+	node.update_port(port);
+	node.update_tls(tls);
+	Ok(node)
+}
+
+/// An insert that violates a constraint is of a node that is
+/// already registered
+fn insert_failure(e: rusqlite::Error) -> NetspaceFailure {
+	match e.sqlite_error_code() {
+		Some(ErrorCode::ConstraintViolation) => NetspaceFailure::DuplicateNode,
+		_ => NetspaceFailure::DatabaseError,
+	}
+}
+
+impl SqliteNetspace {
+	/// Open or create the netspace database at `path`
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteNetspace, NetspaceFailure> {
+		SqliteNetspace::from_connection(db_try!(Connection::open(path)))
+	}
+
+	/// Create a netspace in a private, in-memory database
+	pub fn open_in_memory() -> Result<SqliteNetspace, NetspaceFailure> {
+		SqliteNetspace::from_connection(db_try!(Connection::open_in_memory()))
+	}
+
+	/// Use an existing connection, migrating its schema
+	pub fn from_connection(conn: Connection) -> Result<SqliteNetspace, NetspaceFailure> {
+		let version : i64 = db_try!(conn.query_row("PRAGMA user_version", [], |r| r.get(0)));
+
+		if !(0..=SCHEMA_VERSION).contains(&version) {
+			return Err(NetspaceFailure::DatabaseError)
+		}
+
+		for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+			let batch = format!("BEGIN;\n{}\nPRAGMA user_version = {};\nCOMMIT;", migration, i + 1);
+			if conn.execute_batch(&batch).is_err() {
+				let _ = conn.execute_batch("ROLLBACK;");
+				return Err(NetspaceFailure::DatabaseError)
+			}
+		}
+
+		Ok(SqliteNetspace {
+			db: Mutex::new(conn)
+		})
+	}
+
+	/// The schema version of the open database
+	pub fn schema_version(&self) -> Result<i64, NetspaceFailure> {
+		Ok(db_try!(self.lock().query_row("PRAGMA user_version", [], |r| r.get(0))))
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Connection> {
+		match self.db.lock() {
+			Ok(guard) => guard,
+			Err(poisoned) => poisoned.into_inner(),
+		}
+	}
+
+	fn nodes(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<Node>, NetspaceFailure> {
+		let db = self.lock();
+		let mut stmt = db_try!(db.prepare(sql));
+		let rows = db_try!(stmt.query_map(params, node_from_row));
+
+		let mut v : Vec<Node> = Vec::new();
+		for n in rows {
+			v.push(db_try!(n))
+		}
+		Ok(v)
+	}
+
+	fn node(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Node,NetspaceFailure> {
+		match self.nodes(sql, params)?.into_iter().next() {
+			Some(n) => Ok(n),
+			None => Err(NetspaceFailure::NodeNotFound)
+		}
+	}
+
+	/// Execute a statement that should change exactly one row
+	fn change(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Success,NetspaceFailure> {
+		match self.lock().execute(sql, params) {
+			Ok(0) => Err(NetspaceFailure::NodeNotFound),
+			Ok(_) => Ok(Success::Ok),
+			Err(_) => Err(NetspaceFailure::DatabaseError),
+		}
+	}
+
+	/// Insert a row, failing with `DuplicateNode` if it is
+	/// already there
+	fn insert(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Success,NetspaceFailure> {
+		match self.lock().execute(sql, params) {
+			Ok(_) => Ok(Success::Ok),
+			Err(e) => Err(insert_failure(e)),
+		}
+	}
+
+	/// Update the columns `set` of a GSN node and of its copies
+	/// in the GTN, which are the root nodes of the same
+	/// springname and hostname
+	///
+	/// The springname is the last of `params`
+	fn update(&self, set: &str, params: &[&dyn ToSql]) -> Result<Success,NetspaceFailure> {
+		let spring = params.len();
+		let gtn = format!(
+			"UPDATE geotop_netspace SET {} WHERE springname = ?{n} AND hostname = (SELECT hostname FROM geosub_netspace WHERE springname = ?{n})",
+			set, n = spring
+		);
+		let gsn = format!("UPDATE geosub_netspace SET {} WHERE springname = ?{}", set, spring);
+
+		let mut db = self.lock();
+		let tx = db_try!(db.transaction());
+		db_try!(tx.execute(&gtn, params));
+
+		if db_try!(tx.execute(&gsn, params)) == 0 {
+			return Err(NetspaceFailure::NodeNotFound)
+		}
+
+		db_try!(tx.commit());
+		Ok(Success::Ok)
+	}
+
+	fn tokens(&self, sql: &str, params: &[&dyn ToSql]) -> Vec<(String,String)> {
+		let db = self.lock();
+		let mut stmt = match db.prepare(sql) {
+			Ok(s) => s,
+			Err(_) => return Vec::new()
+		};
+
+		let rows = match stmt.query_map(params, |r| Ok((r.get(0)?, r.get(1)?))) {
+			Ok(r) => r,
+			Err(_) => return Vec::new()
+		};
+
+		rows.filter_map(|r| r.ok()).collect()
+	}
+}
+
+impl Netspace for SqliteNetspace {
+	fn gsn_nodes(&self) -> Vec<Node> {
+		let sql = format!("SELECT {} FROM geosub_netspace", NODE_COLUMNS);
+		self.nodes(&sql, &[]).unwrap_or_default()
+	}
+
+	fn gsn_nodes_by_address(&self, address: IpAddr) -> Vec<Node> {
+		let sql = format!("SELECT {} FROM geosub_netspace WHERE address = ?1", NODE_COLUMNS);
+		self.nodes(&sql, &[&canonical_ip(address).to_string()]).unwrap_or_default()
+	}
+
+	fn gsn_nodes_by_type(&self, types: NodeRole) -> Vec<Node> {
+		let sql = format!("SELECT {} FROM geosub_netspace WHERE role = ?1", NODE_COLUMNS);
+		self.nodes(&sql, &[&(types as i64)]).unwrap_or_default()
+	}
+
+	fn gsn_nodes_by_state(&self, state: NodeState) -> Vec<Node> {
+		let sql = format!("SELECT {} FROM geosub_netspace WHERE state = ?1", NODE_COLUMNS);
+		self.nodes(&sql, &[&(state as i64)]).unwrap_or_default()
+	}
+
+	fn gsn_node_by_springname(&self, name: &str) -> Result<Node,NetspaceFailure> {
+		let name = match to_ascii(name) {
+			Some(n) => n,
+			None => return Err(NetspaceFailure::NodeNotFound)
+		};
+		let sql = format!("SELECT {} FROM geosub_netspace WHERE springname = ?1", NODE_COLUMNS);
+		self.node(&sql, &[&name])
+	}
+
+	fn gsn_node_by_hostname(&self, name: &str) -> Result<Node,NetspaceFailure> {
+		let sql = format!("SELECT {} FROM geosub_netspace WHERE hostname = ?1", NODE_COLUMNS);
+		self.node(&sql, &[&name])
+	}
+
+	fn gtn_root_nodes(&self) -> Vec<Node> {
+		let sql = format!("SELECT {} FROM geotop_netspace GROUP BY springname", NODE_COLUMNS);
+		self.nodes(&sql, &[]).unwrap_or_default()
+	}
+
+	fn gtn_geosubs(&self) -> Vec<String> {
+		let db = self.lock();
+		let mut stmt = match db.prepare("SELECT DISTINCT geosub FROM geotop_netspace") {
+			Ok(s) => s,
+			Err(_) => return Vec::new()
+		};
+
+		let rows = match stmt.query_map([], |r| r.get(0)) {
+			Ok(r) => r,
+			Err(_) => return Vec::new()
+		};
+
+		rows.filter_map(|r| r.ok()).collect()
+	}
+
+	fn gsn_node_register(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		let sql = format!("INSERT INTO geosub_netspace ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)", NODE_COLUMNS);
+		self.insert(&sql, &[
+			&node.springname(), &node.hostname(), &node.hostpath(), &address_text(node),
+			&(node.service() as i64), &(node.state() as i64), &(node.role() as i64), &node.key(),
+			&node.port(), &node.is_tls()
+		])
+	}
+
+	fn gsn_node_unregister(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.change("DELETE FROM geosub_netspace WHERE springname = ?1", &[&node.springname()])
+	}
+
+	fn gsn_node_update_state(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update("state = ?1", &[&(node.state() as i64), &node.springname()])
+	}
+
+	fn gsn_node_update_service(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update("service = ?1", &[&(node.service() as i64), &node.springname()])
+	}
+
+	fn gsn_node_update_role(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update("role = ?1", &[&(node.role() as i64), &node.springname()])
+	}
+
+	fn gsn_node_update_hostname(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update("hostname = ?1, hostpath = ?2, port = ?3, tls = ?4", &[
+			&node.hostname(), &node.hostpath(), &node.port(), &node.is_tls(), &node.springname()
+		])
+	}
+
+	fn gsn_node_update_address(&self, node: &Node) -> Result<Success,NetspaceFailure> {
+		self.update("address = ?1", &[&address_text(node), &node.springname()])
+	}
+
+	fn gtn_geosub_root_nodes(&self, gsn: &str) -> Vec<Node> {
+		let sql = format!("SELECT {} FROM geotop_netspace WHERE geosub = ?1", NODE_COLUMNS);
+		self.nodes(&sql, &[&gsn]).unwrap_or_default()
+	}
+
+	fn gtn_geosub_node_by_springname(&self, name: &str, gsn: &str) -> Result<Node,NetspaceFailure> {
+		let (name, gsn) = match (to_ascii(name), to_ascii(gsn)) {
+			(Some(n), Some(g)) => (n, g),
+			_ => return Err(NetspaceFailure::NodeNotFound)
+		};
+		let sql = format!("SELECT {} FROM geotop_netspace WHERE springname = ?1 AND geosub = ?2", NODE_COLUMNS);
+		self.node(&sql, &[&name, &gsn])
+	}
+
+	fn gtn_geosub_register_node(&self, node: &Node, gsn: &str) -> Result<Success,NetspaceFailure> {
+		let sql = format!("INSERT INTO geotop_netspace ({}, geosub) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)", NODE_COLUMNS);
+		self.insert(&sql, &[
+			&node.springname(), &node.hostname(), &node.hostpath(), &address_text(node),
+			&(node.service() as i64), &(node.state() as i64), &(node.role() as i64), &node.key(),
+			&node.port(), &node.is_tls(), &gsn
+		])
+	}
+
+	fn gtn_geosub_unregister_node(&self, node: &Node, gsn: &str) -> Result<Success,NetspaceFailure> {
+		self.change("DELETE FROM geotop_netspace WHERE springname = ?1 AND geosub = ?2", &[&node.springname(), &gsn])
+	}
+
+	fn gsn_check_token(&self, token: &str) -> bool {
+		!self.tokens("SELECT token, springname FROM geosub_tokens WHERE token = ?1", &[&token]).is_empty()
+	}
+
+	fn gsn_token(&self, token: &str) -> Option<String> {
+		self.tokens("SELECT token, springname FROM geosub_tokens WHERE token = ?1", &[&token])
+			.into_iter()
+			.next()
+			.map(|(_, s)| s)
+	}
+
+	fn gsn_add_token(&self, token: &str, springname: &str) {
+		let _ = self.lock().execute("INSERT OR REPLACE INTO geosub_tokens (token, springname) VALUES (?1, ?2)", [token, springname]);
+	}
+
+	fn gsn_remove_token(&self, token: &str) -> bool {
+		matches!(self.lock().execute("DELETE FROM geosub_tokens WHERE token = ?1", [token]), Ok(n) if n > 0)
+	}
+
+	fn gsn_remove_token_by_springname(&self, springname: &str) {
+		let _ = self.lock().execute("DELETE FROM geosub_tokens WHERE springname = ?1", [springname]);
+	}
+
+	fn gsn_tokens(&self) -> Vec<(String,String)> {
+		self.tokens("SELECT token, springname FROM geosub_tokens", &[])
+	}
+
+	fn gsn_token_by_springname(&self, springname: &str) -> Vec<(String,String)> {
+		self.tokens("SELECT token, springname FROM geosub_tokens WHERE springname = ?1", &[&springname])
+	}
+}
@@ -0,0 +1,329 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `stream`
+//!
+//! Framing for exchanging many messages over one connection,
+//! such as on `Port::Stream`.
+//!
+//! Messages in the text protocol can contain newlines, so each
+//! is framed as a netstring: the length of the message in
+//! decimal, a colon, the message itself and a comma.
+//!
+//! `4:info,` `12:info network,`
+//!
+//! A `FrameDecoder` pulls frames out of bytes as they arrive,
+//! so a frame may be split across reads and several frames may
+//! arrive in one. `MessageReader` and `MessageWriter` wrap a
+//! `Read` or `Write` with the decoder and encoder.
+//!
+//! # Example
+//!
+//! ```
+//! use spring_dvs::protocol::{Message, ProtocolObject};
+//! use spring_dvs::stream::{MessageReader, MessageWriter};
+//!
+//! let mut writer = MessageWriter::new(Vec::new());
+//! writer.write_message(&Message::from_bytes(b"info network").unwrap()).unwrap();
+//! writer.write_message(&Message::from_bytes(b"200").unwrap()).unwrap();
+//!
+//! let bytes = writer.into_inner();
+//! assert_eq!(bytes, b"12:info network,3:200,");
+//!
+//! let mut reader = MessageReader::new(&bytes[..]);
+//! assert!(reader.read_message().unwrap().is_some());
+//! assert!(reader.read_message().unwrap().is_some());
+//! assert!(reader.read_message().unwrap().is_none());
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+use std::str;
+
+use crate::enums::ParseFailure;
+use crate::protocol::{Message, ProtocolObject};
+
+/// The largest frame accepted by default
+pub const MAX_FRAME: usize = 65536;
+
+// The digits of a length no larger than usize::MAX
+const MAX_DIGITS: usize = 20;
+
+/// The reasons reading from a stream can fail
+#[derive(Debug)]
+pub enum StreamError {
+	/// Receiving from the stream failed
+	Io(io::Error),
+
+	/// The stream closed part way through a frame
+	Closed,
+
+	/// The bytes on the stream are not a frame
+	MalformedFrame,
+
+	/// The frame is larger than the reader's limit
+	FrameTooLarge,
+
+	/// The frame is not a valid message
+	Parse(ParseFailure),
+}
+
+impl fmt::Display for StreamError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			StreamError::Io(ref e) => write!(f, "i/o error: {}", e),
+			StreamError::Closed => write!(f, "stream closed part way through a frame"),
+			StreamError::MalformedFrame => write!(f, "malformed frame"),
+			StreamError::FrameTooLarge => write!(f, "frame too large"),
+			StreamError::Parse(ref e) => write!(f, "invalid message: {:?}", e),
+		}
+	}
+}
+
+impl error::Error for StreamError {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match *self {
+			StreamError::Io(ref e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for StreamError {
+	fn from(e: io::Error) -> StreamError {
+		match e.kind() {
+			io::ErrorKind::UnexpectedEof => StreamError::Closed,
+			_ => StreamError::Io(e),
+		}
+	}
+}
+
+impl From<ParseFailure> for StreamError {
+	fn from(e: ParseFailure) -> StreamError {
+		StreamError::Parse(e)
+	}
+}
+
+/// Frame bytes as a netstring
+pub fn encode_frame(bytes: &[u8]) -> Vec<u8> {
+	let mut v = format!("{}:", bytes.len()).into_bytes();
+	v.extend_from_slice(bytes);
+	v.push(b',');
+	v
+}
+
+/// Pulls frames out of bytes as they arrive
+#[derive(Clone, Debug)]
+pub struct FrameDecoder {
+	buf: Vec<u8>,
+	max_frame: usize,
+}
+
+impl Default for FrameDecoder {
+	fn default() -> FrameDecoder {
+		FrameDecoder::new()
+	}
+}
+
+impl FrameDecoder {
+	/// A decoder accepting frames of up to `MAX_FRAME` bytes
+	pub fn new() -> FrameDecoder {
+		FrameDecoder {
+			buf: Vec::new(),
+			max_frame: MAX_FRAME,
+		}
+	}
+
+	pub fn with_max_frame(mut self, limit: usize) -> FrameDecoder {
+		self.max_frame = limit;
+		self
+	}
+
+	/// Add bytes received from the stream
+	pub fn feed(&mut self, bytes: &[u8]) {
+		self.buf.extend_from_slice(bytes)
+	}
+
+	/// Whether there are no bytes waiting to be decoded
+	pub fn is_empty(&self) -> bool {
+		self.buf.is_empty()
+	}
+
+	/// Take the next complete frame, if one has arrived
+	///
+	/// Once this fails the stream is out of step and nothing
+	/// more can be read from it
+	pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, StreamError> {
+		let colon = match self.buf.iter().take(MAX_DIGITS + 1).position(|b| *b == b':') {
+			Some(i) => i,
+			None => {
+				if self.buf.len() > MAX_DIGITS || !self.buf.iter().all(u8::is_ascii_digit) {
+					return Err(StreamError::MalformedFrame)
+				}
+				return Ok(None)
+			},
+		};
+
+		let digits = &self.buf[..colon];
+		if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+			return Err(StreamError::MalformedFrame)
+		}
+
+		let len : usize = match str::from_utf8(digits).ok().and_then(|s| s.parse().ok()) {
+			Some(n) => n,
+			None => return Err(StreamError::FrameTooLarge),
+		};
+
+		if len > self.max_frame {
+			return Err(StreamError::FrameTooLarge)
+		}
+
+		let start = colon + 1;
+		let end = match start.checked_add(len) {
+			Some(e) => e,
+			None => return Err(StreamError::FrameTooLarge),
+		};
+		if self.buf.len() <= end {
+			return Ok(None)
+		}
+
+		if self.buf[end] != b',' {
+			return Err(StreamError::MalformedFrame)
+		}
+
+		let frame = self.buf[start..end].to_vec();
+		self.buf.drain(..=end);
+		Ok(Some(frame))
+	}
+}
+
+/// Reads framed messages from a stream
+pub struct MessageReader<R: Read> {
+	inner: R,
+	decoder: FrameDecoder,
+	failed: bool,
+}
+
+impl<R: Read> MessageReader<R> {
+	/// A reader accepting frames of up to `MAX_FRAME` bytes
+	pub fn new(inner: R) -> MessageReader<R> {
+		MessageReader {
+			inner,
+			decoder: FrameDecoder::new(),
+			failed: false,
+		}
+	}
+
+	pub fn with_max_frame(mut self, limit: usize) -> MessageReader<R> {
+		self.decoder = self.decoder.with_max_frame(limit);
+		self
+	}
+
+	/// Read the next message, or `None` if the stream closed
+	/// between frames
+	///
+	/// A frame that is not a valid message fails with
+	/// `StreamError::Parse` but is consumed, so the following
+	/// messages can still be read
+	pub fn read_message(&mut self) -> Result<Option<Message>, StreamError> {
+		match self.read_frame()? {
+			Some(frame) => Ok(Some(Message::from_bytes(&frame)?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Read the bytes of the next frame, or `None` if the
+	/// stream closed between frames
+	pub fn read_frame(&mut self) -> Result<Option<Vec<u8>>, StreamError> {
+		let mut chunk = [0; 4096];
+
+		loop {
+			if let Some(frame) = self.decoder.next_frame()? {
+				return Ok(Some(frame))
+			}
+
+			match self.inner.read(&mut chunk) {
+				Ok(0) if self.decoder.is_empty() => return Ok(None),
+				Ok(0) => return Err(StreamError::Closed),
+				Ok(n) => self.decoder.feed(&chunk[..n]),
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(StreamError::from(e)),
+			}
+		}
+	}
+
+	pub fn get_ref(&self) -> &R {
+		&self.inner
+	}
+
+	pub fn get_mut(&mut self) -> &mut R {
+		&mut self.inner
+	}
+
+	/// The underlying stream; any bytes already read but not
+	/// yet decoded are lost
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+/// Iterates over the messages until the stream closes or
+/// fails; a message that does not parse does not end the
+/// iteration
+impl<R: Read> Iterator for MessageReader<R> {
+	type Item = Result<Message, StreamError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None
+		}
+
+		let r = self.read_message().transpose();
+		if let Some(Err(ref e)) = r {
+			self.failed = !matches!(*e, StreamError::Parse(_));
+		}
+		r
+	}
+}
+
+/// Writes framed messages to a stream
+pub struct MessageWriter<W: Write> {
+	inner: W,
+}
+
+impl<W: Write> MessageWriter<W> {
+	pub fn new(inner: W) -> MessageWriter<W> {
+		MessageWriter {
+			inner,
+		}
+	}
+
+	/// Write a message as a single frame
+	pub fn write_message(&mut self, msg: &Message) -> io::Result<()> {
+		self.write_frame(&msg.to_bytes())
+	}
+
+	/// Write bytes as a single frame
+	pub fn write_frame(&mut self, bytes: &[u8]) -> io::Result<()> {
+		self.inner.write_all(&encode_frame(bytes))
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+
+	pub fn get_ref(&self) -> &W {
+		&self.inner
+	}
+
+	pub fn get_mut(&mut self) -> &mut W {
+		&mut self.inner
+	}
+
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
@@ -0,0 +1,272 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `token`
+//!
+//! Issues and checks the registration tokens held in a
+//! `Netspace`.
+//!
+//! A token is issued for a single springname and is only
+//! good for registering that node. The time it expires is
+//! part of the token itself, so the netspace only has to
+//! store the token and springname as before:
+//!
+//! `<32 hex digits>.<expiry in seconds since the epoch>`
+//!
+//! Consuming a token removes it from the netspace so it
+//! cannot be used for a second registration.
+//!
+//! # Example
+//!
+//! ```
+//! use spring_dvs::protocol::{ContentRegistration, ProtocolObject};
+//! use spring_dvs::spaces::MemoryNetspace;
+//! use spring_dvs::token::{TokenIssuer, TokenFailure};
+//!
+//! let nio = MemoryNetspace::new();
+//! let tokens = TokenIssuer::new(&nio);
+//! let token = tokens.issue("foo").unwrap();
+//!
+//! let reg = format!("foo,foo.tld;org;http;{}\nKEY", token);
+//! let reg = ContentRegistration::from_bytes(reg.as_bytes()).unwrap();
+//!
+//! assert!(tokens.consume(&reg).is_ok());
+//! assert_eq!(tokens.consume(&reg), Err(TokenFailure::Unknown));
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::names::to_ascii;
+use crate::protocol::ContentRegistration;
+use crate::spaces::Netspace;
+
+/// How long an issued token is valid for by default
+pub const DEFAULT_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+const RANDOM_BYTES: usize = 16;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TokenFailure {
+	/// The token was never issued or has been used
+	Unknown,
+
+	/// The token is not in the issued format
+	Malformed,
+
+	/// The token has expired
+	Expired,
+
+	/// The token was issued for a different springname
+	WrongSpringname,
+
+	/// No randomness was available to generate a token
+	RandomFailure,
+}
+
+impl fmt::Display for TokenFailure {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			TokenFailure::Unknown => write!(f, "unknown token"),
+			TokenFailure::Malformed => write!(f, "malformed token"),
+			TokenFailure::Expired => write!(f, "expired token"),
+			TokenFailure::WrongSpringname => write!(f, "token issued for another springname"),
+			TokenFailure::RandomFailure => write!(f, "no randomness available"),
+		}
+	}
+}
+
+impl std::error::Error for TokenFailure {}
+
+/// A registration token and the time it expires
+#[derive(Clone, PartialEq, Debug)]
+pub struct Token {
+	value: String,
+	expires: SystemTime,
+}
+
+impl Token {
+	/// Generate a random token that expires `lifetime` after
+	/// `now`
+	pub fn generate(now: SystemTime, lifetime: Duration) -> Result<Token, TokenFailure> {
+		let mut bytes = [0u8; RANDOM_BYTES];
+		if getrandom::getrandom(&mut bytes).is_err() {
+			return Err(TokenFailure::RandomFailure)
+		}
+
+		// Expiry is held to the second
+		let expires = epoch_secs(now + lifetime);
+		let random : String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+		Ok(Token {
+			value: format!("{}.{}", random, expires),
+			expires: UNIX_EPOCH + Duration::from_secs(expires),
+		})
+	}
+
+	/// Read a token, failing if it is not in the issued format
+	pub fn parse(value: &str) -> Result<Token, TokenFailure> {
+		let (random, expires) = match value.split_once('.') {
+			Some(p) => p,
+			None => return Err(TokenFailure::Malformed),
+		};
+
+		if random.len() != RANDOM_BYTES * 2 || !random.bytes().all(|b| b.is_ascii_hexdigit()) {
+			return Err(TokenFailure::Malformed)
+		}
+
+		if expires.is_empty() || !expires.bytes().all(|b| b.is_ascii_digit()) {
+			return Err(TokenFailure::Malformed)
+		}
+
+		let expires = match expires.parse().ok().and_then(|s| UNIX_EPOCH.checked_add(Duration::from_secs(s))) {
+			Some(t) => t,
+			None => return Err(TokenFailure::Malformed),
+		};
+
+		Ok(Token {
+			value: String::from(value),
+			expires,
+		})
+	}
+
+	pub fn value(&self) -> &str {
+		&self.value
+	}
+
+	pub fn expires(&self) -> SystemTime {
+		self.expires
+	}
+
+	/// Whether the token has expired at the time `now`
+	pub fn is_expired(&self, now: SystemTime) -> bool {
+		now >= self.expires
+	}
+}
+
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.value)
+	}
+}
+
+fn epoch_secs(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Issues and checks registration tokens held in a netspace
+pub struct TokenIssuer<'a, N: Netspace + 'a> {
+	nio: &'a N,
+	lifetime: Duration,
+}
+
+impl<'a, N: Netspace + 'a> TokenIssuer<'a, N> {
+	/// Issue tokens that are valid for `DEFAULT_LIFETIME`
+	pub fn new(nio: &'a N) -> TokenIssuer<'a, N> {
+		TokenIssuer {
+			nio,
+			lifetime: DEFAULT_LIFETIME,
+		}
+	}
+
+	pub fn with_lifetime(mut self, lifetime: Duration) -> TokenIssuer<'a, N> {
+		self.lifetime = lifetime;
+		self
+	}
+
+	/// Issue a token for registering `springname`, which may
+	/// be given in Unicode
+	///
+	/// Any tokens previously issued for the springname are
+	/// revoked
+	pub fn issue(&self, springname: &str) -> Result<Token, TokenFailure> {
+		self.issue_at(springname, SystemTime::now())
+	}
+
+	/// Issue a token as if the time were `now`
+	pub fn issue_at(&self, springname: &str, now: SystemTime) -> Result<Token, TokenFailure> {
+		let token = Token::generate(now, self.lifetime)?;
+		let springname = to_ascii(springname).unwrap_or(Cow::Borrowed(springname));
+
+		self.nio.gsn_remove_token_by_springname(&springname);
+		self.nio.gsn_add_token(token.value(), &springname);
+
+		Ok(token)
+	}
+
+	/// Check the token of a registration without using it
+	pub fn validate(&self, reg: &ContentRegistration) -> Result<Token, TokenFailure> {
+		self.validate_at(reg, SystemTime::now())
+	}
+
+	/// Check the token of a registration as if the time were
+	/// `now`
+	pub fn validate_at(&self, reg: &ContentRegistration, now: SystemTime) -> Result<Token, TokenFailure> {
+		let token = Token::parse(&reg.token)?;
+
+		let springname = match self.nio.gsn_token(&reg.token) {
+			Some(s) => s,
+			None => return Err(TokenFailure::Unknown),
+		};
+
+		if springname != reg.ndouble.spring {
+			return Err(TokenFailure::WrongSpringname)
+		}
+
+		if token.is_expired(now) {
+			return Err(TokenFailure::Expired)
+		}
+
+		Ok(token)
+	}
+
+	/// Check the token of a registration and remove it from
+	/// the netspace so it cannot be used again
+	pub fn consume(&self, reg: &ContentRegistration) -> Result<Token, TokenFailure> {
+		self.consume_at(reg, SystemTime::now())
+	}
+
+	/// Consume the token of a registration as if the time were
+	/// `now`
+	///
+	/// Removing the token is the final check: if a concurrent
+	/// registration removed it first this fails with `Unknown`
+	pub fn consume_at(&self, reg: &ContentRegistration, now: SystemTime) -> Result<Token, TokenFailure> {
+		let token = self.validate_at(reg, now)?;
+
+		if !self.nio.gsn_remove_token(token.value()) {
+			return Err(TokenFailure::Unknown)
+		}
+
+		Ok(token)
+	}
+
+	/// Remove expired tokens from the netspace, returning how
+	/// many were removed
+	///
+	/// Tokens in another format, such as those added to the
+	/// netspace by hand, carry no expiry and are left alone.
+	pub fn purge_expired(&self) -> usize {
+		self.purge_expired_at(SystemTime::now())
+	}
+
+	/// Remove tokens that are expired at the time `now`
+	pub fn purge_expired_at(&self, now: SystemTime) -> usize {
+		let mut removed = 0;
+
+		for (value, _) in self.nio.gsn_tokens() {
+			let expired = match Token::parse(&value) {
+				Ok(t) => t.is_expired(now),
+				Err(_) => false,
+			};
+
+			if expired && self.nio.gsn_remove_token(&value) {
+				removed += 1;
+			}
+		}
+
+		removed
+	}
+}
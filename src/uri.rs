@@ -1,74 +1,177 @@
+//! Module `uri`
+//!
+//! Spring URIs name a node by its route through the network,
+//! optionally followed by a resource path, a query and a
+//! fragment:
+//!
+//! `spring://cci.esusx.uk/res/home?query=test#top`
+//!
+//! Resource segments and the fragment are percent-encoded in
+//! the text form of a URI and held decoded. The query is held
+//! as it was given, and its keys and values are decoded by
+//! `query_map`. Spaces are accepted without encoding.
+//!
+//! # Example
+//!
+//! ```
+//! use spring_dvs::uri::{Uri, UriBuilder};
+//!
+//! let uri = UriBuilder::new()
+//!     .with_route("cci.esusx.uk")
+//!     .with_resource("open days")
+//!     .with_query("from", "a&b")
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(uri.to_string(), "spring://cci.esusx.uk/open%20days?from=a%26b");
+//! assert_eq!(Uri::parse(&uri.to_string()).unwrap(), uri);
+//! assert_eq!(uri.query_param("from").unwrap(), "a&b");
+//! ```
+
 use std::str;
 use std::fmt;
+use std::borrow::Cow;
 use std::collections::HashMap;
-pub use ::enums::Failure;
-pub use ::node::Node;
+pub use crate::enums::Failure;
+pub use crate::node::Node;
+use crate::names::{is_label, to_ascii, to_unicode};
+
+const SCHEME: &str = "spring://";
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+/// The part of a URI that is not valid
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum UriError {
+	/// The URI does not start with `spring://`
+	Scheme,
+
+	/// One of the route labels is not valid
+	Route,
+
+	/// A resource segment is not correctly percent-encoded
+	Resource,
+
+	/// The query is not correctly percent-encoded
+	Query,
+
+	/// The fragment is not correctly percent-encoded
+	Fragment,
+}
+
+impl fmt::Display for UriError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			UriError::Scheme => write!(f, "scheme is not spring://"),
+			UriError::Route => write!(f, "invalid route"),
+			UriError::Resource => write!(f, "invalid resource"),
+			UriError::Query => write!(f, "invalid query"),
+			UriError::Fragment => write!(f, "invalid fragment"),
+		}
+	}
+}
 
+impl std::error::Error for UriError {}
+
+impl From<UriError> for Failure {
+	fn from(_: UriError) -> Failure {
+		Failure::InvalidFormat
+	}
+}
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Uri {
 	
 	gsn: Vec<String>,
-	gtn: String,
 	res: Vec<String>,
 	query: String,
+	fragment: String,
 }
 
 impl Uri {
 	
+	/// Parse a URI, failing with `Failure::InvalidFormat`
+	///
+	/// `Uri::parse` gives the reason a URI is not valid
 	pub fn new(uri: &str) -> Result<Uri, Failure> {
+		Ok(Uri::parse(uri)?)
+	}
+	
+	/// Parse a URI
+	///
+	/// Empty route labels and resource segments are skipped.
+	/// Route labels are held in lowercase and, with the `idna`
+	/// feature, may be in Unicode and are held as punycode.
+	///
+	/// The route may be empty, as it is once every label has
+	/// been taken from it.
+	pub fn parse(uri: &str) -> Result<Uri, UriError> {
+		let rest = match uri.get(..SCHEME.len()) {
+			Some(s) if s.eq_ignore_ascii_case(SCHEME) => &uri[SCHEME.len()..],
+			_ => return Err(UriError::Scheme),
+		};
 		
-		let initial : Vec<&str> = uri.split("://").collect();
-
-		if initial[0] != "spring" || initial.len() < 2 {
-			return Err(Failure::InvalidFormat)
-		}
+		let (rest, fragment) = rest.split_once('#').unwrap_or((rest, ""));
+		let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+		let (route, path) = rest.split_once('/').unwrap_or((rest, ""));
 		
-		if initial[1].len() == 0 {
-			return Err(Failure::InvalidFormat)
-		}
-
 		let mut gsn : Vec<String> = Vec::new();
-		let mut res: Vec<String> = Vec::new();
-		let mut query: &str = "";
-
-
-		let atoms : Vec<&str> = initial[1].split('?').collect();
-		if atoms.len() > 1 {
-			query = atoms[1]
-		}
-
-		let atoms : Vec<&str> = atoms[0].split('/').collect();
-
-		if atoms.len() > 1 {
-			for i in 1 .. atoms.len() {
-				if atoms[i].is_empty() { continue } 
-				res.push(String::from(atoms[i]))
-			}
+		for s in route.split('.').filter(|s| !s.is_empty()) {
+			gsn.push(route_label(s)?)
 		}
 		
+		let mut res : Vec<String> = Vec::new();
+		for s in path.split('/').filter(|s| !s.is_empty()) {
+			res.push(percent_decode(s).ok_or(UriError::Resource)?)
+		}
 		
-		let v : Vec<&str> = atoms[0].split('.').collect();
-		
-		
-		let gtn = match v[v.len()-1] {
-			"uk" => "uk",
-			_ => ""
-		};
-		
-		for s in v {
-			if s.is_empty() { continue };
-			
-			gsn.push(String::from(s))
+		if percent_decode(query).is_none() {
+			return Err(UriError::Query)
 		}
 		
+		let fragment = percent_decode(fragment).ok_or(UriError::Fragment)?;
+		
 		Ok(Uri {
-			gsn: gsn,
-			gtn: String::from(gtn),
-			res: res,
+			gsn,
+			res,
 			query: String::from(query),
+			fragment,
 		})
 	}
+	
+	/// The URI in its normal form
+	///
+	/// Route labels are lowercased, `.` and `..` resource
+	/// segments are resolved, empty query parameters are
+	/// dropped and the rest are percent-encoded only where
+	/// they need to be, with uppercase hex digits.
+	pub fn normalise(&self) -> Uri {
+		let gsn : Vec<String> = self.gsn.iter().map(|l| l.to_ascii_lowercase()).collect();
+		
+		let mut res : Vec<String> = Vec::new();
+		for s in &self.res {
+			match s.as_str() {
+				"." => {},
+				".." => { res.pop(); },
+				_ => res.push(s.clone()),
+			}
+		}
+		
+		let query : Vec<String> = self.query.split('&')
+			.filter(|p| !p.is_empty())
+			.map(|p| match p.split_once('=') {
+				Some((k, v)) => format!("{}={}", encode_query(&decode_lossy(k)), encode_query(&decode_lossy(v))),
+				None => encode_query(&decode_lossy(p)).into_owned(),
+			})
+			.collect();
+		
+		Uri {
+			gsn,
+			res,
+			query: query.join("&"),
+			fragment: self.fragment.clone(),
+		}
+	}
 		
 	pub fn route(&self) -> &Vec<String> {
 		&self.gsn
@@ -78,9 +181,18 @@ impl Uri {
 		&mut self.gsn
 	} 
 	
+	/// The labels of the route in Unicode for display
+	pub fn route_unicode(&self) -> Vec<String> {
+		self.gsn.iter().map(|l| to_unicode(l).into_owned()).collect()
+	}
+	
 	
+	/// The top level of the route, if it is a known GTN
 	pub fn gtn(&self) -> &str {
-		&self.gtn
+		match self.gsn.last().map(String::as_str) {
+			Some("uk") => "uk",
+			_ => "",
+		}
 	}
 
 
@@ -88,6 +200,10 @@ impl Uri {
 		&self.query
 	}
 	
+	pub fn fragment(&self) -> &str {
+		&self.fragment
+	}
+	
 	pub fn res(&self) -> &Vec<String> {
 		&self.res
 	}
@@ -103,24 +219,18 @@ impl Uri {
 		format!("{}", self)
 	}
 	
+	/// The parameters of the query with their keys and values
+	/// decoded, or `None` if there is no query
 	pub fn query_map(&self) -> Option<HashMap<String, String>> {
 		if self.query.is_empty() { return None }
 		
-		
-		
 		let mut m = HashMap::new();
 			 
-		for val in self.query.split("&") {
-			let (k,v) = val.split_at(match val.find("=") {
-					None => val.len(),
-					Some(i) => i, 
-				});
+		for val in self.query.split('&') {
+			if val.is_empty() { continue }
 			
-			if v.len() > 0 {				 
-				m.insert(String::from(k),String::from(&v[1..])); 
-			} else { 
-				m.insert(String::from(k),String::from(v));
-			}
+			let (k,v) = val.split_once('=').unwrap_or((val, ""));
+			m.insert(decode_lossy(k).into_owned(), decode_lossy(v).into_owned());
 		}
 			
 		Some(m)
@@ -140,51 +250,209 @@ impl Uri {
 	
 }
 
-impl Clone for Uri {
-	fn clone(&self) -> Uri {
-		Uri {
-			gsn: (&self).gsn.clone(),
-			gtn: (&self).gtn.to_string(),
-			res: (&self).res.clone(),
-			query: (&self).query.to_string()
+impl fmt::Display for Uri {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}", SCHEME, self.gsn.join("."))?;
+		
+		for p in &self.res {
+			if p.is_empty() { continue }
+			
+			write!(f, "/{}", encode(p, is_pchar))?;
+		}
+
+		if !self.query.is_empty() {
+			write!(f, "?{}", self.query)?;
+		}
+		
+		if !self.fragment.is_empty() {
+			write!(f, "#{}", encode(&self.fragment, is_fragment_char))?;
 		}
+		
+		Ok(())
 	}
+}
 
-	fn clone_from(&mut self, source: &Uri)  {
-			self.gsn = source.route().clone();
-			self.gtn = source.gtn().to_string();
-			self.res = source.res().clone();
-			self.query = source.query().to_string();
+/// A URI is serialised as its string form
+#[cfg(feature = "serde")]
+impl serde::Serialize for Uri {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
 	}
 }
 
-impl fmt::Display for Uri {
-	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		let mut s = "spring://".to_string();
-		let last = self.gsn.len()-1;
-		
-		for i in 0 .. last {
-			s.push_str(&self.gsn[i]);
-			s.push('.');
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Uri {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Uri, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		Uri::parse(&s).map_err(|e| serde::de::Error::custom(format_args!("invalid uri `{}`: {}", s, e)))
+	}
+}
+
+
+/// Builds a URI from its parts
+///
+/// The parts are given decoded and are percent-encoded where
+/// they need to be. Empty labels and resource segments are
+/// skipped, as they are when parsing.
+#[derive(Clone, Debug, Default)]
+pub struct UriBuilder {
+	gsn: Vec<String>,
+	res: Vec<String>,
+	query: Vec<String>,
+	fragment: String,
+}
+
+impl UriBuilder {
+	pub fn new() -> UriBuilder {
+		UriBuilder::default()
+	}
+	
+	/// Add a label to the end of the route
+	pub fn with_label(mut self, label: &str) -> UriBuilder {
+		self.gsn.push(String::from(label));
+		self
+	}
+	
+	/// Add each label of a route such as `cci.esusx.uk`
+	pub fn with_route(mut self, route: &str) -> UriBuilder {
+		self.gsn.extend(route.split('.').map(String::from));
+		self
+	}
+	
+	/// Add a segment to the end of the resource path
+	pub fn with_resource(mut self, segment: &str) -> UriBuilder {
+		self.res.push(String::from(segment));
+		self
+	}
+	
+	/// Add a parameter to the query
+	pub fn with_query(mut self, key: &str, value: &str) -> UriBuilder {
+		self.query.push(format!("{}={}", encode_query(key), encode_query(value)));
+		self
+	}
+	
+	pub fn with_fragment(mut self, fragment: &str) -> UriBuilder {
+		self.fragment = String::from(fragment);
+		self
+	}
+	
+	/// Fails with `UriError::Route` if the route is empty or
+	/// one of its labels is not valid
+	pub fn build(self) -> Result<Uri, UriError> {
+		let mut gsn : Vec<String> = Vec::new();
+		for s in self.gsn.iter().filter(|s| !s.is_empty()) {
+			gsn.push(route_label(s)?)
 		}
 		
-		s.push_str(&self.gsn[last]);
+		if gsn.is_empty() {
+			return Err(UriError::Route)
+		}
 		
+		Ok(Uri {
+			gsn,
+			res: self.res.into_iter().filter(|s| !s.is_empty()).collect(),
+			query: self.query.join("&"),
+			fragment: self.fragment,
+		})
+	}
+}
 
-		if self.res.len() > 0 {
-			for p in &self.res {
-				if p.is_empty() { continue }
-				
-				s.push('/');
-				s.push_str(p.as_str());
-			}
+/// Parse a URI and write it in its normal form
+pub fn normalise(uri: &str) -> Result<String, UriError> {
+	Ok(Uri::parse(uri)?.normalise().to_string())
+}
+
+/// Decode the percent-encoded bytes of `s`
+///
+/// Fails if an escape is malformed, the decoded bytes are not
+/// UTF-8 or `s` has a control character that should have been
+/// encoded. Spaces are taken as they are.
+pub fn percent_decode(s: &str) -> Option<String> {
+	let b = s.as_bytes();
+	let mut out = Vec::with_capacity(b.len());
+	let mut i = 0;
+	
+	while i < b.len() {
+		match b[i] {
+			b'%' => {
+				let hi = hex_value(*b.get(i+1)?)?;
+				let lo = hex_value(*b.get(i+2)?)?;
+				out.push(hi << 4 | lo);
+				i += 3;
+			},
+			c if c < b' ' || c == 0x7f => return None,
+			c => {
+				out.push(c);
+				i += 1;
+			},
 		}
+	}
+	
+	String::from_utf8(out).ok()
+}
+
+/// Percent-encode every byte of `s` other than the unreserved
+/// characters of RFC 3986
+pub fn percent_encode(s: &str) -> String {
+	encode(s, is_unreserved).into_owned()
+}
+
+fn route_label(s: &str) -> Result<String, UriError> {
+	match to_ascii(s) {
+		Some(l) if is_label(&l) => Ok(l.into_owned()),
+		_ => Err(UriError::Route),
+	}
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+	(c as char).to_digit(16).map(|d| d as u8)
+}
+
+fn decode_lossy(s: &str) -> Cow<'_, str> {
+	match percent_decode(s) {
+		Some(d) => Cow::Owned(d),
+		None => Cow::Borrowed(s),
+	}
+}
 
-		if self.query.len() > 0 {
-			s.push('?');
-			s.push_str(&self.query);
+fn encode(s: &str, keep: fn(u8) -> bool) -> Cow<'_, str> {
+	if s.bytes().all(keep) {
+		return Cow::Borrowed(s)
+	}
+	
+	let mut out = String::with_capacity(s.len() * 3);
+	for c in s.bytes() {
+		if keep(c) {
+			out.push(c as char);
+		} else {
+			out.push('%');
+			out.push(HEX[(c >> 4) as usize] as char);
+			out.push(HEX[(c & 15) as usize] as char);
 		}
-		write!(f,"{}", s)
-		
 	}
-}
\ No newline at end of file
+	
+	Cow::Owned(out)
+}
+
+fn encode_query(s: &str) -> Cow<'_, str> {
+	encode(s, is_query_char)
+}
+
+fn is_unreserved(c: u8) -> bool {
+	c.is_ascii_alphanumeric() || matches!(c, b'-' | b'.' | b'_' | b'~')
+}
+
+fn is_pchar(c: u8) -> bool {
+	is_unreserved(c) || matches!(c, b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' | b':' | b'@')
+}
+
+/// Characters left as they are in query keys and values, which
+/// excludes the `&` and `=` that separate them and the `+`
+/// that some decoders read as a space
+fn is_query_char(c: u8) -> bool {
+	(is_pchar(c) && !matches!(c, b'&' | b'=' | b'+')) || matches!(c, b'/' | b'?')
+}
+
+fn is_fragment_char(c: u8) -> bool {
+	is_pchar(c) || matches!(c, b'/' | b'?')
+}
@@ -0,0 +1,630 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Module `wire`
+//!
+//! A compact binary encoding of `Message` for links where the
+//! text protocol is too costly. Every message and content type
+//! implements `NetSerial`, and a message decoded from its
+//! binary form encodes to the same text as the original.
+//!
+//! The encoding is a command byte followed by the content of
+//! the message:
+//!
+//! * Strings are a length followed by UTF-8 bytes
+//! * Lengths and counts are unsigned LEB128 varints
+//! * Roles, services, states and response codes are a byte
+//! * Names and addresses are validated as in the text format
+//!
+//! The command byte is never a printable character, so a
+//! receiver can tell the binary form from text with
+//! `is_binary`.
+//!
+//! # Example
+//!
+//! ```
+//! use spring_dvs::protocol::{Message, ProtocolObject};
+//! use spring_dvs::serialise::NetSerial;
+//!
+//! let msg = Message::from_bytes(b"info node foo state").unwrap();
+//! let bytes = msg.serialise();
+//! assert!(bytes.len() < msg.to_bytes().len());
+//!
+//! let decoded = Message::deserialise(&bytes).unwrap();
+//! assert_eq!(decoded.to_bytes(), msg.to_bytes());
+//! ```
+
+use std::str;
+
+use crate::enums::Failure;
+use crate::formats::{NodeSingleFmt, NodeDoubleFmt, NodeQuadFmt, NodeInfoFmt, canonical_address};
+use crate::names::{is_springname, is_hostfield};
+use crate::protocol::*;
+use crate::serialise::NetSerial;
+use crate::uri::Uri;
+
+const CMD_REGISTER: u8 = 1;
+const CMD_UNREGISTER: u8 = 2;
+const CMD_INFO: u8 = 3;
+const CMD_UPDATE: u8 = 4;
+const CMD_RESOLVE: u8 = 5;
+const CMD_SERVICE: u8 = 6;
+const CMD_RESPONSE: u8 = 7;
+
+const INFO_NETWORK: u8 = 0;
+const INFO_NODE: u8 = 1;
+
+const PROPERTY_ALL: u8 = 0;
+const PROPERTY_HOSTNAME: u8 = 1;
+const PROPERTY_ADDRESS: u8 = 2;
+const PROPERTY_STATE: u8 = 3;
+const PROPERTY_SERVICE: u8 = 4;
+const PROPERTY_ROLE: u8 = 5;
+
+/// An unset value of a node property
+const PROPERTY_UNSET: u8 = 0xff;
+
+const RESPONSE_EMPTY: u8 = 0;
+const RESPONSE_NODE_SINGLE: u8 = 1;
+const RESPONSE_NETWORK: u8 = 2;
+const RESPONSE_NODE_INFO: u8 = 3;
+const RESPONSE_SERVICE_TEXT: u8 = 4;
+const RESPONSE_SERVICE_MULTI: u8 = 5;
+const RESPONSE_RESOLUTION: u8 = 6;
+
+const RESOLUTION_NODE: u8 = 0;
+const RESOLUTION_REFERRAL: u8 = 1;
+
+/// Whether bytes are a binary encoded message rather than
+/// text
+pub fn is_binary(bytes: &[u8]) -> bool {
+	match bytes.first() {
+		Some(b) => (CMD_REGISTER..=CMD_RESPONSE).contains(b),
+		None => false,
+	}
+}
+
+/// Reads values from the front of a byte slice
+struct Reader<'a> {
+	bytes: &'a [u8],
+	index: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn new(bytes: &'a [u8]) -> Reader<'a> {
+		Reader {
+			bytes,
+			index: 0,
+		}
+	}
+
+	fn u8(&mut self) -> Result<u8, Failure> {
+		match self.bytes.get(self.index) {
+			Some(b) => {
+				self.index += 1;
+				Ok(*b)
+			},
+			None => Err(Failure::OutOfBounds),
+		}
+	}
+
+	fn varint(&mut self) -> Result<u64, Failure> {
+		let mut value : u64 = 0;
+
+		for shift in (0..64).step_by(7) {
+			let b = self.u8()?;
+			value |= u64::from(b & 0x7f) << shift;
+
+			if b & 0x80 == 0 {
+				return Ok(value)
+			}
+		}
+
+		Err(Failure::InvalidFormat)
+	}
+
+	fn len(&mut self) -> Result<usize, Failure> {
+		let len = self.varint()? as usize;
+
+		// A length can never exceed what is left to read
+		if len > self.bytes.len() - self.index {
+			return Err(Failure::OutOfBounds)
+		}
+
+		Ok(len)
+	}
+
+	fn str(&mut self) -> Result<&'a str, Failure> {
+		let len = self.len()?;
+		let bytes = &self.bytes[self.index..self.index+len];
+		self.index += len;
+
+		str::from_utf8(bytes).map_err(|_| Failure::InvalidBytes)
+	}
+
+	fn string(&mut self) -> Result<String, Failure> {
+		self.str().map(String::from)
+	}
+
+	/// Fail if anything is left unread
+	fn finish(&self) -> Result<(), Failure> {
+		if self.index != self.bytes.len() {
+			return Err(Failure::InvalidBytes)
+		}
+
+		Ok(())
+	}
+}
+
+fn write_varint(v: &mut Vec<u8>, mut value: u64) {
+	loop {
+		let b = (value & 0x7f) as u8;
+		value >>= 7;
+
+		if value == 0 {
+			v.push(b);
+			return
+		}
+
+		v.push(b | 0x80);
+	}
+}
+
+fn write_str(v: &mut Vec<u8>, s: &str) {
+	write_varint(v, s.len() as u64);
+	v.extend_from_slice(s.as_bytes());
+}
+
+fn format_failure(_: ParseFailure) -> Failure {
+	Failure::InvalidFormat
+}
+
+/// Read a name, failing as the text format does if `valid`
+/// rejects it; an empty name is only read if `optional`
+fn read_name(r: &mut Reader, valid: fn(&str) -> bool, optional: bool) -> Result<String, Failure> {
+	let name = r.string()?;
+
+	if optional && name.is_empty() {
+		return Ok(name)
+	}
+
+	if !valid(&name) {
+		return Err(format_failure(ParseFailure::InvalidNaming))
+	}
+
+	Ok(name)
+}
+
+fn read_role(r: &mut Reader) -> Result<NodeRole, Failure> {
+	match r.u8()? {
+		0 => Ok(NodeRole::Undefined),
+		1 => Ok(NodeRole::Hub),
+		2 => Ok(NodeRole::Org),
+		3 => Ok(NodeRole::Hybrid),
+		_ => Err(Failure::InvalidConversion),
+	}
+}
+
+fn read_service(r: &mut Reader) -> Result<NodeService, Failure> {
+	match r.u8()? {
+		0 => Ok(NodeService::Undefined),
+		1 => Ok(NodeService::Dvsp),
+		2 => Ok(NodeService::Http),
+		_ => Err(Failure::InvalidConversion),
+	}
+}
+
+fn read_state(r: &mut Reader) -> Result<NodeState, Failure> {
+	match r.u8()? {
+		0 => Ok(NodeState::Disabled),
+		1 => Ok(NodeState::Enabled),
+		2 => Ok(NodeState::Unresponsive),
+		3 => Ok(NodeState::Unspecified),
+		_ => Err(Failure::InvalidConversion),
+	}
+}
+
+fn code_byte(code: Response) -> u8 {
+	match code {
+		Response::NetspaceError => 101,
+		Response::NetspaceDuplication => 102,
+		Response::NetworkError => 103,
+		Response::MalformedContent => 104,
+		Response::UnsupportedAction => 121,
+		Response::UnsupportedService => 122,
+		Response::Ok => 200,
+		Response::Chunk => 201,
+		Response::Eot => 202,
+	}
+}
+
+fn read_code(r: &mut Reader) -> Result<Response, Failure> {
+	let code = r.u8()?;
+	Response::from_str(&code.to_string()).ok_or(Failure::InvalidConversion)
+}
+
+fn write_quads(v: &mut Vec<u8>, network: &[NodeQuadFmt]) {
+	write_varint(v, network.len() as u64);
+
+	for n in network {
+		write_str(v, &n.spring);
+		write_str(v, &n.host);
+		write_str(v, &n.address.to_string());
+		v.push(n.service as u8);
+	}
+}
+
+fn read_quads(r: &mut Reader) -> Result<Vec<NodeQuadFmt>, Failure> {
+	let count = r.len()?;
+	let mut network = Vec::with_capacity(count);
+
+	for _ in 0..count {
+		let spring = r.str()?;
+		let host = r.str()?;
+		let address = r.str()?;
+		let service = read_service(r)?;
+
+		let quad = format!("{},{},{},{}", spring, host, address, service);
+		network.push(NodeQuadFmt::from_str(&quad).map_err(format_failure)?);
+	}
+
+	Ok(network)
+}
+
+/// Encoding of a type within a message
+trait Wire : Sized {
+	fn write(&self, v: &mut Vec<u8>);
+	fn read(r: &mut Reader) -> Result<Self, Failure>;
+}
+
+macro_rules! net_serial {
+	($t:ty, $lower:expr) => (
+		impl NetSerial for $t {
+			fn serialise(&self) -> Vec<u8> {
+				let mut v = Vec::new();
+				self.write(&mut v);
+				v
+			}
+
+			fn deserialise(bytes: &[u8]) -> Result<Self, Failure> {
+				if bytes.len() < Self::lower_bound() {
+					return Err(Failure::OutOfBounds)
+				}
+
+				let mut r = Reader::new(bytes);
+				let value = Self::read(&mut r)?;
+				r.finish()?;
+				Ok(value)
+			}
+
+			fn lower_bound() -> usize {
+				$lower
+			}
+		}
+	)
+}
+
+impl Wire for Message {
+	fn write(&self, v: &mut Vec<u8>) {
+		v.push(match self.cmd {
+			CmdType::Register => CMD_REGISTER,
+			CmdType::Unregister => CMD_UNREGISTER,
+			CmdType::Info => CMD_INFO,
+			CmdType::Update => CMD_UPDATE,
+			CmdType::Resolve => CMD_RESOLVE,
+			CmdType::Service => CMD_SERVICE,
+			CmdType::Response => CMD_RESPONSE,
+		});
+
+		match &self.content {
+			MessageContent::Empty => {},
+			MessageContent::Registration(c) => c.write(v),
+			MessageContent::Info(c) => c.write(v),
+			MessageContent::Update(c) => c.write(v),
+			MessageContent::Resolve(c) => c.write(v),
+			MessageContent::NodeSingle(c) => c.write(v),
+			MessageContent::Service(c) => c.write(v),
+			MessageContent::Response(c) => c.write(v),
+		}
+	}
+
+	fn read(r: &mut Reader) -> Result<Message, Failure> {
+		let (cmd, content) = match r.u8()? {
+			CMD_REGISTER => (CmdType::Register, MessageContent::Registration(ContentRegistration::read(r)?)),
+			CMD_UNREGISTER => (CmdType::Unregister, MessageContent::NodeSingle(ContentNodeSingle::read(r)?)),
+			CMD_INFO => (CmdType::Info, MessageContent::Info(ContentInfoRequest::read(r)?)),
+			CMD_UPDATE => (CmdType::Update, MessageContent::Update(ContentNodeProperty::read(r)?)),
+			CMD_RESOLVE => (CmdType::Resolve, MessageContent::Resolve(ContentUri::read(r)?)),
+			CMD_SERVICE => (CmdType::Service, MessageContent::Service(ContentUri::read(r)?)),
+			CMD_RESPONSE => (CmdType::Response, MessageContent::Response(ContentResponse::read(r)?)),
+			_ => return Err(Failure::InvalidConversion),
+		};
+
+		Ok(Message::new(cmd, content))
+	}
+}
+
+impl Wire for ContentRegistration {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_str(v, &self.ndouble.spring);
+		write_str(v, &self.ndouble.host);
+		v.push(self.role as u8);
+		v.push(self.service as u8);
+		write_str(v, &self.token);
+		write_str(v, &self.key);
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentRegistration, Failure> {
+		let spring = r.str()?;
+		let host = r.str()?;
+		let ndouble = NodeDoubleFmt::from_str(&format!("{},{}", spring, host)).map_err(format_failure)?;
+
+		Ok(ContentRegistration {
+			ndouble,
+			role: read_role(r)?,
+			service: read_service(r)?,
+			token: r.string()?,
+			key: r.string()?,
+		})
+	}
+}
+
+impl Wire for ContentNodeSingle {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_str(v, &self.nsingle.spring);
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentNodeSingle, Failure> {
+		Ok(ContentNodeSingle {
+			nsingle: NodeSingleFmt::from_str(r.str()?).map_err(format_failure)?,
+		})
+	}
+}
+
+impl Wire for ContentInfoRequest {
+	fn write(&self, v: &mut Vec<u8>) {
+		match &self.info {
+			InfoContent::Network => v.push(INFO_NETWORK),
+			InfoContent::Node(p) => {
+				v.push(INFO_NODE);
+				p.write(v);
+			},
+		}
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentInfoRequest, Failure> {
+		let info = match r.u8()? {
+			INFO_NETWORK => InfoContent::Network,
+			INFO_NODE => InfoContent::Node(ContentNodeProperty::read(r)?),
+			_ => return Err(Failure::InvalidConversion),
+		};
+
+		Ok(ContentInfoRequest { info })
+	}
+}
+
+impl Wire for ContentNodeProperty {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_str(v, &self.spring);
+
+		let (property, value) = match self.property {
+			NodeProperty::All => (PROPERTY_ALL, None),
+			NodeProperty::Hostname => (PROPERTY_HOSTNAME, None),
+			NodeProperty::Address => (PROPERTY_ADDRESS, None),
+			NodeProperty::State(s) => (PROPERTY_STATE, Some(s.map_or(PROPERTY_UNSET, |s| s as u8))),
+			NodeProperty::Service(s) => (PROPERTY_SERVICE, Some(s.map_or(PROPERTY_UNSET, |s| s as u8))),
+			NodeProperty::Role(s) => (PROPERTY_ROLE, Some(s.map_or(PROPERTY_UNSET, |s| s as u8))),
+		};
+
+		v.push(property);
+		if let Some(value) = value {
+			v.push(value);
+		}
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentNodeProperty, Failure> {
+		let spring = read_name(r, is_springname, false)?;
+
+		let property = match r.u8()? {
+			PROPERTY_ALL => NodeProperty::All,
+			PROPERTY_HOSTNAME => NodeProperty::Hostname,
+			PROPERTY_ADDRESS => NodeProperty::Address,
+			PROPERTY_STATE => NodeProperty::State(read_property(r, read_state)?),
+			PROPERTY_SERVICE => NodeProperty::Service(read_property(r, read_service)?),
+			PROPERTY_ROLE => NodeProperty::Role(read_property(r, read_role)?),
+			_ => return Err(Failure::InvalidConversion),
+		};
+
+		Ok(ContentNodeProperty {
+			spring,
+			property,
+		})
+	}
+}
+
+/// Read the value of a property, which may be unset
+fn read_property<T, F>(r: &mut Reader, read: F) -> Result<Option<T>, Failure>
+	where F: Fn(&mut Reader) -> Result<T, Failure> {
+	if r.bytes.get(r.index) == Some(&PROPERTY_UNSET) {
+		r.index += 1;
+		return Ok(None)
+	}
+
+	read(r).map(Some)
+}
+
+impl Wire for ContentUri {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_str(v, &self.uri.to_string());
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentUri, Failure> {
+		Ok(ContentUri::new(Uri::new(r.str()?)?))
+	}
+}
+
+impl Wire for ContentResponse {
+	fn write(&self, v: &mut Vec<u8>) {
+		v.push(code_byte(self.code));
+		write_varint(v, u64::from(self.len));
+
+		match &self.content {
+			ResponseContent::Empty => v.push(RESPONSE_EMPTY),
+			ResponseContent::NodeSingle(c) => {
+				v.push(RESPONSE_NODE_SINGLE);
+				c.write(v);
+			},
+			ResponseContent::Network(c) => {
+				v.push(RESPONSE_NETWORK);
+				c.write(v);
+			},
+			ResponseContent::NodeInfo(c) => {
+				v.push(RESPONSE_NODE_INFO);
+				c.write(v);
+			},
+			ResponseContent::ServiceText(c) => {
+				v.push(RESPONSE_SERVICE_TEXT);
+				c.write(v);
+			},
+			ResponseContent::ServiceMulti(c) => {
+				v.push(RESPONSE_SERVICE_MULTI);
+				c.write(v);
+			},
+			ResponseContent::Resolution(c) => {
+				v.push(RESPONSE_RESOLUTION);
+				c.write(v);
+			},
+		}
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentResponse, Failure> {
+		let code = read_code(r)?;
+
+		let len = r.varint()?;
+		if len > u64::from(u32::MAX) {
+			return Err(Failure::OutOfBounds)
+		}
+
+		let content = match r.u8()? {
+			RESPONSE_EMPTY => ResponseContent::Empty,
+			RESPONSE_NODE_SINGLE => ResponseContent::NodeSingle(ContentNodeSingle::read(r)?),
+			RESPONSE_NETWORK => ResponseContent::Network(ContentNetwork::read(r)?),
+			RESPONSE_NODE_INFO => ResponseContent::NodeInfo(ContentNodeInfo::read(r)?),
+			RESPONSE_SERVICE_TEXT => ResponseContent::ServiceText(ContentServiceText::read(r)?),
+			RESPONSE_SERVICE_MULTI => ResponseContent::ServiceMulti(ContentServiceMulti::read(r)?),
+			RESPONSE_RESOLUTION => ResponseContent::Resolution(ContentResolution::read(r)?),
+			_ => return Err(Failure::InvalidConversion),
+		};
+
+		Ok(ContentResponse {
+			code,
+			len: len as u32,
+			content,
+		})
+	}
+}
+
+impl Wire for ContentNetwork {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_quads(v, &self.network);
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentNetwork, Failure> {
+		Ok(ContentNetwork {
+			network: read_quads(r)?,
+		})
+	}
+}
+
+impl Wire for ContentNodeInfo {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_str(v, &self.info.spring);
+		write_str(v, &self.info.host);
+		write_str(v, &self.info.address.map(|a| a.to_string()).unwrap_or_default());
+		v.push(self.info.service as u8);
+		v.push(self.info.state as u8);
+		v.push(self.info.role as u8);
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentNodeInfo, Failure> {
+		let mut info = NodeInfoFmt::new();
+		info.spring = read_name(r, is_springname, true)?;
+		info.host = read_name(r, is_hostfield, true)?;
+		info.address = match r.str()? {
+			"" => None,
+			a => Some(canonical_address(a).ok_or(Failure::InvalidFormat)?),
+		};
+		info.service = read_service(r)?;
+		info.state = read_state(r)?;
+		info.role = read_role(r)?;
+
+		Ok(ContentNodeInfo::new(info))
+	}
+}
+
+impl Wire for ContentServiceText {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_str(v, &self.content);
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentServiceText, Failure> {
+		Ok(ContentServiceText::new(r.str()?))
+	}
+}
+
+impl Wire for ContentServiceMulti {
+	fn write(&self, v: &mut Vec<u8>) {
+		write_varint(v, self.parts.len() as u64);
+
+		for part in &self.parts {
+			part.write(v);
+		}
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentServiceMulti, Failure> {
+		let count = r.len()?;
+		let mut parts = Vec::with_capacity(count);
+
+		for _ in 0..count {
+			parts.push(ContentServiceText::read(r)?);
+		}
+
+		Ok(ContentServiceMulti { parts })
+	}
+}
+
+impl Wire for ContentResolution {
+	fn write(&self, v: &mut Vec<u8>) {
+		v.push(match self.kind {
+			ResolutionKind::Node => RESOLUTION_NODE,
+			ResolutionKind::Referral => RESOLUTION_REFERRAL,
+		});
+
+		write_quads(v, &self.network);
+	}
+
+	fn read(r: &mut Reader) -> Result<ContentResolution, Failure> {
+		let kind = match r.u8()? {
+			RESOLUTION_NODE => ResolutionKind::Node,
+			RESOLUTION_REFERRAL => ResolutionKind::Referral,
+			_ => return Err(Failure::InvalidConversion),
+		};
+
+		Ok(ContentResolution::new(kind, read_quads(r)?))
+	}
+}
+
+// Lower bounds are the smallest valid encoding of each type
+net_serial!(Message, 2);
+net_serial!(ContentRegistration, 8);
+net_serial!(ContentNodeSingle, 2);
+net_serial!(ContentInfoRequest, 1);
+net_serial!(ContentNodeProperty, 3);
+net_serial!(ContentUri, 2);
+net_serial!(ContentResponse, 3);
+net_serial!(ContentNetwork, 1);
+net_serial!(ContentNodeInfo, 6);
+net_serial!(ContentServiceText, 1);
+net_serial!(ContentServiceMulti, 1);
+net_serial!(ContentResolution, 2);
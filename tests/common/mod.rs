@@ -0,0 +1,13 @@
+/* Notice:  Copyright 2016, The Care Connections Initiative c.i.c.
+ * Author:  Charlie Fyvie-Gauld (cfg@zunautica.org)
+ * License: GPLv3 (http://www.gnu.org/licenses/gpl-3.0.txt)
+ */
+//! Fixtures shared by the integration tests
+
+use spring_dvs::spaces::*;
+
+/// An enabled org node served over HTTP from
+/// `<spring>.tld/spring`
+pub fn new_node(spring: &str, address: &str) -> Node {
+	Node::new(spring, &format!("{}.tld/spring", spring), address.parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "KEY")
+}
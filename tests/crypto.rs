@@ -0,0 +1,196 @@
+#![cfg(feature = "crypto")]
+extern crate spring_dvs;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use spring_dvs::crypto::*;
+use spring_dvs::dispatch::Dispatcher;
+use spring_dvs::http::{HttpWrapper, RequestReader, Endpoint, Outbound};
+use spring_dvs::protocol::*;
+use spring_dvs::spaces::{Netspace, MemoryNetspace};
+
+fn secret(b: u8) -> NodeKey {
+	NodeKey::from_secret(&[b; 32])
+}
+
+fn peer() -> SocketAddr {
+	SocketAddr::from_str("192.168.1.2:55000").unwrap()
+}
+
+#[test]
+fn ts_crypto_sign_verify_pass() {
+	let key = NodeKey::generate().unwrap();
+	let sig = key.sign(b"info network");
+	assert!(verify(&key.public_key_block(), b"info network", &sig).is_ok());
+	
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	let sig = key.sign_message(&msg);
+	assert!(verify(&key.public_key_block(), &msg.to_bytes(), &sig).is_ok());
+}
+
+#[test]
+fn ts_crypto_sign_verify_fail() {
+	let key = secret(1);
+	let sig = key.sign(b"info network");
+	
+	assert_eq!(verify(&key.public_key_block(), b"info networks", &sig), Err(CryptoFailure::VerificationFailed));
+	assert_eq!(verify(&secret(2).public_key_block(), b"info network", &sig), Err(CryptoFailure::VerificationFailed));
+	assert_eq!(verify(&key.public_key_block(), b"info network", "not base64!"), Err(CryptoFailure::InvalidSignature));
+	assert_eq!(verify(&key.public_key_block(), b"info network", "AAAA"), Err(CryptoFailure::InvalidSignature));
+}
+
+#[test]
+fn ts_crypto_key_block_pass() {
+	let key = secret(7);
+	let block = key.public_key_block();
+	assert!(block.starts_with("PUBLIC KEY\n"));
+	assert_eq!(block.lines().count(), 2);
+	
+	// Wrapped and padded blocks are accepted
+	let b64 = block.lines().nth(1).unwrap();
+	let wrapped = format!("  PUBLIC KEY\r\n{}\r\n{}\n", &b64[..20], &b64[20..]);
+	let sig = key.sign(b"foo");
+	assert!(verify(&wrapped, b"foo", &sig).is_ok());
+	
+	let restored = NodeKey::from_secret_block(&key.to_secret_block()).unwrap();
+	assert_eq!(restored.public_key_block(), block);
+}
+
+#[test]
+fn ts_crypto_key_block_fail() {
+	let sig = secret(1).sign(b"foo");
+	assert_eq!(verify("KEY", b"foo", &sig), Err(CryptoFailure::InvalidKey));
+	assert_eq!(verify("PUBLIC KEY\nAbCdEf", b"foo", &sig), Err(CryptoFailure::InvalidKey));
+	assert_eq!(verify("PRIVATE KEY\nAbCdEf", b"foo", &sig), Err(CryptoFailure::InvalidKey));
+	
+	// A private key block is not accepted as a public one
+	assert_eq!(verify(&secret(1).to_secret_block(), b"foo", &sig), Err(CryptoFailure::InvalidKey));
+	assert!(NodeKey::from_secret_block(&secret(1).public_key_block()).is_err());
+}
+
+#[test]
+fn ts_crypto_registered_key_pass() {
+	// A node registers its public key and signs later requests with it
+	let key = secret(3);
+	let nio = MemoryNetspace::new();
+	nio.gsn_add_token("abcdef", "foo");
+	
+	let reg = format!("register foo,foo.tld;org;http;abcdef\n{}", key.public_key_block());
+	let m = Message::from_bytes(reg.as_bytes()).unwrap();
+	Dispatcher::new(&nio, "esusx").dispatch(&m, "192.168.1.2".parse().unwrap());
+	
+	let node = nio.gsn_node_by_springname("foo").unwrap();
+	let body = b"update foo state enabled";
+	let signed = HttpWrapper::wrap_signed_request(body, "spring.tld", "spring", &key);
+	
+	let request = RequestReader::new().read(&mut &signed[..], peer()).unwrap();
+	assert!(request.verify(node.key()).is_ok());
+	
+	let timestamp = request.header(TIMESTAMP_HEADER).unwrap();
+	let signature = request.header(SIGNATURE_HEADER).unwrap();
+	assert_eq!(request.request_line(), "POST /spring");
+	assert!(verify_node(&node, &request.request_line(), timestamp, &request.body, signature).is_ok());
+	
+	// The signature does not carry over to another target
+	assert_eq!(verify_node(&node, "POST /other", timestamp, &request.body, signature), Err(CryptoFailure::VerificationFailed));
+}
+
+#[test]
+fn ts_crypto_stale_signature_fail() {
+	let key = secret(3);
+	let public = key.public_key_block();
+	let sig = key.sign_line("POST /spring", 1000, b"update foo state enabled");
+	let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+	
+	assert!(verify_line_at(&public, "POST /spring", "1000", b"update foo state enabled", &sig, at(1000)).is_ok());
+	assert!(verify_line_at(&public, "POST /spring", "1000", b"update foo state enabled", &sig, at(1300)).is_ok());
+	assert!(verify_line_at(&public, "POST /spring", "1000", b"update foo state enabled", &sig, at(700)).is_ok());
+	
+	// A captured request cannot be replayed later
+	assert_eq!(verify_line_at(&public, "POST /spring", "1000", b"update foo state enabled", &sig, at(1301)), Err(CryptoFailure::StaleSignature));
+	assert_eq!(verify_line_at(&public, "POST /spring", "1000", b"update foo state enabled", &sig, at(699)), Err(CryptoFailure::StaleSignature));
+	
+	// Nor given a new time
+	assert_eq!(verify_line_at(&public, "POST /spring", "1200", b"update foo state enabled", &sig, at(1200)), Err(CryptoFailure::VerificationFailed));
+	assert_eq!(verify_line_at(&public, "POST /spring", "soon", b"update foo state enabled", &sig, at(1000)), Err(CryptoFailure::StaleSignature));
+	assert_eq!(verify_line_at(&public, "POST /spring", "18446744073709551615", b"update foo state enabled", &sig, at(1000)), Err(CryptoFailure::StaleSignature));
+}
+
+#[test]
+fn ts_crypto_request_verify_fail() {
+	let key = secret(3);
+	
+	let unsigned = HttpWrapper::wrap_request(b"info network", "spring.tld", "spring");
+	let request = RequestReader::new().read(&mut &unsigned[..], peer()).unwrap();
+	assert_eq!(request.verify(&key.public_key_block()), Err(CryptoFailure::MissingSignature));
+	
+	let signed = HttpWrapper::wrap_signed_request(b"info network", "spring.tld", "spring", &secret(4));
+	let request = RequestReader::new().read(&mut &signed[..], peer()).unwrap();
+	assert_eq!(request.verify(&key.public_key_block()), Err(CryptoFailure::VerificationFailed));
+}
+
+#[test]
+fn ts_crypto_http_verify_signature_pass() {
+	let key = secret(5);
+	
+	let signed = HttpWrapper::wrap_signed_request(b"info network", "spring.tld", "spring", &key);
+	assert!(HttpWrapper::verify_signature(&signed, &key.public_key_block()).is_ok());
+	
+	let msg = Message::from_bytes(b"200 19 service/text foobar").unwrap();
+	let response = HttpWrapper::serialise_signed_response(&msg, &key);
+	assert!(HttpWrapper::verify_signature(&response, &key.public_key_block()).is_ok());
+}
+
+#[test]
+fn ts_crypto_http_verify_signature_fail() {
+	let key = secret(5);
+	
+	let mut signed = HttpWrapper::wrap_signed_request(b"info network", "spring.tld", "spring", &key);
+	let last = signed.len() - 1;
+	signed[last] = b'x';
+	assert_eq!(HttpWrapper::verify_signature(&signed, &key.public_key_block()), Err(CryptoFailure::VerificationFailed));
+	
+	// Nor moved to another target
+	let signed = HttpWrapper::wrap_signed_request(b"info network", "spring.tld", "spring", &key);
+	let moved = String::from_utf8(signed).unwrap().replacen("POST /spring", "POST /other", 1);
+	assert_eq!(HttpWrapper::verify_signature(moved.as_bytes(), &key.public_key_block()), Err(CryptoFailure::VerificationFailed));
+	
+	// A signature without its time is not accepted
+	let signed = HttpWrapper::wrap_signed_request(b"info network", "spring.tld", "spring", &key);
+	let untimed = String::from_utf8(signed).unwrap().replacen(TIMESTAMP_HEADER, "X-Other", 1);
+	assert_eq!(HttpWrapper::verify_signature(untimed.as_bytes(), &key.public_key_block()), Err(CryptoFailure::MissingSignature));
+	
+	let response = HttpWrapper::serialise_response(&Message::from_bytes(b"200").unwrap());
+	assert_eq!(HttpWrapper::verify_signature(&response, &key.public_key_block()), Err(CryptoFailure::MissingSignature));
+	assert_eq!(HttpWrapper::verify_signature(b"200", &key.public_key_block()), Err(CryptoFailure::MissingSignature));
+}
+
+#[test]
+fn ts_crypto_endpoint_signing_key_pass() {
+	let key = Arc::new(secret(6));
+	let public = key.public_key_block();
+	
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	
+	let handle = thread::spawn(move || {
+		let (mut stream, addr) = listener.accept().unwrap();
+		let request = RequestReader::new().read(&mut stream, addr).unwrap();
+		stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n200").unwrap();
+		let mut rest = Vec::new();
+		let _ = stream.read_to_end(&mut rest);
+		request
+	});
+	
+	let e = Endpoint::new("127.0.0.1", "spring.tld", "spring").with_port(port).with_signing_key(key);
+	let msg = Message::from_bytes(b"info network").unwrap();
+	assert!(Outbound::new().request_message(&msg, &e).is_ok());
+	
+	let request = handle.join().unwrap();
+	assert!(request.verify(&public).is_ok());
+}
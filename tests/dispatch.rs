@@ -0,0 +1,248 @@
+#[macro_use]
+extern crate spring_dvs;
+
+use std::time::Duration;
+
+use spring_dvs::protocol::*;
+use spring_dvs::spaces::{Netspace, MemoryNetspace, Node};
+use spring_dvs::dispatch::Dispatcher;
+use spring_dvs::token::TokenIssuer;
+
+fn new_netspace() -> MemoryNetspace {
+	let nio = MemoryNetspace::new();
+	nio.gsn_add_token("abcdef", "foo");
+	
+	let n = Node::new("bar", "bar.tld", "192.168.1.3".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	nio.gsn_node_register(&n).unwrap();
+	nio
+}
+
+fn dispatch(nio: &MemoryNetspace, msg: &[u8], address: &str) -> ContentResponse {
+	let m = Message::from_bytes(msg).unwrap();
+	let r = Dispatcher::new(nio, "esusx").dispatch(&m, address.parse().unwrap());
+	assert_eq!(r.cmd, CmdType::Response);
+	msg_response!(r.content).clone()
+}
+
+#[test]
+fn ts_dispatch_register_pass() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"register foo,foo.tld;org;http;abcdef\nKEY", "192.168.1.2");
+	assert_eq!(r.code, Response::Ok);
+	
+	let n = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(n.address(), "192.168.1.2".parse().ok());
+	assert_eq!(n.state(), NodeState::Disabled);
+	assert_eq!(n.key(), "KEY");
+}
+
+#[test]
+fn ts_dispatch_register_token_fail() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"register foo,foo.tld;org;http;fedcba\nKEY", "192.168.1.2");
+	assert_eq!(r.code, Response::NetspaceError);
+	assert!(nio.gsn_node_by_springname("foo").is_err());
+}
+
+#[test]
+fn ts_dispatch_register_token_consumed_fail() {
+	let nio = new_netspace();
+	
+	// The token was given for foo
+	let r = dispatch(&nio, b"register baz,baz.tld;org;http;abcdef\nKEY", "192.168.1.2");
+	assert_eq!(r.code, Response::NetspaceError);
+	assert!(nio.gsn_node_by_springname("baz").is_err());
+	
+	let r = dispatch(&nio, b"register foo,foo.tld;org;http;abcdef\nKEY", "192.168.1.2");
+	assert_eq!(r.code, Response::Ok);
+	assert!(!nio.gsn_check_token("abcdef"));
+	
+	// And is only good once
+	nio.gsn_node_unregister(&nio.gsn_node_by_springname("foo").unwrap()).unwrap();
+	let r = dispatch(&nio, b"register foo,foo.tld;org;http;abcdef\nKEY", "192.168.1.2");
+	assert_eq!(r.code, Response::NetspaceError);
+}
+
+#[test]
+fn ts_dispatch_register_duplicate_fail() {
+	let nio = new_netspace();
+	nio.gsn_add_token("abcdef", "bar");
+	let r = dispatch(&nio, b"register bar,foo.tld;org;http;abcdef\nKEY", "192.168.1.2");
+	assert_eq!(r.code, Response::NetspaceDuplication);
+}
+
+#[test]
+fn ts_dispatch_unregister_pass() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"unregister bar", "192.168.1.3");
+	assert_eq!(r.code, Response::Ok);
+	assert!(nio.gsn_node_by_springname("bar").is_err());
+}
+
+#[test]
+fn ts_dispatch_unregister_fail() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"unregister foo", "192.168.1.3");
+	assert_eq!(r.code, Response::NetspaceError);
+	
+	let r = dispatch(&nio, b"unregister bar", "192.168.1.2");
+	assert_eq!(r.code, Response::UnsupportedAction);
+	assert!(nio.gsn_node_by_springname("bar").is_ok());
+}
+
+#[test]
+fn ts_dispatch_info_node_pass() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"info node bar state", "192.168.1.2");
+	assert_eq!(r.code, Response::Ok);
+	
+	let info = match r.content {
+		ResponseContent::NodeInfo(ref i) => &i.info,
+		_ => panic!("unexpected content"),
+	};
+	assert_eq!(info.state, NodeState::Enabled);
+	assert_eq!(info.spring, "");
+	
+	let r = dispatch(&nio, b"info node bar", "192.168.1.2");
+	assert_eq!(format!("{}", r), "200 84 node spring:bar,host:bar.tld,address:192.168.1.3,service:http,state:enabled,role:org");
+}
+
+#[test]
+fn ts_dispatch_info_node_fail() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"info node foo state", "192.168.1.2");
+	assert_eq!(r.code, Response::NetspaceError);
+}
+
+#[test]
+fn ts_dispatch_info_network_pass() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"info network", "192.168.1.2");
+	assert_eq!(r.code, Response::Ok);
+	assert_eq!(format!("{}", r), "200 37 network bar,bar.tld,192.168.1.3,http;");
+}
+
+#[test]
+fn ts_dispatch_update_pass() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"update bar state disabled", "192.168.1.3");
+	assert_eq!(r.code, Response::Ok);
+	assert_eq!(nio.gsn_node_by_springname("bar").unwrap().state(), NodeState::Disabled);
+	
+	let r = dispatch(&nio, b"update bar role hybrid", "192.168.1.3");
+	assert_eq!(r.code, Response::Ok);
+	assert_eq!(nio.gsn_node_by_springname("bar").unwrap().role(), NodeRole::Hybrid);
+}
+
+#[test]
+fn ts_dispatch_update_fail() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"update bar state disabled", "192.168.1.2");
+	assert_eq!(r.code, Response::UnsupportedAction);
+	
+	let r = dispatch(&nio, b"update bar hostname", "192.168.1.3");
+	assert_eq!(r.code, Response::UnsupportedAction);
+	
+	let r = dispatch(&nio, b"update foo state disabled", "192.168.1.3");
+	assert_eq!(r.code, Response::NetspaceError);
+	assert_eq!(nio.gsn_node_by_springname("bar").unwrap().state(), NodeState::Enabled);
+}
+
+#[test]
+fn ts_dispatch_unsupported_fail() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"service spring://bar.esusx.uk/", "192.168.1.2");
+	assert_eq!(r.code, Response::UnsupportedAction);
+	
+	let r = dispatch(&nio, b"200", "192.168.1.2");
+	assert_eq!(r.code, Response::UnsupportedAction);
+}
+
+#[test]
+fn ts_dispatch_resolve_pass() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"resolve spring://bar.esusx.uk", "192.168.1.2");
+	assert_eq!(r.code, Response::Ok);
+	assert_eq!(format!("{}", r), "200 42 resolve node bar,bar.tld,192.168.1.3,http;");
+}
+
+#[test]
+fn ts_dispatch_resolve_fail() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"resolve spring://foo.esusx.uk", "192.168.1.2");
+	assert_eq!(r.code, Response::NetspaceError);
+}
+
+#[test]
+fn ts_dispatch_ipv6_pass() {
+	let nio = new_netspace();
+	let r = dispatch(&nio, b"register foo,foo.tld;org;http;abcdef\nKEY", "2001:db8::2");
+	assert_eq!(r.code, Response::Ok);
+	assert_eq!(nio.gsn_node_by_springname("foo").unwrap().address(), "2001:db8::2".parse().ok());
+	
+	let r = dispatch(&nio, b"update foo state enabled", "2001:db8::2");
+	assert_eq!(r.code, Response::Ok);
+	
+	let r = dispatch(&nio, b"info network", "192.168.1.2");
+	let text = format!("{}", r);
+	assert!(text.contains("foo,foo.tld,2001:db8::2,http;"));
+	
+	// The listing parses back with the address intact
+	let m = Message::from_bytes(text.as_bytes()).unwrap();
+	match msg_response!(m.content).content {
+		ResponseContent::Network(ref n) => assert!(n.network.iter().any(|q| q.address.to_string() == "2001:db8::2")),
+		_ => panic!("unexpected content"),
+	}
+	
+	let r = dispatch(&nio, b"unregister foo", "2001:db8::3");
+	assert_eq!(r.code, Response::UnsupportedAction);
+	
+	let r = dispatch(&nio, b"unregister foo", "2001:db8::2");
+	assert_eq!(r.code, Response::Ok);
+}
+
+#[test]
+fn ts_dispatch_register_token_issuer_pass() {
+	let nio = new_netspace();
+	let tokens = TokenIssuer::new(&nio);
+	let t = tokens.issue("foo").unwrap();
+	let msg = format!("register foo,foo.tld;org;http;{}\nKEY", t);
+	
+	let d = Dispatcher::new(&nio, "esusx").with_token_issuer(TokenIssuer::new(&nio));
+	let r = d.dispatch(&Message::from_bytes(msg.as_bytes()).unwrap(), "192.168.1.2".parse().unwrap());
+	assert_eq!(msg_response!(r.content).code, Response::Ok);
+	assert!(nio.gsn_node_by_springname("foo").is_ok());
+	assert!(!nio.gsn_check_token(t.value()));
+}
+
+#[test]
+fn ts_dispatch_register_token_issuer_fail() {
+	let nio = new_netspace();
+	let d = Dispatcher::new(&nio, "esusx").with_token_issuer(TokenIssuer::new(&nio));
+	let register = |msg: &str| {
+		let r = d.dispatch(&Message::from_bytes(msg.as_bytes()).unwrap(), "192.168.1.2".parse().unwrap());
+		msg_response!(r.content).code
+	};
+	
+	// A stored token that was not issued is not accepted
+	assert_eq!(register("register foo,foo.tld;org;http;abcdef\nKEY"), Response::NetspaceError);
+	
+	// Nor is a token issued for another springname
+	let t = TokenIssuer::new(&nio).issue("baz").unwrap();
+	assert_eq!(register(&format!("register foo,foo.tld;org;http;{}\nKEY", t)), Response::NetspaceError);
+	
+	// Nor an expired one
+	let t = TokenIssuer::new(&nio).with_lifetime(Duration::from_secs(0)).issue("foo").unwrap();
+	assert_eq!(register(&format!("register foo,foo.tld;org;http;{}\nKEY", t)), Response::NetspaceError);
+	
+	// A failed registration leaves the token to be used again
+	let t = TokenIssuer::new(&nio).issue("bar").unwrap();
+	assert_eq!(register(&format!("register bar,foo.tld;org;http;{}\nKEY", t)), Response::NetspaceDuplication);
+	assert!(nio.gsn_check_token(t.value()));
+	
+	// And a token is only good once
+	let t = TokenIssuer::new(&nio).issue("foo").unwrap();
+	assert_eq!(register(&format!("register foo,foo.tld;org;http;{}\nKEY", t)), Response::Ok);
+	nio.gsn_node_unregister(&nio.gsn_node_by_springname("foo").unwrap()).unwrap();
+	assert_eq!(register(&format!("register foo,foo.tld;org;http;{}\nKEY", t)), Response::NetspaceError);
+}
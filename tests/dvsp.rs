@@ -0,0 +1,229 @@
+extern crate spring_dvs;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+use std::time::Duration;
+
+use spring_dvs::dvsp::*;
+use spring_dvs::enums::ParseFailure;
+use spring_dvs::http::OutboundError;
+use spring_dvs::protocol::*;
+use spring_dvs::stream::StreamError;
+
+/// Start a server answering `info` with the address of the
+/// peer and everything else with `Ok`
+fn server() -> SocketAddr {
+	let server = DvspServer::bind("127.0.0.1:0").unwrap().with_timeout(Some(Duration::from_secs(5)));
+	let address = server.local_addr().unwrap();
+	
+	thread::spawn(move || {
+		server.serve(|msg, peer| match msg.cmd {
+			CmdType::Info => generate_response_service_text(&peer.ip().to_string()),
+			_ => generate_response_empty_code(Response::Ok),
+		})
+	});
+	
+	address
+}
+
+fn msg(bytes: &[u8]) -> Message {
+	Message::from_bytes(bytes).unwrap()
+}
+
+#[test]
+fn ts_dvsp_request_pass() {
+	let address = server();
+	
+	let r = DvspOutbound::new().request(&msg(b"update foo state enabled"), "127.0.0.1", address.port()).unwrap();
+	assert_eq!(r.to_bytes(), b"200");
+	
+	let r = DvspOutbound::new().request(&msg(b"info network"), "127.0.0.1", address.port()).unwrap();
+	assert_eq!(r.to_bytes(), generate_response_service_text("127.0.0.1").to_bytes());
+}
+
+#[test]
+fn ts_dvsp_connection_pass() {
+	let address = server();
+	
+	let mut c = DvspOutbound::new().connect("127.0.0.1", address.port()).unwrap();
+	c.set_timeout(Some(Duration::from_secs(5))).unwrap();
+	assert_eq!(c.peer_addr().unwrap(), address);
+	
+	for _ in 0..3 {
+		assert_eq!(c.request(&msg(b"unregister foo")).unwrap().to_bytes(), b"200");
+		assert_eq!(c.request(&msg(b"info node foo")).unwrap().cmd, CmdType::Response);
+	}
+}
+
+#[test]
+fn ts_dvsp_server_malformed_content_pass() {
+	let address = server();
+	let mut stream = std::net::TcpStream::connect(address).unwrap();
+	
+	// Both requests arrive at once and are answered in turn
+	stream.write_all(b"4:void,12:info network,").unwrap();
+	
+	let mut reader = spring_dvs::stream::MessageReader::new(stream);
+	assert_eq!(reader.read_message().unwrap().unwrap().to_bytes(), b"104");
+	assert_eq!(reader.read_message().unwrap().unwrap().to_bytes(), generate_response_service_text("127.0.0.1").to_bytes());
+}
+
+#[test]
+fn ts_dvsp_server_malformed_frame_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap();
+	let server = DvspServer::bind("127.0.0.1:0").unwrap();
+	
+	let handle = thread::spawn(move || {
+		let (stream, _) = listener.accept().unwrap();
+		server.serve_connection(stream, &|_: &Message, _| generate_response_empty_code(Response::Ok))
+	});
+	
+	let mut stream = std::net::TcpStream::connect(address).unwrap();
+	stream.write_all(b"info network").unwrap();
+	
+	assert!(matches!(handle.join().unwrap(), Err(StreamError::MalformedFrame)));
+	
+	let mut rest = Vec::new();
+	stream.read_to_end(&mut rest).unwrap();
+	assert!(rest.is_empty());
+}
+
+#[test]
+fn ts_dvsp_request_closed_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap();
+	
+	thread::spawn(move || {
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut buf = [0; 64];
+		let _ = stream.read(&mut buf);
+	});
+	
+	let r = DvspOutbound::new().request(&msg(b"info network"), "127.0.0.1", address.port());
+	assert!(matches!(r, Err(OutboundError::Closed)));
+}
+
+#[test]
+fn ts_dvsp_request_malformed_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap();
+	
+	thread::spawn(move || {
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut buf = [0; 64];
+		let _ = stream.read(&mut buf);
+		stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+	});
+	
+	let r = DvspOutbound::new().request(&msg(b"info network"), "127.0.0.1", address.port());
+	match r {
+		Err(ref e @ OutboundError::MalformedFrame) => assert!(!e.is_transient()),
+		r => panic!("unexpected {:?}", r),
+	}
+}
+
+#[test]
+fn ts_dvsp_stream_error_pass() {
+	assert!(matches!(OutboundError::from(StreamError::Closed), OutboundError::Closed));
+	assert!(matches!(OutboundError::from(StreamError::FrameTooLarge), OutboundError::MalformedFrame));
+	assert!(matches!(OutboundError::from(StreamError::Parse(ParseFailure::InvalidCommand)), OutboundError::Parse(ParseFailure::InvalidCommand)));
+	
+	let e = std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out");
+	assert!(matches!(OutboundError::from(StreamError::Io(e)), OutboundError::Timeout));
+}
+
+#[test]
+fn ts_dvsp_request_timeout_fail() {
+	// Accept the connection but never answer it
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap();
+	thread::spawn(move || {
+		let held = listener.accept();
+		thread::sleep(Duration::from_secs(2));
+		drop(held);
+	});
+	
+	let outbound = DvspOutbound::new().with_timeout(Some(Duration::from_millis(100)));
+	let r = outbound.request(&msg(b"info network"), "127.0.0.1", address.port());
+	assert!(matches!(r, Err(OutboundError::Timeout)));
+}
+
+#[test]
+fn ts_dvsp_request_max_frame_pass() {
+	let server = DvspServer::bind("127.0.0.1:0").unwrap().with_timeout(Some(Duration::from_secs(5)));
+	let address = server.local_addr().unwrap();
+	let text = "a".repeat(100 * 1024);
+	
+	thread::spawn(move || {
+		server.serve(|_, _| generate_response_service_text(&text))
+	});
+	
+	let r = DvspOutbound::new().request(&msg(b"info network"), "127.0.0.1", address.port());
+	assert!(matches!(r, Err(OutboundError::MalformedFrame)));
+	
+	let r = DvspOutbound::new().with_max_frame(1024 * 1024).request(&msg(b"info network"), "127.0.0.1", address.port());
+	assert_eq!(r.unwrap().cmd, CmdType::Response);
+}
+
+#[test]
+fn ts_dvsp_server_max_connections_pass() {
+	let server = DvspServer::bind("127.0.0.1:0").unwrap()
+		.with_timeout(Some(Duration::from_secs(5)))
+		.with_max_connections(1);
+	let address = server.local_addr().unwrap();
+	
+	thread::spawn(move || {
+		server.serve(|_, _| generate_response_empty_code(Response::Ok))
+	});
+	
+	let mut first = DvspOutbound::new().connect("127.0.0.1", address.port()).unwrap();
+	assert_eq!(first.request(&msg(b"info network")).unwrap().to_bytes(), b"200");
+	
+	// A second connection is closed while the first is open
+	let r = DvspOutbound::new().request(&msg(b"info network"), "127.0.0.1", address.port());
+	assert!(r.is_err());
+	
+	// And served once it has closed
+	drop(first);
+	let mut served = false;
+	for _ in 0..50 {
+		if DvspOutbound::new().request(&msg(b"info network"), "127.0.0.1", address.port()).is_ok() {
+			served = true;
+			break
+		}
+		thread::sleep(Duration::from_millis(20));
+	}
+	assert!(served);
+}
+
+#[test]
+fn ts_dvsp_server_short_response_pass() {
+	let server = DvspServer::bind("127.0.0.1:0").unwrap()
+		.with_timeout(Some(Duration::from_secs(5)))
+		.with_max_connections(1);
+	let address = server.local_addr().unwrap();
+	
+	thread::spawn(move || {
+		server.serve(|_, _| generate_response_empty_code(Response::Ok))
+	});
+	
+	let mut stream = std::net::TcpStream::connect(address).unwrap();
+	stream.write_all(b"2:20,").unwrap();
+	
+	let mut reader = spring_dvs::stream::MessageReader::new(stream);
+	assert_eq!(reader.read_message().unwrap().unwrap().to_bytes(), b"104");
+	drop(reader);
+	
+	// The connection gives back its slot once it closes
+	let mut served = false;
+	for _ in 0..50 {
+		if DvspOutbound::new().request(&msg(b"info network"), "127.0.0.1", address.port()).is_ok() {
+			served = true;
+			break
+		}
+		thread::sleep(Duration::from_millis(20));
+	}
+	assert!(served);
+}
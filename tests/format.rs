@@ -104,7 +104,7 @@ fn ts_format_node_triple_fmt_pass() {
 	let nd : NodeTripleFmt = o.unwrap();
 	assert_eq!(nd.spring, "foo");
 	assert_eq!(nd.host, "bar");
-	assert_eq!(nd.address, "192.168.1.2");
+	assert_eq!(nd.address.to_string(), "192.168.1.2");
 }
 
 #[test]
@@ -181,7 +181,7 @@ fn ts_format_node_quad_fmt_pass() {
 	
 	assert_eq!(nq.spring, "foo");
 	assert_eq!(nq.host, "bar");
-	assert_eq!(nq.address, "127.1.4.3");
+	assert_eq!(nq.address.to_string(), "127.1.4.3");
 	assert_eq!(nq.service, NodeService::Http);
 
 }
@@ -228,7 +228,7 @@ fn ts_format_node_info_fmt_from_bytes_pass() {
 	let nf :  NodeInfoFmt = o.unwrap();
 	assert_eq!(nf.spring, "foo");
 	assert_eq!(nf.host, "bar");
-	assert_eq!(nf.address, "127.1.4.3");
+	assert_eq!(nf.address, "127.1.4.3".parse().ok());
 	assert_eq!(nf.service, NodeService::Http);
 	assert_eq!(nf.state, NodeState::Unspecified);
 	assert_eq!(nf.role, NodeRole::Undefined);
@@ -257,4 +257,73 @@ fn ts_format_node_info_fmt_from_str_fail() {
 	let o = NodeInfoFmt::from_str("spring:foo,host:bar,address:127.1.4.3,service:http,role:hybrid,state:jacked");
 	assert!(o.is_err());
 	assert_match!(o, Err(ParseFailure::InvalidState));
-}
\ No newline at end of file
+}
+
+#[test]
+fn ts_format_node_triple_fmt_ipv6_pass() {
+	let nd = NodeTripleFmt::from_str("foo,bar,2001:DB8:0:0::0001").unwrap();
+	assert_eq!(nd.address.to_string(), "2001:db8::1");
+	assert_eq!(nd.to_string(), "foo,bar,2001:db8::1");
+	
+	let nd = NodeTripleFmt::from_str("foo,[2001:db8::1]:8080/spring,::ffff:192.168.1.2").unwrap();
+	assert_eq!(nd.host, "[2001:db8::1]:8080/spring");
+	assert_eq!(nd.address.to_string(), "192.168.1.2");
+}
+
+#[test]
+fn ts_format_node_quad_fmt_ipv6_pass() {
+	let nq = NodeQuadFmt::from_str("foo,bar,2001:db8::1,http").unwrap();
+	assert_eq!(nq.address.to_string(), "2001:db8::1");
+	assert_eq!(nq.service, NodeService::Http);
+	assert_eq!(nq.to_string(), "foo,bar,2001:db8::1,http");
+	assert_eq!(NodeQuadFmt::from_str(&nq.to_string()).unwrap(), nq);
+}
+
+#[test]
+fn ts_format_node_quad_fmt_ipv6_fail() {
+	let o = NodeQuadFmt::from_str("foo,bar,[2001:db8::1],http");
+	assert_match!(o, Err(ParseFailure::InvalidAddress));
+	
+	let o = NodeQuadFmt::from_str("foo,bar,2001:db8::1::2,http");
+	assert_match!(o, Err(ParseFailure::InvalidAddress));
+	
+	let o = NodeQuadFmt::from_str("foo,2001:db8::1,2001:db8::1,http");
+	assert_match!(o, Err(ParseFailure::InvalidNaming));
+}
+
+#[test]
+fn ts_format_node_info_fmt_ipv6_pass() {
+	let ni = NodeInfoFmt::from_str("spring:foo,host:[::1]:8080,address:2001:db8::1").unwrap();
+	assert_eq!(ni.host, "[::1]:8080");
+	assert_eq!(ni.address, "2001:db8::1".parse().ok());
+	assert_eq!(ni.to_string(), "spring:foo,host:[::1]:8080,address:2001:db8::1");
+}
+
+#[test]
+fn ts_format_node_info_fmt_address_pass() {
+	let ni = NodeInfoFmt::from_str("spring:foo,address:").unwrap();
+	assert_eq!(ni.address, None);
+	assert_eq!(ni.to_string(), "spring:foo");
+	
+	let ni = NodeInfoFmt::from_str("address:::ffff:10.0.0.1").unwrap();
+	assert_eq!(ni.address, "10.0.0.1".parse().ok());
+}
+
+#[test]
+fn ts_format_node_info_fmt_address_fail() {
+	let o = NodeInfoFmt::from_str("spring:foo,address:foo.tld");
+	assert_match!(o, Err(ParseFailure::InvalidAddress));
+	
+	let o = NodeInfoFmt::from_str("address:192.168.1");
+	assert_match!(o, Err(ParseFailure::InvalidAddress));
+}
+
+
+#[test]
+fn ts_format_node_info_fmt_naming_fail() {
+	let o = NodeInfoFmt::from_str("spring:foo.bar,host:bar");
+	assert_match!(o, Err(ParseFailure::InvalidNaming));
+
+	let o = NodeInfoFmt::from_str("spring:foo,host:bar(baz");
+	assert_match!(o, Err(ParseFailure::InvalidNaming));
+}
@@ -0,0 +1,231 @@
+extern crate spring_dvs;
+
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use spring_dvs::health::*;
+use spring_dvs::http::OutboundError;
+use spring_dvs::protocol::*;
+use spring_dvs::spaces::{Netspace, MemoryNetspace, Node};
+
+/// Answers every probe except those to nodes that are down
+#[derive(Default)]
+struct MockTransport {
+	down: RefCell<Vec<String>>,
+	probes: RefCell<Vec<String>>,
+}
+
+impl MockTransport {
+	fn set_down(&self, spring: &str, down: bool) {
+		let mut d = self.down.borrow_mut();
+		d.retain(|s| s != spring);
+		if down {
+			d.push(String::from(spring));
+		}
+	}
+}
+
+impl<'t> Transport for &'t MockTransport {
+	fn request_node(&self, message: &Message, node: &Node) -> Result<Message, OutboundError> {
+		self.probes.borrow_mut().push(String::from_utf8(message.to_bytes()).unwrap());
+		
+		if self.down.borrow().iter().any(|s| s == node.springname()) {
+			return Err(OutboundError::Connect(io::Error::from(io::ErrorKind::ConnectionRefused)))
+		}
+		
+		Ok(generate_response_empty_code(Response::Ok))
+	}
+}
+
+#[derive(Default)]
+struct MockClock {
+	secs: Cell<u64>,
+	sleeps: RefCell<Vec<Duration>>,
+}
+
+impl<'c> Clock for &'c MockClock {
+	fn now(&self) -> SystemTime {
+		UNIX_EPOCH + Duration::from_secs(self.secs.get())
+	}
+	
+	fn sleep(&self, duration: Duration) {
+		self.sleeps.borrow_mut().push(duration);
+		self.secs.set(self.secs.get() + duration.as_secs());
+	}
+}
+
+fn new_netspace() -> MemoryNetspace {
+	let nio = MemoryNetspace::new();
+	let nodes = [("foo", NodeState::Enabled), ("bar", NodeState::Enabled), ("baz", NodeState::Disabled)];
+	for &(spring, state) in nodes.iter() {
+		let n = Node::new(spring, &format!("{}.tld", spring), "192.168.1.2".parse().ok(), NodeService::Http, state, NodeRole::Org, "");
+		nio.gsn_node_register(&n).unwrap();
+	}
+	nio
+}
+
+fn state(nio: &MemoryNetspace, spring: &str) -> NodeState {
+	nio.gsn_node_by_springname(spring).unwrap().state()
+}
+
+#[test]
+fn ts_health_check_pass() {
+	let nio = new_netspace();
+	let transport = MockTransport::default();
+	let clock = MockClock::default();
+	clock.secs.set(100);
+	
+	let mut monitor = HealthMonitor::new(&nio).with_transport(&transport).with_clock(&clock);
+	let round = monitor.check();
+	
+	let mut checked = round.checked.clone();
+	checked.sort();
+	assert_eq!(checked, vec!["bar", "foo"]);
+	assert!(round.failed.is_empty());
+	
+	let mut probes = transport.probes.borrow().clone();
+	probes.sort();
+	assert_eq!(probes, vec!["info node bar state", "info node foo state"]);
+	
+	let h = monitor.health("foo").unwrap();
+	assert_eq!(h.failures, 0);
+	assert_eq!(h.last_seen, Some(UNIX_EPOCH + Duration::from_secs(100)));
+	assert!(monitor.health("baz").is_none());
+}
+
+#[test]
+fn ts_health_unresponsive_threshold_pass() {
+	let nio = new_netspace();
+	let transport = MockTransport::default();
+	let clock = MockClock::default();
+	transport.set_down("foo", true);
+	
+	let mut monitor = HealthMonitor::new(&nio).with_transport(&transport).with_clock(&clock).with_threshold(3);
+	
+	for failures in 1..3 {
+		let round = monitor.check();
+		assert_eq!(round.failed, vec!["foo"]);
+		assert!(round.unresponsive.is_empty());
+		assert_eq!(monitor.health("foo").unwrap().failures, failures);
+		assert_eq!(state(&nio, "foo"), NodeState::Enabled);
+	}
+	
+	let round = monitor.check();
+	assert_eq!(round.unresponsive, vec!["foo"]);
+	assert_eq!(state(&nio, "foo"), NodeState::Unresponsive);
+	assert_eq!(state(&nio, "bar"), NodeState::Enabled);
+	
+	// Still failing but already marked
+	let round = monitor.check();
+	assert_eq!(round.failed, vec!["foo"]);
+	assert!(round.unresponsive.is_empty());
+}
+
+#[test]
+fn ts_health_failures_reset_pass() {
+	let nio = new_netspace();
+	let transport = MockTransport::default();
+	let clock = MockClock::default();
+	
+	let mut monitor = HealthMonitor::new(&nio).with_transport(&transport).with_clock(&clock).with_threshold(2);
+	
+	transport.set_down("foo", true);
+	monitor.check();
+	transport.set_down("foo", false);
+	monitor.check();
+	assert_eq!(monitor.health("foo").unwrap().failures, 0);
+	
+	transport.set_down("foo", true);
+	monitor.check();
+	assert_eq!(state(&nio, "foo"), NodeState::Enabled);
+}
+
+#[test]
+fn ts_health_restore_pass() {
+	let nio = new_netspace();
+	let transport = MockTransport::default();
+	let clock = MockClock::default();
+	
+	let mut monitor = HealthMonitor::new(&nio).with_transport(&transport).with_clock(&clock).with_threshold(1);
+	
+	transport.set_down("bar", true);
+	assert_eq!(monitor.check().unresponsive, vec!["bar"]);
+	assert_eq!(state(&nio, "bar"), NodeState::Unresponsive);
+	
+	transport.set_down("bar", false);
+	clock.secs.set(500);
+	let round = monitor.check();
+	assert_eq!(round.restored, vec!["bar"]);
+	assert_eq!(state(&nio, "bar"), NodeState::Enabled);
+	assert_eq!(monitor.health("bar").unwrap().last_seen, Some(UNIX_EPOCH + Duration::from_secs(500)));
+}
+
+#[test]
+fn ts_health_disabled_not_restored_pass() {
+	let nio = new_netspace();
+	let transport = MockTransport::default();
+	let clock = MockClock::default();
+	
+	let mut monitor = HealthMonitor::new(&nio).with_transport(&transport).with_clock(&clock);
+	monitor.check();
+	
+	assert_eq!(state(&nio, "baz"), NodeState::Disabled);
+	assert!(!transport.probes.borrow().iter().any(|p| p.contains("baz")));
+}
+
+#[test]
+fn ts_health_run_pass() {
+	let nio = new_netspace();
+	let transport = MockTransport::default();
+	let clock = MockClock::default();
+	transport.set_down("foo", true);
+	
+	let mut monitor = HealthMonitor::new(&nio)
+		.with_transport(&transport)
+		.with_clock(&clock)
+		.with_threshold(2)
+		.with_interval(Duration::from_secs(30));
+	
+	let mut rounds = 0;
+	monitor.run(|r| {
+		rounds += 1;
+		r.unresponsive.is_empty()
+	});
+	
+	assert_eq!(rounds, 2);
+	assert_eq!(*clock.sleeps.borrow(), vec![Duration::from_secs(30)]);
+	assert_eq!(monitor.health("foo").unwrap().last_checked, Some(UNIX_EPOCH + Duration::from_secs(30)));
+	assert_eq!(monitor.health("foo").unwrap().last_seen, None);
+}
+
+
+#[test]
+fn ts_health_http_transport_timeout_pass() {
+	// Accept the probe's connection but never answer it
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	thread::spawn(move || {
+		let mut held = Vec::new();
+		for s in listener.incoming() {
+			held.push(s);
+		}
+	});
+	
+	let nio = MemoryNetspace::new();
+	let n = Node::new("silent", &format!("127.0.0.1:{}", port), "127.0.0.1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	nio.gsn_node_register(&n).unwrap();
+	
+	let transport = HttpTransport::new().with_timeout(Duration::from_millis(100));
+	let mut monitor = HealthMonitor::new(&nio).with_transport(transport).with_threshold(1);
+	
+	let start = Instant::now();
+	let round = monitor.check();
+	
+	assert!(start.elapsed() < Duration::from_secs(2));
+	assert_eq!(round.failed, vec!["silent"]);
+	assert_eq!(round.unresponsive, vec!["silent"]);
+	assert_eq!(state(&nio, "silent"), NodeState::Unresponsive);
+}
@@ -1,11 +1,19 @@
 extern crate spring_dvs;
 
 use std::str::FromStr;
-use std::net::SocketAddr;
+use std::net::{SocketAddr, IpAddr};
 
-use spring_dvs::enums::Failure;
+use std::io;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use spring_dvs::enums::{Failure, ParseFailure};
 use spring_dvs::protocol::{ProtocolObject,Message,MessageContent,CmdType,ContentInfoRequest,InfoContent,ContentNodeProperty};
-use spring_dvs::http::HttpWrapper;
+use spring_dvs::http::{HttpWrapper, OutboundError, ResponseParser, Outbound, Endpoint, RequestReader, RequestError, TrustedProxies, Headers, MessageEncoding, MAX_HEADER};
+use spring_dvs::node::Node;
+use spring_dvs::protocol::{NodeService, NodeState, NodeRole, Port};
 
 
 #[test]
@@ -90,4 +98,881 @@ Content-Length: 3\r\n\r
 	
 	let s = r.unwrap();
 	assert_eq!(s, chk);
-}
\ No newline at end of file
+}
+#[test]
+fn ts_http_outbound_error_transient_pass() {
+	let e = OutboundError::Connect(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+	assert!(e.is_transient());
+	assert!(OutboundError::Timeout.is_transient());
+	assert!(OutboundError::Closed.is_transient());
+}
+
+#[test]
+fn ts_http_outbound_error_transient_fail() {
+	assert!(!OutboundError::MalformedHeader.is_transient());
+	assert!(!OutboundError::InvalidChunk.is_transient());
+	assert!(!OutboundError::Parse(ParseFailure::InvalidCommand).is_transient());
+}
+
+#[test]
+fn ts_http_outbound_error_from_io_pass() {
+	let e = OutboundError::from(io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+	assert!(matches!(e, OutboundError::Timeout));
+	
+	let e = OutboundError::from(io::Error::new(io::ErrorKind::UnexpectedEof, "eof"));
+	assert!(matches!(e, OutboundError::Closed));
+	
+	let e = OutboundError::from(io::Error::new(io::ErrorKind::BrokenPipe, "pipe"));
+	match e {
+		OutboundError::Io(ref i) => assert_eq!(i.kind(), io::ErrorKind::BrokenPipe),
+		_ => panic!("unexpected error: {:?}", e),
+	}
+}
+
+#[test]
+fn ts_http_outbound_error_from_parse_pass() {
+	let e = OutboundError::from(ParseFailure::InvalidContentFormat);
+	assert!(matches!(e, OutboundError::Parse(ParseFailure::InvalidContentFormat)));
+}
+
+/// Simple LCG so fragment sizes vary but runs are repeatable
+struct Lcg(u64);
+
+impl Lcg {
+	fn next(&mut self, max: usize) -> usize {
+		self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		((self.0 >> 33) as usize % max) + 1
+	}
+}
+
+/// A reader that hands back its bytes in random fragments
+struct Fragments {
+	bytes: Vec<u8>,
+	index: usize,
+	rng: Lcg,
+}
+
+impl io::Read for Fragments {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = self.rng.next(64).min(buf.len()).min(self.bytes.len() - self.index);
+		buf[..n].copy_from_slice(&self.bytes[self.index..self.index+n]);
+		self.index += n;
+		Ok(n)
+	}
+}
+
+fn parse_fragmented(payload: &[u8], seed: u64, max: usize) -> Result<ResponseParser, OutboundError> {
+	let mut rng = Lcg(seed);
+	let mut p = ResponseParser::new();
+	let mut index = 0;
+	
+	while index < payload.len() {
+		let n = rng.next(max).min(payload.len() - index);
+		if p.feed(&payload[index..index+n])? {
+			return Ok(p)
+		}
+		index += n;
+	}
+	
+	p.finish()?;
+	Ok(p)
+}
+
+fn binary_body() -> Vec<u8> {
+	(0..5000).map(|i| (i % 256) as u8).collect()
+}
+
+fn chunk_encode(body: &[u8], size: usize) -> Vec<u8> {
+	let mut v = Vec::from(&b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\n\r\n"[..]);
+	for (i, c) in body.chunks(size).enumerate() {
+		v.extend_from_slice(format!("{:x};n={}\r\n", c.len(), i).as_bytes());
+		v.extend_from_slice(c);
+		v.extend_from_slice(b"\r\n");
+	}
+	v.extend_from_slice(b"0\r\nX-Checksum: none\r\nX-Other: foo\r\n\r\n");
+	v
+}
+
+#[test]
+fn ts_http_response_parser_content_length_pass() {
+	let mut p = ResponseParser::new();
+	assert_eq!(p.feed(b"HTTP/1.1 200 OK\r\nServer: foo\r\n").unwrap(), false);
+	assert_eq!(p.status(), 0);
+	assert_eq!(p.feed(b"content-length: 6\r\n\r\n200").unwrap(), false);
+	assert_eq!(p.status(), 200);
+	assert_eq!(p.header("Server"), Some("foo"));
+	assert_eq!(p.header("Content-Length"), Some("6"));
+	assert_eq!(p.feed(b" 0 trailing").unwrap(), true);
+	assert!(p.is_complete());
+	assert_eq!(p.body(), b"200 0 ");
+}
+
+#[test]
+fn ts_http_response_parser_chunked_pass() {
+	let mut p = ResponseParser::new();
+	let r = p.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 1\r\n\r\n3;foo=bar\r\n200\r\nA\r\n service/t\r\n0\r\nX-Foo: bar\r\n\r\n");
+	assert_eq!(r.unwrap(), true);
+	assert_eq!(p.body(), b"200 service/t");
+}
+
+#[test]
+fn ts_http_response_parser_to_close_pass() {
+	let mut p = ResponseParser::new();
+	assert_eq!(p.feed(b"HTTP/1.0 200 OK\r\n\r\n200 ").unwrap(), false);
+	assert_eq!(p.feed(b"foo").unwrap(), false);
+	assert!(p.finish().is_ok());
+	assert_eq!(p.body(), b"200 foo");
+}
+
+#[test]
+fn ts_http_response_parser_interim_pass() {
+	let mut p = ResponseParser::new();
+	let r = p.feed(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n200");
+	assert_eq!(r.unwrap(), true);
+	assert_eq!(p.status(), 200);
+	assert_eq!(p.body(), b"200");
+}
+
+#[test]
+fn ts_http_response_parser_no_content_pass() {
+	let mut p = ResponseParser::new();
+	assert_eq!(p.feed(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap(), true);
+	assert!(p.body().is_empty());
+}
+
+#[test]
+fn ts_http_response_parser_header_fail() {
+	let mut p = ResponseParser::new();
+	assert!(matches!(p.feed(b"SPRING/1.1 200 OK\r\n\r\n"), Err(OutboundError::MalformedHeader)));
+	
+	let mut p = ResponseParser::new();
+	assert!(matches!(p.feed(b"HTTP/1.1 OK\r\n\r\n"), Err(OutboundError::MalformedHeader)));
+	
+	let mut p = ResponseParser::new();
+	assert!(matches!(p.feed(b"HTTP/1.1 200 OK\r\nContent-Length: -1\r\n\r\n"), Err(OutboundError::MalformedHeader)));
+	
+	let mut p = ResponseParser::new();
+	assert!(matches!(p.feed(b"HTTP/1.1 200 OK\r\nbroken\r\n\r\n"), Err(OutboundError::MalformedHeader)));
+}
+
+#[test]
+fn ts_http_response_parser_header_limit_fail() {
+	let mut p = ResponseParser::new();
+	assert_eq!(p.feed(b"HTTP/1.1 200 OK\r\n").unwrap(), false);
+	
+	let filler = vec![b'a'; 4096];
+	let mut r = Ok(false);
+	for _ in 0..5 {
+		r = p.feed(&filler);
+	}
+	assert!(matches!(r, Err(OutboundError::MalformedHeader)));
+}
+
+#[test]
+fn ts_http_response_parser_trailer_limit_fail() {
+	let mut p = ResponseParser::new();
+	assert_eq!(p.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n200\r\n0\r\n").unwrap(), false);
+	
+	// Many short trailer fields, each well under the limit
+	let mut r = Ok(false);
+	for _ in 0..(MAX_HEADER / 8 + 1) {
+		r = p.feed(b"X-A: b\r\n");
+		if r.is_err() { break }
+	}
+	assert!(matches!(r, Err(OutboundError::MalformedHeader)));
+}
+
+#[test]
+fn ts_http_response_parser_chunk_fail() {
+	let mut p = ResponseParser::new();
+	let r = p.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n");
+	assert!(matches!(r, Err(OutboundError::InvalidChunk)));
+	
+	let mut p = ResponseParser::new();
+	let r = p.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n2000\r\n");
+	assert!(matches!(r, Err(OutboundError::InvalidChunk)));
+}
+
+#[test]
+fn ts_http_response_parser_closed_fail() {
+	let mut p = ResponseParser::new();
+	assert_eq!(p.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n200").unwrap(), false);
+	assert!(matches!(p.finish(), Err(OutboundError::Closed)));
+	
+	let mut p = ResponseParser::new();
+	assert_eq!(p.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n200\r\n").unwrap(), false);
+	assert!(matches!(p.finish(), Err(OutboundError::Closed)));
+}
+
+#[test]
+fn ts_http_response_parser_fuzz_content_length_pass() {
+	let body = binary_body();
+	let mut payload = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+	payload.extend_from_slice(&body);
+	
+	for seed in 0..200 {
+		let p = parse_fragmented(&payload, seed, 1 + seed as usize % 97).unwrap();
+		assert_eq!(p.body(), &body[..], "seed {}", seed);
+	}
+}
+
+#[test]
+fn ts_http_response_parser_fuzz_chunked_pass() {
+	let body = binary_body();
+	
+	for seed in 0..200 {
+		let payload = chunk_encode(&body, 1 + seed as usize * 7 % 600);
+		let p = parse_fragmented(&payload, seed, 1 + seed as usize % 53).unwrap();
+		assert_eq!(p.body(), &body[..], "seed {}", seed);
+	}
+}
+
+#[test]
+fn ts_http_response_parser_fuzz_to_close_pass() {
+	let body = binary_body();
+	let mut payload = Vec::from(&b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n"[..]);
+	payload.extend_from_slice(&body);
+	
+	for seed in 0..200 {
+		let p = parse_fragmented(&payload, seed, 1 + seed as usize % 89).unwrap();
+		assert_eq!(p.body(), &body[..], "seed {}", seed);
+	}
+}
+
+#[test]
+fn ts_http_outbound_read_response_pass() {
+	let body = binary_body();
+	
+	for seed in 0..50 {
+		let mut reader = Fragments { bytes: chunk_encode(&body, 1000), index: 0, rng: Lcg(seed) };
+		assert_eq!(Outbound::new().read_response(&mut reader).unwrap(), body);
+	}
+}
+
+#[test]
+fn ts_http_outbound_read_response_fail() {
+	let mut reader = Fragments { bytes: Vec::from(&b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n200"[..]), index: 0, rng: Lcg(1) };
+	assert!(matches!(Outbound::new().read_response(&mut reader), Err(OutboundError::Closed)));
+}
+
+fn node(host: &str) -> Node {
+	Node::new("foo", host, "127.0.0.1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "")
+}
+
+/// Accept one connection, read the request and reply with `response`.
+/// The request is handed back through the join handle.
+fn serve_once(response: &'static [u8]) -> (u16, thread::JoinHandle<String>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	
+	let handle = thread::spawn(move || {
+		let (mut stream, _) = listener.accept().unwrap();
+		let mut buf = [0; 4096];
+		let size = stream.read(&mut buf).unwrap();
+		stream.write_all(response).unwrap();
+		String::from_utf8_lossy(&buf[..size]).into_owned()
+	});
+	
+	(port, handle)
+}
+
+#[test]
+fn ts_http_port_number_pass() {
+	assert_eq!(Port::Dvsp.number(), 55301);
+	assert_eq!(Port::Http.number(), 80);
+	assert_eq!(Port::Https.number(), 443);
+	assert_eq!(Port::Stream.number(), 55300);
+	assert_eq!(format!("{}", Port::Dvsp), "55301");
+}
+
+#[test]
+fn ts_http_endpoint_from_node_pass() {
+	let e = Endpoint::from_node(&node("foo.tld"));
+	assert_eq!(e.address(), "127.0.0.1");
+	assert_eq!(e.port(), 80);
+	assert_eq!(e.host(), "foo.tld");
+	assert_eq!(e.path(), "spring");
+	assert!(!e.is_tls());
+	assert_eq!(e.host_header(), "foo.tld");
+	
+	let e = Endpoint::from_node(&node("https://foo.tld/node"));
+	assert_eq!(e.port(), 443);
+	assert_eq!(e.path(), "node/spring");
+	assert!(e.is_tls());
+	assert_eq!(e.host_header(), "foo.tld");
+	
+	let e = Endpoint::from_node(&node("https://foo.tld:8443"));
+	assert_eq!(e.port(), 8443);
+	assert_eq!(e.host_header(), "foo.tld:8443");
+	
+	let e = Endpoint::from_node(&node("foo.tld")).with_port(Port::Dvsp.number());
+	assert_eq!(e.port(), 55301);
+	assert_eq!(e.host_header(), "foo.tld:55301");
+}
+
+#[test]
+fn ts_http_outbound_request_node_port_pass() {
+	let (port, handle) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 28\r\n\r\n200 19 service/text foobar\r\n");
+	
+	let n = node(&format!("foo.tld:{}/node", port));
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	let r = Outbound::new().request_node(&msg, &n).unwrap();
+	
+	assert_eq!(r.to_bytes(), b"200 19 service/text foobar".to_vec());
+	
+	let request = handle.join().unwrap();
+	assert!(request.starts_with("POST /node/spring HTTP/1.1\r\n"));
+	assert!(request.contains(&format!("Host: foo.tld:{}\r\n", port)));
+}
+
+#[test]
+fn ts_http_outbound_request_endpoint_pass() {
+	let (port, handle) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n200");
+	
+	let e = Endpoint::new("127.0.0.1", "foo.tld", "spring").with_port(port);
+	assert_eq!(Outbound::new().request_endpoint(b"info network", &e).unwrap(), b"200".to_vec());
+	assert!(handle.join().unwrap().ends_with("\r\n\r\ninfo network"));
+}
+
+#[test]
+fn ts_http_outbound_request_endpoint_connect_fail() {
+	let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+	
+	let e = Endpoint::new("127.0.0.1", "foo.tld", "spring").with_port(port);
+	let r = Outbound::new().request_endpoint(b"info network", &e);
+	assert!(matches!(r, Err(OutboundError::Connect(_))));
+}
+
+#[cfg(not(feature = "tls"))]
+#[test]
+fn ts_http_outbound_request_tls_unsupported_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	
+	let e = Endpoint::from_node(&node(&format!("https://foo.tld:{}", port)));
+	match Outbound::new().request_endpoint(b"info network", &e) {
+		Err(OutboundError::Tls(ref e)) => assert_eq!(e.kind(), io::ErrorKind::Unsupported),
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+fn peer() -> SocketAddr {
+	SocketAddr::from_str("192.168.1.2:55000").unwrap()
+}
+
+fn request_bytes(body: &str, extra: &str) -> Vec<u8> {
+	format!("POST /node/spring HTTP/1.1\r\nHost: foo.tld\r\n{}Content-Length: {}\r\n\r\n{}", extra, body.len(), body).into_bytes()
+}
+
+#[test]
+fn ts_http_request_reader_pass() {
+	let mut stream = &request_bytes("info network", "X-Test: foo\r\n")[..];
+	let r = RequestReader::new().read(&mut stream, peer()).unwrap();
+	
+	assert_eq!(r.method, "POST");
+	assert_eq!(r.path, "/node/spring");
+	assert_eq!(r.header("host"), Some("foo.tld"));
+	assert_eq!(r.header("X-TEST"), Some("foo"));
+	assert_eq!(r.message.cmd, CmdType::Info);
+	assert_eq!(r.address, peer());
+}
+
+#[test]
+fn ts_http_request_reader_fragments_pass() {
+	// Body contains a blank line and arrives in pieces
+	let body = "service spring://foo.esusx.uk/\r\n\r\n";
+	
+	for seed in 0..50 {
+		let mut reader = Fragments { bytes: request_bytes(body, ""), index: 0, rng: Lcg(seed) };
+		let r = RequestReader::new().read(&mut reader, peer()).unwrap();
+		assert_eq!(r.message.cmd, CmdType::Service);
+		assert_eq!(r.header("Content-Length"), Some("34"));
+	}
+}
+
+#[test]
+fn ts_http_request_reader_forwarded_pass() {
+	let mut proxies = TrustedProxies::none();
+	proxies.add("192.168.1.0/24").unwrap();
+	let reader = RequestReader::new().with_trusted_proxies(proxies);
+	
+	let mut stream = &request_bytes("info network", "X-Forwarded-For: 10.0.0.5\r\n")[..];
+	let r = reader.read(&mut stream, peer()).unwrap();
+	assert_eq!(r.address, SocketAddr::from_str("10.0.0.5:55000").unwrap());
+	
+	// An unparsable forwarded address is ignored
+	let mut stream = &request_bytes("info network", "X-Forwarded-For: unknown\r\n")[..];
+	let r = reader.read(&mut stream, peer()).unwrap();
+	assert_eq!(r.address, peer());
+}
+
+#[test]
+fn ts_http_request_reader_forwarded_fail() {
+	// The peer is not a trusted proxy
+	let mut stream = &request_bytes("info network", "X-Forwarded-For: 10.0.0.5\r\n")[..];
+	let r = RequestReader::new().read(&mut stream, peer()).unwrap();
+	assert_eq!(r.address, peer());
+}
+
+fn headers(v: &[(&str, &str)]) -> Headers {
+	let mut h = Headers::new();
+	for &(k, v) in v {
+		h.add(k, v);
+	}
+	h
+}
+
+fn ip(s: &str) -> IpAddr {
+	IpAddr::from_str(s).unwrap()
+}
+
+fn proxies() -> TrustedProxies {
+	let mut p = TrustedProxies::loopback();
+	p.add("10.0.0.0/8").unwrap();
+	p.add("fd00::/8").unwrap();
+	p
+}
+
+#[test]
+fn ts_http_trusted_proxies_contains_pass() {
+	let p = proxies();
+	assert!(p.contains(ip("127.0.0.1")));
+	assert!(p.contains(ip("127.10.0.1")));
+	assert!(p.contains(ip("::1")));
+	assert!(p.contains(ip("10.200.0.1")));
+	assert!(p.contains(ip("::ffff:10.0.0.1")));
+	assert!(p.contains(ip("fd12::1")));
+	
+	assert!(!p.contains(ip("11.0.0.1")));
+	assert!(!p.contains(ip("fe80::1")));
+	assert!(!TrustedProxies::none().contains(ip("127.0.0.1")));
+}
+
+#[test]
+fn ts_http_trusted_proxies_add_fail() {
+	let mut p = TrustedProxies::none();
+	assert_eq!(p.add("foo"), Err(Failure::InvalidFormat));
+	assert_eq!(p.add("10.0.0.0/33"), Err(Failure::InvalidFormat));
+	assert_eq!(p.add("10.0.0.0/a"), Err(Failure::InvalidFormat));
+	assert_eq!(p.add("::/129"), Err(Failure::InvalidFormat));
+	assert!(p.add("0.0.0.0/0").is_ok());
+	assert!(p.contains(ip("203.0.113.9")));
+}
+
+#[test]
+fn ts_http_trusted_proxies_forwarded_pass() {
+	let p = proxies();
+	
+	let h = headers(&[("Forwarded", "for=192.0.2.60;proto=http;by=203.0.113.43")]);
+	assert_eq!(p.client_address(ip("127.0.0.1"), &h), ip("192.0.2.60"));
+	
+	let h = headers(&[("forwarded", "for=\"[2001:db8:cafe::17]:4711\"")]);
+	assert_eq!(p.client_address(ip("127.0.0.1"), &h), ip("2001:db8:cafe::17"));
+	
+	let h = headers(&[("Forwarded", "For=\"192.0.2.43:47011\", for=10.0.0.3"), ("Forwarded", "for=10.0.0.2")]);
+	assert_eq!(p.client_address(ip("127.0.0.1"), &h), ip("192.0.2.43"));
+	
+	// Forwarded is preferred over the de-facto headers
+	let h = headers(&[("X-Forwarded-For", "192.0.2.9"), ("Forwarded", "for=192.0.2.60")]);
+	assert_eq!(p.client_address(ip("127.0.0.1"), &h), ip("192.0.2.60"));
+}
+
+#[test]
+fn ts_http_trusted_proxies_x_forwarded_for_pass() {
+	let p = proxies();
+	
+	let h = headers(&[("X-Forwarded-For", "192.0.2.1, 10.0.0.7, 10.0.0.8")]);
+	assert_eq!(p.client_address(ip("10.0.0.1"), &h), ip("192.0.2.1"));
+	
+	let h = headers(&[("X-Forwarded-For", "2001:db8::1"), ("X-Forwarded-For", "10.0.0.7")]);
+	assert_eq!(p.client_address(ip("::1"), &h), ip("2001:db8::1"));
+	
+	let h = headers(&[("X-Forwarded-For", "192.0.2.1:5000")]);
+	assert_eq!(p.client_address(ip("127.0.0.1"), &h), ip("192.0.2.1"));
+	
+	// Every hop is a trusted proxy
+	let h = headers(&[("X-Forwarded-For", "10.0.0.9, 10.0.0.7")]);
+	assert_eq!(p.client_address(ip("127.0.0.1"), &h), ip("10.0.0.9"));
+}
+
+#[test]
+fn ts_http_trusted_proxies_x_real_ip_pass() {
+	let h = headers(&[("X-Real-IP", "192.0.2.1")]);
+	assert_eq!(proxies().client_address(ip("127.0.0.1"), &h), ip("192.0.2.1"));
+	
+	let h = headers(&[("X-Real-IP", "192.0.2.1"), ("X-Forwarded-For", "192.0.2.2")]);
+	assert_eq!(proxies().client_address(ip("127.0.0.1"), &h), ip("192.0.2.2"));
+}
+
+#[test]
+fn ts_http_trusted_proxies_spoof_fail() {
+	let p = proxies();
+	
+	// The peer is not trusted, so its headers are ignored
+	let h = headers(&[("X-Forwarded-For", "192.0.2.1"), ("Forwarded", "for=192.0.2.1"), ("X-Real-IP", "192.0.2.1")]);
+	assert_eq!(p.client_address(ip("203.0.113.5"), &h), ip("203.0.113.5"));
+	
+	// A client's own header is passed on by the proxy, which appends the real address
+	let h = headers(&[("X-Forwarded-For", "192.0.2.1, 203.0.113.5")]);
+	assert_eq!(p.client_address(ip("10.0.0.1"), &h), ip("203.0.113.5"));
+	
+	// An unknown hop stops the walk at the proxy that reported it
+	let h = headers(&[("Forwarded", "for=192.0.2.1, for=unknown, for=10.0.0.4")]);
+	assert_eq!(p.client_address(ip("10.0.0.1"), &h), ip("10.0.0.4"));
+	
+	let h = headers(&[("X-Forwarded-For", "garbage")]);
+	assert_eq!(p.client_address(ip("10.0.0.1"), &h), ip("10.0.0.1"));
+	
+	let h = headers(&[("X-Forwarded-For", "[::1")]);
+	assert_eq!(p.client_address(ip("10.0.0.1"), &h), ip("10.0.0.1"));
+}
+
+#[test]
+fn ts_http_deserialise_http_request_forwarded_pass() {
+	let v = request_bytes("info network", "X-Forwarded-For: 192.0.2.1, 2001:db8::1\r\n");
+	let mut address = SocketAddr::from_str("127.0.0.1:80").unwrap();
+	assert!(HttpWrapper::deserialise_request(v, &mut address).is_ok());
+	assert_eq!(address, SocketAddr::from_str("[2001:db8::1]:80").unwrap());
+	
+	let v = request_bytes("info network", "X-Forwarded-For: 192.0.2.1\r\n");
+	let mut address = peer();
+	assert!(HttpWrapper::deserialise_request(v, &mut address).is_ok());
+	assert_eq!(address, peer());
+}
+
+#[test]
+fn ts_http_deserialise_http_request_with_proxies_pass() {
+	let v = request_bytes("info network", "X-Forwarded-For: 192.0.2.1\r\n");
+	let mut address = SocketAddr::from_str("10.1.2.3:80").unwrap();
+	assert!(HttpWrapper::deserialise_request_with(v, &mut address, &proxies()).is_ok());
+	assert_eq!(address, SocketAddr::from_str("192.0.2.1:80").unwrap());
+	
+	// The loopback interface is not trusted unless listed
+	let v = request_bytes("info network", "X-Forwarded-For: 192.0.2.1\r\n");
+	let mut address = SocketAddr::from_str("127.0.0.1:80").unwrap();
+	assert!(HttpWrapper::deserialise_request_with(v, &mut address, &TrustedProxies::none()).is_ok());
+	assert_eq!(address, SocketAddr::from_str("127.0.0.1:80").unwrap());
+}
+
+#[test]
+fn ts_http_request_reader_body_limit_fail() {
+	let mut stream = &request_bytes("info network", "")[..];
+	let r = RequestReader::new().with_max_body(11).read(&mut stream, peer());
+	assert!(matches!(r, Err(RequestError::BodyTooLarge)));
+	
+	let mut stream = &request_bytes("info network", "")[..];
+	assert!(RequestReader::new().with_max_body(12).read(&mut stream, peer()).is_ok());
+}
+
+#[test]
+fn ts_http_request_reader_header_fail() {
+	let mut stream = &b"POST /spring/\r\nContent-Length: 3\r\n\r\n200"[..];
+	assert!(matches!(RequestReader::new().read(&mut stream, peer()), Err(RequestError::MalformedHeader)));
+	
+	let mut stream = &b"POST /spring/ HTTP/1.1\r\nContent-Length: three\r\n\r\n200"[..];
+	assert!(matches!(RequestReader::new().read(&mut stream, peer()), Err(RequestError::MalformedHeader)));
+	
+	let mut stream = &b"POST /spring/ HTTP/1.1\r\nHost: foo.tld\r\n\r\n200"[..];
+	assert!(matches!(RequestReader::new().read(&mut stream, peer()), Err(RequestError::LengthRequired)));
+	
+	let mut big = Vec::from(&b"POST /spring/ HTTP/1.1\r\n"[..]);
+	big.extend(vec![b'a'; 20000]);
+	let mut stream = &big[..];
+	assert!(matches!(RequestReader::new().read(&mut stream, peer()), Err(RequestError::HeaderTooLarge)));
+}
+
+#[test]
+fn ts_http_request_reader_closed_fail() {
+	let mut stream = &b"POST /spring/ HTTP/1.1\r\nContent-Length: 12\r\n\r\ninfo"[..];
+	assert!(matches!(RequestReader::new().read(&mut stream, peer()), Err(RequestError::Closed)));
+	
+	let mut stream = &b"POST /spring/ HTTP/1.1\r\n"[..];
+	assert!(matches!(RequestReader::new().read(&mut stream, peer()), Err(RequestError::Closed)));
+}
+
+#[test]
+fn ts_http_request_reader_parse_fail() {
+	let mut stream = &request_bytes("foobar", "")[..];
+	assert!(matches!(RequestReader::new().read(&mut stream, peer()), Err(RequestError::Parse(_))));
+}
+
+#[test]
+fn ts_http_deserialise_http_request_blank_line_pass() {
+	let v = request_bytes("service spring://foo.esusx.uk/\r\n\r\n", "");
+	let r = HttpWrapper::deserialise_request(v, &mut peer());
+	assert_eq!(r.unwrap().cmd, CmdType::Service);
+}
+
+#[test]
+fn ts_http_headers_parse_pass() {
+	let h = Headers::parse("Host: foo.tld\r\nX-Forwarded-For: 2001:db8::1\r\nvia: a\r\nVia: b, c\nEmpty:").unwrap();
+	assert_eq!(h.len(), 5);
+	assert_eq!(h.get("HOST"), Some("foo.tld"));
+	assert_eq!(h.get("x-forwarded-for"), Some("2001:db8::1"));
+	assert_eq!(h.get("Via"), Some("a"));
+	assert_eq!(h.get_all("VIA"), vec!["a", "b, c"]);
+	assert_eq!(h.get_list("via"), vec!["a", "b", "c"]);
+	assert_eq!(h.get("Empty"), Some(""));
+	assert!(h.contains("empty"));
+	assert!(!h.contains("Content-Length"));
+	assert!(Headers::parse("").unwrap().is_empty());
+}
+
+#[test]
+fn ts_http_headers_parse_fail() {
+	assert_eq!(Headers::parse("Host foo.tld"), None);
+	assert_eq!(Headers::parse("Host: foo.tld\r\n\r\n"), None);
+	assert_eq!(Headers::parse(": foo"), None);
+	assert_eq!(Headers::parse("Bad Name: foo"), None);
+	
+	let h = Headers::parse_lenient("POST /spring/ HTTP/1.1\r\nHost: foo.tld\r\nbroken");
+	assert_eq!(h.len(), 1);
+	assert_eq!(h.get("host"), Some("foo.tld"));
+}
+
+#[test]
+fn ts_http_headers_modify_pass() {
+	let mut h = Headers::new();
+	h.add("Via", "a");
+	h.add("via", "b");
+	h.add("Host", "foo.tld");
+	assert_eq!(h.get_all("Via").len(), 2);
+	
+	h.set("VIA", "c");
+	assert_eq!(h.get_all("via"), vec!["c"]);
+	
+	h.remove("host");
+	assert!(!h.contains("Host"));
+	assert_eq!(format!("{}", h), "VIA: c\r\n");
+	assert_eq!(h.iter().collect::<Vec<_>>(), vec![("VIA", "c")]);
+}
+
+#[test]
+fn ts_http_headers_line_break_fail() {
+	let mut h = Headers::new();
+	h.add("X-Note", "a\r\nContent-Length: 0");
+	h.add("X-\nOther", "b\n\r\n");
+	assert_eq!(format!("{}", h), "X-Note: aContent-Length: 0\r\nX-Other: b\r\n");
+	
+	// Nothing injected reaches a wrapped request
+	let v = HttpWrapper::wrap_request_with(b"info network", "spring.tld", "spring", &h);
+	let r = RequestReader::new().read(&mut &v[..], peer()).unwrap();
+	assert_eq!(r.headers.get_all("Content-Length"), vec!["12"]);
+	assert_eq!(r.header("X-Note"), Some("aContent-Length: 0"));
+}
+
+#[test]
+fn ts_http_headers_content_length_pass() {
+	assert_eq!(headers(&[("content-length", "12")]).content_length(), Some(Ok(12)));
+	assert_eq!(headers(&[("Content-Length", "12"), ("Content-Length", "12")]).content_length(), Some(Ok(12)));
+	assert_eq!(headers(&[("Host", "foo")]).content_length(), None);
+}
+
+#[test]
+fn ts_http_headers_content_length_fail() {
+	assert_eq!(headers(&[("Content-Length", "twelve")]).content_length(), Some(Err(Failure::InvalidConversion)));
+	assert_eq!(headers(&[("Content-Length", "12"), ("content-length", "13")]).content_length(), Some(Err(Failure::InvalidFormat)));
+	assert_eq!(headers(&[("Content-Length", "12, 13")]).content_length(), Some(Err(Failure::InvalidFormat)));
+}
+
+#[test]
+fn ts_http_extract_header_pass() {
+	let block = "HTTP/1.1 200 OK\r\ncontent-length: 12\r\nX-Real-IP: 2001:db8::1";
+	assert_eq!(HttpWrapper::extract_header("Content-Length", block), Some(String::from("12")));
+	assert_eq!(HttpWrapper::extract_header("X-Real-IP", block), Some(String::from("2001:db8::1")));
+	assert_eq!(HttpWrapper::content_len(block.as_bytes()), Some(12));
+	assert_eq!(HttpWrapper::content_len(b"HTTP/1.1 200 OK\r\nContent-Length: x"), None);
+}
+
+#[test]
+fn ts_http_deserialise_http_request_content_length_pass() {
+	// Only Content-Length bytes of the body are the message
+	let v = Vec::from(&b"POST /spring/ HTTP/1.1\r\ncontent-length: 12\r\n\r\ninfo networkfoo"[..]);
+	let r = HttpWrapper::deserialise_request(v, &mut peer());
+	assert_eq!(r.unwrap().cmd, CmdType::Info);
+}
+
+#[test]
+fn ts_http_message_encoding_text_pass() {
+	assert_eq!(MessageEncoding::from_content_type("text/plain"), Some(MessageEncoding::Text));
+	assert_eq!(MessageEncoding::from_content_type(" Text/Plain; charset=utf-8"), Some(MessageEncoding::Text));
+	assert_eq!(MessageEncoding::of(&Headers::new()), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&Headers::new()), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&headers(&[("Accept", "text/html")])), MessageEncoding::Text);
+	
+	let m = Message::from_bytes(b"info network").unwrap();
+	assert_eq!(MessageEncoding::Text.encode(&m).unwrap(), b"info network");
+	assert_eq!(MessageEncoding::Text.decode(b" info network\r\n").unwrap().cmd, CmdType::Info);
+}
+
+#[test]
+fn ts_http_message_encoding_text_fail() {
+	assert_eq!(MessageEncoding::from_content_type("text/html"), None);
+	assert_eq!(MessageEncoding::from_content_type(""), None);
+	assert!(MessageEncoding::Text.decode(b"void").is_err());
+}
+
+#[test]
+fn ts_http_request_respond_text_pass() {
+	let mut stream = &b"POST /spring/ HTTP/1.1\r\nContent-Length: 12\r\n\r\ninfo network"[..];
+	let r = RequestReader::new().read(&mut stream, peer()).unwrap();
+	assert_eq!(r.encoding(), MessageEncoding::Text);
+	assert_eq!(r.response_encoding(), MessageEncoding::Text);
+	
+	let response = Message::from_bytes(b"200").unwrap();
+	assert_eq!(r.respond(&response), HttpWrapper::serialise_response(&response));
+}
+
+#[test]
+fn ts_http_endpoint_ipv6_pass() {
+	let n = Node::new("foo", "[2001:db8::1]:8080/node", "2001:db8::1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let e = Endpoint::from_node(&n);
+	assert_eq!(e.address(), "2001:db8::1");
+	assert_eq!(e.port(), 8080);
+	assert_eq!(e.path(), "node/spring");
+	assert_eq!(e.host_header(), "[2001:db8::1]:8080");
+	
+	let e = Endpoint::new("[::1]", "::1", "spring");
+	assert_eq!(e.address(), "::1");
+	assert_eq!(e.host_header(), "[::1]");
+	assert_eq!(e.with_port(8080).host_header(), "[::1]:8080");
+}
+
+#[test]
+fn ts_http_outbound_ipv6_pass() {
+	// Skip where the host has no IPv6 loopback
+	let listener = match TcpListener::bind("[::1]:0") {
+		Ok(l) => l,
+		Err(_) => return,
+	};
+	let port = listener.local_addr().unwrap().port();
+	
+	let handle = thread::spawn(move || {
+		let (mut stream, peer) = listener.accept().unwrap();
+		let mut buf = [0; 4096];
+		let size = stream.read(&mut buf).unwrap();
+		stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n200").unwrap();
+		(peer, String::from_utf8_lossy(&buf[..size]).into_owned())
+	});
+	
+	let n = Node::new("foo", &format!("[::1]:{}", port), "::1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let m = Message::from_bytes(b"info network").unwrap();
+	let r = Outbound::new().request_node(&m, &n).unwrap();
+	assert_eq!(r.to_bytes(), b"200");
+	
+	let (peer, request) = handle.join().unwrap();
+	assert!(peer.is_ipv6());
+	assert!(request.contains(&format!("Host: [::1]:{}\r\n", port)));
+}
+
+#[test]
+fn ts_http_deserialise_request_ipv6_pass() {
+	let bytes = request_bytes("info network", "X-Forwarded-For: 2001:db8::7\r\n");
+	
+	// A loopback proxy forwarding for an IPv6 client
+	let mut address = SocketAddr::from_str("[::1]:80").unwrap();
+	assert!(HttpWrapper::deserialise_request(bytes.clone(), &mut address).is_ok());
+	assert_eq!(address, SocketAddr::from_str("[2001:db8::7]:80").unwrap());
+	
+	// An IPv6 client that is not a proxy
+	let mut address = SocketAddr::from_str("[2001:db8::9]:80").unwrap();
+	assert!(HttpWrapper::deserialise_request(bytes.clone(), &mut address).is_ok());
+	assert_eq!(address, SocketAddr::from_str("[2001:db8::9]:80").unwrap());
+	
+	// An IPv4 client on a dual stack socket
+	let mut address = SocketAddr::from_str("[::ffff:192.0.2.1]:80").unwrap();
+	assert!(HttpWrapper::deserialise_request(bytes, &mut address).is_ok());
+	assert_eq!(address, SocketAddr::from_str("192.0.2.1:80").unwrap());
+}
+
+#[test]
+fn ts_http_endpoint_from_node_no_address_pass() {
+	let n = Node::new("foo", "foo.tld:8080", None, NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let e = Endpoint::from_node(&n);
+	assert_eq!(e.address(), "foo.tld");
+	assert_eq!(e.port(), 8080);
+	
+	let n = Node::new("foo", "[::1]", None, NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	assert_eq!(Endpoint::from_node(&n).address(), "::1");
+}
+
+#[test]
+fn ts_http_response_parser_body_too_large_fail() {
+	let mut p = ResponseParser::new().with_max_body(4);
+	assert!(matches!(p.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n"), Err(OutboundError::BodyTooLarge)));
+	
+	let mut p = ResponseParser::new().with_max_body(4);
+	assert!(p.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n200\r\n").is_ok());
+	assert!(matches!(p.feed(b"2\r\nab\r\n0\r\n\r\n"), Err(OutboundError::BodyTooLarge)));
+	
+	let mut p = ResponseParser::new().with_max_body(4);
+	assert!(p.feed(b"HTTP/1.1 200 OK\r\n\r\n200").is_ok());
+	assert!(matches!(p.feed(b"00"), Err(OutboundError::BodyTooLarge)));
+	
+	let mut p = ResponseParser::new().with_max_body(4);
+	assert!(p.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n2000").unwrap());
+}
+
+#[test]
+fn ts_http_outbound_max_body_fail() {
+	let (port, handle) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 26\r\n\r\n200 19 service/text foobar");
+	
+	let e = Endpoint::new("127.0.0.1", "foo.tld", "spring").with_port(port);
+	let r = Outbound::new().with_max_body(16).request_endpoint(b"info network", &e);
+	assert!(matches!(r, Err(OutboundError::BodyTooLarge)));
+	handle.join().unwrap();
+}
+
+#[test]
+fn ts_http_outbound_read_timeout_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let handle = thread::spawn(move || {
+		let (_stream, _) = listener.accept().unwrap();
+		thread::sleep(Duration::from_secs(2));
+	});
+	
+	let start = Instant::now();
+	let e = Endpoint::new("127.0.0.1", "foo.tld", "spring").with_port(port);
+	let r = Outbound::new().with_read_timeout(Duration::from_millis(100)).request_endpoint(b"info network", &e);
+	assert!(matches!(r, Err(OutboundError::Timeout)), "{:?}", r);
+	assert!(start.elapsed() < Duration::from_secs(1));
+	handle.join().unwrap();
+}
+
+#[test]
+fn ts_http_outbound_slow_drip_timeout_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	thread::spawn(move || {
+		let (mut stream, _) = listener.accept().unwrap();
+		let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n");
+		for _ in 0..100 {
+			thread::sleep(Duration::from_millis(20));
+			if stream.write_all(b"2").is_err() {
+				break
+			}
+		}
+	});
+	
+	let start = Instant::now();
+	let e = Endpoint::new("127.0.0.1", "foo.tld", "spring").with_port(port);
+	let r = Outbound::new().with_read_timeout(Duration::from_millis(200)).request_endpoint(b"info network", &e);
+	assert!(matches!(r, Err(OutboundError::Timeout)), "{:?}", r);
+	assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn ts_http_outbound_request_node_status_fail() {
+	let (port, handle) = serve_once(b"HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\nContent-Length: 22\r\n\r\n<h1>Not Found</h1>\r\n\r\n");
+	
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	match Outbound::new().request_node(&msg, &node(&format!("foo.tld:{}", port))) {
+		Err(OutboundError::Status(404)) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+	handle.join().unwrap();
+	
+	assert!(OutboundError::Status(503).is_transient());
+	assert!(!OutboundError::Status(404).is_transient());
+}
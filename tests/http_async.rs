@@ -0,0 +1,195 @@
+#![cfg(feature = "async")]
+#[macro_use]
+extern crate spring_dvs;
+extern crate tokio;
+
+use std::time::Duration;
+
+use tokio::net::TcpListener;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use spring_dvs::http::OutboundError;
+use spring_dvs::http_async::AsyncOutbound;
+use spring_dvs::protocol::*;
+use spring_dvs::node::Node;
+
+/// Accept one connection, read the request and reply with each
+/// of the pieces in turn before closing
+async fn serve_once(pieces: Vec<&'static [u8]>) -> u16 {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let port = listener.local_addr().unwrap().port();
+	
+	tokio::spawn(async move {
+		let (mut stream, _) = listener.accept().await.unwrap();
+		let mut buf = [0; 4096];
+		let _ = stream.read(&mut buf).await;
+		for p in pieces {
+			stream.write_all(p).await.unwrap();
+			stream.flush().await.unwrap();
+			tokio::time::sleep(Duration::from_millis(5)).await;
+		}
+	});
+	
+	port
+}
+
+fn outbound(port: u16) -> AsyncOutbound {
+	AsyncOutbound::new()
+		.with_port(port)
+		.with_read_timeout(Duration::from_millis(500))
+}
+
+#[tokio::test]
+async fn ts_http_async_request_content_length_pass() {
+	let port = serve_once(vec![b"HTTP/1.1 200 OK\r\nContent-Le", b"ngth: 26\r\n\r\n200 19 serv", b"ice/text foobar"]).await;
+	let r = outbound(port).request(b"info network", "127.0.0.1", "foo.tld", "spring").await;
+	assert_eq!(r.unwrap(), b"200 19 service/text foobar".to_vec());
+}
+
+#[tokio::test]
+async fn ts_http_async_request_chunked_pass() {
+	let port = serve_once(vec![
+		b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n",
+		b"7\r\n200 19 \r\n",
+		b"d;ext=1\r\nservice/t",
+		b"ext \r\n6\r\nfoobar\r\n0\r\n\r\n",
+	]).await;
+	let r = outbound(port).request(b"info network", "127.0.0.1", "foo.tld", "spring").await;
+	assert_eq!(r.unwrap(), b"200 19 service/text foobar".to_vec());
+}
+
+#[tokio::test]
+async fn ts_http_async_request_node_pass() {
+	let port = serve_once(vec![b"HTTP/1.1 200 OK\r\nContent-Length: 28\r\n\r\n200 19 service/text foobar\r\n"]).await;
+	let node = Node::new("foo", &format!("127.0.0.1:{}/node", port), "127.0.0.1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	
+	let r = outbound(port).request_node(&msg, &node).await.unwrap();
+	assert_eq!(msg_response_servicetext!(r.content).content, "foobar");
+}
+
+#[tokio::test]
+async fn ts_http_async_request_node_parse_fail() {
+	let port = serve_once(vec![b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nfoobar"]).await;
+	let node = Node::new("foo", &format!("127.0.0.1:{}", port), "127.0.0.1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	
+	match outbound(port).request_node(&msg, &node).await {
+		Err(OutboundError::Parse(_)) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_connect_fail() {
+	// Bind then drop a listener so the port is very likely closed
+	let port = {
+		let l = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		l.local_addr().unwrap().port()
+	};
+	
+	match outbound(port).request(b"info network", "127.0.0.1", "foo.tld", "spring").await {
+		Err(OutboundError::Connect(_)) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_timeout_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let port = listener.local_addr().unwrap().port();
+	tokio::spawn(async move {
+		let (_stream, _) = listener.accept().await.unwrap();
+		tokio::time::sleep(Duration::from_secs(5)).await;
+	});
+	
+	let o = outbound(port).with_read_timeout(Duration::from_millis(50));
+	match o.request(b"info network", "127.0.0.1", "foo.tld", "spring").await {
+		Err(OutboundError::Timeout) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_malformed_header_fail() {
+	let port = serve_once(vec![b"SPRING 200\r\n\r\n200"]).await;
+	match outbound(port).request(b"info network", "127.0.0.1", "foo.tld", "spring").await {
+		Err(OutboundError::MalformedHeader) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+	
+	let port = serve_once(vec![b"HTTP/1.1 200 OK\r\nContent-Length: ten\r\n\r\n200"]).await;
+	match outbound(port).request(b"info network", "127.0.0.1", "foo.tld", "spring").await {
+		Err(OutboundError::MalformedHeader) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_invalid_chunk_fail() {
+	let port = serve_once(vec![b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\nfoo\r\n0\r\n\r\n"]).await;
+	match outbound(port).request(b"info network", "127.0.0.1", "foo.tld", "spring").await {
+		Err(OutboundError::InvalidChunk) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_closed_fail() {
+	let port = serve_once(vec![b"HTTP/1.1 200 OK\r\nContent-Length: 26\r\n\r\n200 19"]).await;
+	match outbound(port).request(b"info network", "127.0.0.1", "foo.tld", "spring").await {
+		Err(OutboundError::Closed) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_slow_drip_timeout_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let port = listener.local_addr().unwrap().port();
+	tokio::spawn(async move {
+		let (mut stream, _) = listener.accept().await.unwrap();
+		let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n").await;
+		loop {
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			if stream.write_all(b"2").await.is_err() {
+				break
+			}
+		}
+	});
+	
+	let o = outbound(port).with_read_timeout(Duration::from_millis(200));
+	match o.request(b"info network", "127.0.0.1", "foo.tld", "spring").await {
+		Err(OutboundError::Timeout) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_node_status_fail() {
+	let port = serve_once(vec![b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 5\r\n\r\noops!"]).await;
+	let node = Node::new("foo", &format!("127.0.0.1:{}", port), "127.0.0.1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	
+	match outbound(port).request_node(&msg, &node).await {
+		Err(OutboundError::Status(500)) => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[tokio::test]
+async fn ts_http_async_request_node_tls_fail() {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let node = Node::new("foo", &format!("https://127.0.0.1:{}", port), "127.0.0.1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	
+	match outbound(port).request_node(&msg, &node).await {
+		Err(OutboundError::Tls(ref e)) if e.kind() == std::io::ErrorKind::Unsupported => {},
+		r => panic!("unexpected result: {:?}", r),
+	}
+	
+	// Nothing was sent to the node
+	let accepted = tokio::time::timeout(Duration::from_millis(50), listener.accept()).await;
+	assert!(accepted.is_err());
+}
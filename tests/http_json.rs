@@ -0,0 +1,129 @@
+#![cfg(feature = "json")]
+extern crate serde_json;
+extern crate spring_dvs;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::str::FromStr;
+use std::thread;
+
+use spring_dvs::http::*;
+use spring_dvs::protocol::*;
+
+fn peer() -> SocketAddr {
+	SocketAddr::from_str("192.168.1.2:55000").unwrap()
+}
+
+fn accept(value: &str) -> Headers {
+	let mut h = Headers::new();
+	h.add("Accept", value);
+	h
+}
+
+#[test]
+fn ts_http_json_content_type_pass() {
+	assert_eq!(MessageEncoding::Json.content_type(), "application/json");
+	assert_eq!(MessageEncoding::from_content_type("application/json"), Some(MessageEncoding::Json));
+	assert_eq!(MessageEncoding::from_content_type("Application/JSON; charset=utf-8"), Some(MessageEncoding::Json));
+	
+	let mut h = Headers::new();
+	h.add("content-type", "application/json");
+	assert_eq!(MessageEncoding::of(&h), MessageEncoding::Json);
+}
+
+#[test]
+fn ts_http_json_negotiate_pass() {
+	assert_eq!(MessageEncoding::negotiate(&accept("application/json")), MessageEncoding::Json);
+	assert_eq!(MessageEncoding::negotiate(&accept("application/*")), MessageEncoding::Json);
+	assert_eq!(MessageEncoding::negotiate(&accept("text/html, application/json;q=0.5")), MessageEncoding::Json);
+	assert_eq!(MessageEncoding::negotiate(&accept("text/plain;q=0.2, */*;q=0.9")), MessageEncoding::Json);
+	assert_eq!(MessageEncoding::negotiate(&accept("text/plain;q=0.4, application/json;q=0.5")), MessageEncoding::Json);
+	
+	let mut h = accept("text/html");
+	h.add("Accept", "application/json");
+	assert_eq!(MessageEncoding::negotiate(&h), MessageEncoding::Json);
+}
+
+#[test]
+fn ts_http_json_negotiate_fail() {
+	// Text on a tie and when json is not acceptable
+	assert_eq!(MessageEncoding::negotiate(&accept("*/*")), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&accept("text/plain, application/json")), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&accept("application/json;q=0")), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&accept("application/json;q=0.5, text/*")), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&accept("application/json;q=2")), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&accept("application/json;q=high")), MessageEncoding::Text);
+	assert_eq!(MessageEncoding::negotiate(&accept("*/*;q=0.1, application/json;q=0")), MessageEncoding::Text);
+}
+
+#[test]
+fn ts_http_json_request_pass() {
+	let m = Message::from_bytes(b"update foo state enabled").unwrap();
+	let body = MessageEncoding::Json.encode(&m).unwrap();
+	let request = HttpWrapper::wrap_request_as(&body, "spring.tld", "spring", MessageEncoding::Json, &accept("application/json"));
+	
+	let r = RequestReader::new().read(&mut &request[..], peer()).unwrap();
+	assert_eq!(r.header("Content-Type"), Some("application/json"));
+	assert_eq!(r.encoding(), MessageEncoding::Json);
+	assert_eq!(r.response_encoding(), MessageEncoding::Json);
+	assert_eq!(r.message.content, m.content);
+	
+	let d = HttpWrapper::deserialise_request(request, &mut peer()).unwrap();
+	assert_eq!(d.content, m.content);
+}
+
+#[test]
+fn ts_http_json_request_fail() {
+	let request = HttpWrapper::wrap_request_as(b"info network", "spring.tld", "spring", MessageEncoding::Json, &Headers::new());
+	
+	match RequestReader::new().read(&mut &request[..], peer()) {
+		Err(RequestError::Parse(ParseFailure::InvalidContentFormat)) => {},
+		r => panic!("unexpected {:?}", r),
+	}
+	
+	assert!(HttpWrapper::deserialise_request(request, &mut peer()).is_err());
+}
+
+#[test]
+fn ts_http_json_respond_pass() {
+	let request = HttpWrapper::wrap_request_with(b"info network", "spring.tld", "spring", &accept("application/json"));
+	let r = RequestReader::new().read(&mut &request[..], peer()).unwrap();
+	assert_eq!(r.encoding(), MessageEncoding::Text);
+	
+	let response = generate_response_service_text("foobar");
+	let bytes = r.respond(&response);
+	let text = String::from_utf8(bytes).unwrap();
+	assert!(text.contains("Content-Type: application/json\r\n"));
+	
+	let body = &text[text.find("\r\n\r\n").unwrap()+4..];
+	let json : serde_json::Value = serde_json::from_str(body).unwrap();
+	assert_eq!(json["content"]["value"]["content"]["value"]["content"], "foobar");
+	assert_eq!(MessageEncoding::Json.decode(body.as_bytes()).unwrap().content, response.content);
+}
+
+#[test]
+fn ts_http_json_endpoint_pass() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	
+	let handle = thread::spawn(move || {
+		let (mut stream, addr) = listener.accept().unwrap();
+		let request = RequestReader::new().read(&mut stream, addr).unwrap();
+		stream.write_all(&request.respond(&generate_response_service_text("foobar"))).unwrap();
+		let mut rest = Vec::new();
+		let _ = stream.read_to_end(&mut rest);
+		request
+	});
+	
+	let e = Endpoint::new("127.0.0.1", "spring.tld", "spring").with_port(port).with_encoding(MessageEncoding::Json);
+	assert_eq!(e.encoding(), MessageEncoding::Json);
+	
+	let msg = Message::from_bytes(b"info node foo state").unwrap();
+	let response = Outbound::new().request_message(&msg, &e).unwrap();
+	assert_eq!(response.content, generate_response_service_text("foobar").content);
+	
+	let request = handle.join().unwrap();
+	assert_eq!(request.encoding(), MessageEncoding::Json);
+	assert_eq!(request.header("Accept"), Some("application/json"));
+	assert_eq!(request.message.content, msg.content);
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "tls")]
+extern crate spring_dvs;
+extern crate rcgen;
+extern crate rustls;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+
+use spring_dvs::http::{Endpoint, Outbound, OutboundError};
+use spring_dvs::node::Node;
+use spring_dvs::protocol::*;
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+	Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// A self-signed certificate for localhost
+fn certificate() -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+	let ck = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+	let key = PrivatePkcs8KeyDer::from(ck.signing_key.serialize_der());
+	(ck.cert.der().clone(), PrivateKeyDer::Pkcs8(key))
+}
+
+/// Client configuration trusting only `cert`
+fn client_config(cert: &CertificateDer<'static>) -> Arc<rustls::ClientConfig> {
+	let mut roots = rustls::RootCertStore::empty();
+	roots.add(cert.clone()).unwrap();
+	
+	Arc::new(rustls::ClientConfig::builder_with_provider(provider())
+		.with_safe_default_protocol_versions().unwrap()
+		.with_root_certificates(roots)
+		.with_no_client_auth())
+}
+
+/// Accept one TLS connection and reply to the request with `body`
+fn serve_tls_once(body: &'static str) -> (u16, CertificateDer<'static>) {
+	let (cert, key) = certificate();
+	
+	let config = Arc::new(rustls::ServerConfig::builder_with_provider(provider())
+		.with_safe_default_protocol_versions().unwrap()
+		.with_no_client_auth()
+		.with_single_cert(vec![cert.clone()], key).unwrap());
+	
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	
+	thread::spawn(move || {
+		let (stream, _) = listener.accept().unwrap();
+		let conn = rustls::ServerConnection::new(config).unwrap();
+		let mut tls = rustls::StreamOwned::new(conn, stream);
+		
+		let mut buf = [0; 4096];
+		if tls.read(&mut buf).is_err() {
+			// The client rejected the certificate
+			return
+		}
+		
+		let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+		tls.write_all(response.as_bytes()).unwrap();
+		tls.conn.send_close_notify();
+		let _ = tls.flush();
+	});
+	
+	(port, cert)
+}
+
+fn node(host: &str) -> Node {
+	Node::new("foo", host, "127.0.0.1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "")
+}
+
+#[test]
+fn ts_http_tls_request_message_pass() {
+	let (port, cert) = serve_tls_once("200 19 service/text foobar");
+	
+	let e = Endpoint::from_node(&node(&format!("https://localhost:{}/node", port)))
+		.with_tls_config(client_config(&cert));
+	assert!(e.is_tls());
+	
+	let msg = Message::from_bytes(b"info node foo").unwrap();
+	let r = Outbound::new().request_message(&msg, &e).unwrap();
+	assert_eq!(r.to_bytes(), b"200 19 service/text foobar".to_vec());
+}
+
+#[test]
+fn ts_http_tls_request_endpoint_pass() {
+	let (port, cert) = serve_tls_once("200");
+	
+	let e = Endpoint::new("127.0.0.1", "localhost", "spring")
+		.with_port(port)
+		.with_tls(true)
+		.with_tls_config(client_config(&cert));
+	
+	assert_eq!(Outbound::new().request_endpoint(b"info network", &e).unwrap(), b"200".to_vec());
+}
+
+#[test]
+fn ts_http_tls_untrusted_certificate_fail() {
+	// The default configuration only trusts the webpki roots
+	let (port, _) = serve_tls_once("200");
+	
+	let e = Endpoint::from_node(&node(&format!("https://localhost:{}", port)));
+	match Outbound::new().request_endpoint(b"info network", &e) {
+		Err(ref e @ OutboundError::Tls(_)) => assert!(!e.is_transient()),
+		r => panic!("unexpected result: {:?}", r),
+	}
+}
+
+#[test]
+fn ts_http_tls_hostname_mismatch_fail() {
+	let (port, cert) = serve_tls_once("200");
+	
+	let e = Endpoint::from_node(&node(&format!("https://foo.tld:{}", port)))
+		.with_tls_config(client_config(&cert));
+	assert!(matches!(Outbound::new().request_endpoint(b"info network", &e), Err(OutboundError::Tls(_))));
+}
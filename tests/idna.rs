@@ -0,0 +1,107 @@
+#![cfg(feature="idna")]
+extern crate spring_dvs;
+
+use spring_dvs::names::{to_ascii, to_unicode};
+use spring_dvs::formats::*;
+use spring_dvs::spaces::*;
+use spring_dvs::uri::Uri;
+
+#[test]
+fn ts_idna_to_ascii_pass() {
+	assert_eq!(to_ascii("café").unwrap(), "xn--caf-dma");
+	assert_eq!(to_ascii("CAFÉ").unwrap(), "xn--caf-dma");
+	assert_eq!(to_ascii("foo-bar").unwrap(), "foo-bar");
+}
+
+#[test]
+fn ts_idna_to_ascii_fail() {
+	assert!(to_ascii("caf\u{0378}").is_none());
+}
+
+#[test]
+fn ts_idna_to_unicode_pass() {
+	assert_eq!(to_unicode("xn--caf-dma"), "café");
+	assert_eq!(to_unicode("foo-bar"), "foo-bar");
+	assert_eq!(to_unicode("xn--"), "xn--");
+}
+
+#[test]
+fn ts_idna_node_single_fmt_pass() {
+	let o = NodeSingleFmt::from_str("Gàidhlig").unwrap();
+	assert!(o.spring.starts_with("xn--"));
+	assert!(o.spring.is_ascii());
+	assert_eq!(o.spring_unicode(), "gàidhlig");
+	assert_eq!(o.to_string(), o.spring);
+}
+
+#[test]
+fn ts_idna_node_single_fmt_fail() {
+	assert_eq!(NodeSingleFmt::from_str("gàidh\u{3002}lig"), Err(ParseFailure::InvalidNaming));
+	assert_eq!(NodeSingleFmt::from_str("caf\u{0378}"), Err(ParseFailure::InvalidNaming));
+}
+
+#[test]
+fn ts_idna_node_double_fmt_pass() {
+	let o = NodeDoubleFmt::from_str("ŵyl,foo.bar").unwrap();
+	assert!(o.spring.starts_with("xn--"));
+	assert_eq!(o.spring_unicode(), "ŵyl");
+	assert_eq!(o.host, "foo.bar");
+}
+
+#[test]
+fn ts_idna_uri_route_pass() {
+	let uri = Uri::new("spring://ŵyl.esusx.uk").unwrap();
+	let ascii = NodeSingleFmt::from_str("ŵyl").unwrap().spring;
+
+	assert_eq!(uri.route()[0], ascii);
+	assert_eq!(uri.route_unicode(), vec!["ŵyl", "esusx", "uk"]);
+	assert_eq!(uri.to_string(), format!("spring://{}.esusx.uk", ascii));
+}
+
+#[test]
+fn ts_idna_node_triple_quad_info_fmt_pass() {
+	let ascii = NodeSingleFmt::from_str("ŵyl").unwrap().spring;
+	
+	assert_eq!(NodeTripleFmt::from_str("ŵyl,foo.bar,192.168.1.2").unwrap().spring, ascii);
+	assert_eq!(NodeQuadFmt::from_str("ŵyl,foo.bar,192.168.1.2,http").unwrap().spring, ascii);
+	assert_eq!(NodeInfoFmt::from_str("spring:ŵyl,host:foo.bar").unwrap().spring, ascii);
+	assert_eq!(NodeInfoFmt::from_str("spring:caf\u{0378}"), Err(ParseFailure::InvalidNaming));
+}
+
+#[test]
+fn ts_idna_netspace_lookup_pass() {
+	let spring = NodeSingleFmt::from_str("gàidhlig").unwrap().spring;
+	let node = Node::new("gàidhlig", "foo.tld", None, NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	assert_eq!(node.springname(), spring);
+
+	let nio = MemoryNetspace::new();
+	assert!(nio.gsn_node_register(&node).is_ok());
+	assert!(nio.gtn_geosub_register_node(&node, "esusx").is_ok());
+
+	assert_eq!(nio.gsn_node_by_springname("gàidhlig").unwrap().springname(), spring);
+	assert_eq!(nio.gsn_node_by_springname(&spring).unwrap().springname(), spring);
+	assert!(nio.gtn_geosub_node_by_springname("gàidhlig", "esusx").is_ok());
+	assert!(nio.gtn_geosub_node_by_springname(&spring, "esusx").is_ok());
+	assert_eq!(nio.gsn_node_by_springname("caf\u{0378}").unwrap_err(), NetspaceFailure::NodeNotFound);
+	assert_eq!(nio.gtn_geosub_root_nodes("esusx").len(), 1);
+	
+	// The same node under another spelling is the same node
+	let other = Node::new("GÀIDHLIG", "foo.tld", None, NodeService::Http, NodeState::Disabled, NodeRole::Org, "");
+	assert_eq!(nio.gsn_node_register(&other), Err(NetspaceFailure::DuplicateNode));
+	assert!(nio.gsn_node_update_state(&other).is_ok());
+	assert_eq!(nio.gsn_node_by_springname(&spring).unwrap().state(), NodeState::Disabled);
+	assert!(nio.gtn_geosub_unregister_node(&other, "esusx").is_ok());
+	assert!(nio.gsn_node_unregister(&other).is_ok());
+	assert!(nio.gsn_nodes().is_empty());
+}
+
+#[test]
+fn ts_idna_token_pass() {
+	let nio = MemoryNetspace::new();
+	let tokens = spring_dvs::token::TokenIssuer::new(&nio);
+	let t = tokens.issue("ŵyl").unwrap();
+	
+	let reg = format!("ŵyl,foo.tld;org;http;{}\nKEY", t);
+	let reg = ContentRegistration::from_bytes(reg.as_bytes()).unwrap();
+	assert!(tokens.consume(&reg).is_ok());
+}
@@ -0,0 +1,122 @@
+extern crate spring_dvs;
+use spring_dvs::names::*;
+use spring_dvs::protocol::{NodeRole, NodeService, NodeState};
+
+#[test]
+fn ts_names_springname_pass() {
+	assert!(is_springname("foo-bar"));
+	assert!(is_springname("foobar"));
+	assert!(is_springname("f"));
+	assert!(is_springname("foo123"));
+	assert!(is_springname("123"));
+	assert!(is_springname(&"a".repeat(MAX_LABEL)));
+}
+
+#[test]
+fn ts_names_springname_fail() {
+	assert!(!is_springname("foo.bar"));
+	assert!(!is_springname("foobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobar"));
+	assert!(!is_springname(""));
+	assert!(!is_springname("foo.123"));
+	assert!(!is_springname("foo_123"));
+	assert!(!is_springname("foo*123"));
+	assert!(!is_springname("-foo"));
+	assert!(!is_springname("foo-"));
+	assert!(!is_springname(&"a".repeat(MAX_LABEL + 1)));
+}
+
+#[test]
+fn ts_names_hostname_pass() {
+	assert!(is_hostname("foo.bar"));
+	assert!(is_hostname("foo-bar"));
+	assert!(is_hostname("f"));
+	assert!(is_hostname("192.168.1.1"));
+	assert!(is_hostname("spring.example.tld"));
+
+	let long = vec!["a".repeat(MAX_LABEL); 4].join(".");
+	assert_eq!(long.len(), 255);
+	assert!(is_hostname(&long[2..]));
+}
+
+#[test]
+fn ts_names_hostname_fail() {
+	assert!(!is_hostname(""));
+	assert!(!is_hostname("foo..bar"));
+	assert!(!is_hostname(".foo"));
+	assert!(!is_hostname("foo."));
+	assert!(!is_hostname("foo.-bar"));
+	assert!(!is_hostname("foo_bar.tld"));
+	assert!(!is_hostname("foo.bar/spring"));
+	assert!(!is_hostname("foo.bar:8080"));
+
+	let long = vec!["a".repeat(MAX_LABEL); 4].join(".");
+	assert!(!is_hostname(&long[1..]));
+}
+
+#[test]
+fn ts_names_hostpath_pass() {
+	assert!(is_hostpath(""));
+	assert!(is_hostpath("spring"));
+	assert!(is_hostpath("spring/node_1/~dvs.v2/"));
+}
+
+#[test]
+fn ts_names_hostpath_fail() {
+	assert!(!is_hostpath("spring?x=1"));
+	assert!(!is_hostpath("spring node"));
+	assert!(!is_hostpath("spring%20node"));
+}
+
+#[test]
+fn ts_names_hostfield_pass() {
+	assert!(is_hostfield("foo.bar"));
+	assert!(is_hostfield("foo-bar"));
+	assert!(is_hostfield("f"));
+	assert!(is_hostfield("foo123"));
+	assert!(is_hostfield("foo.bar:8080/spring"));
+	assert!(is_hostfield("https://foo.bar/spring"));
+	assert!(is_hostfield("https://foo.bar:8443"));
+	assert!(is_hostfield("[2001:db8::1]"));
+	assert!(is_hostfield("[::1]:8080/spring"));
+	assert!(is_hostfield("https://[2001:db8::1]/spring"));
+	assert!(is_hostfield("foo.bar/spring/path/that/is/longer/than/sixty/three/characters/in/all"));
+}
+
+#[test]
+fn ts_names_hostfield_fail() {
+	assert!(!is_hostfield("foo(bar"));
+	assert!(!is_hostfield("foobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobarfoobar"));
+	assert!(!is_hostfield(""));
+	assert!(!is_hostfield("foo[123"));
+	assert!(!is_hostfield("foo_123"));
+	assert!(!is_hostfield("foo*123"));
+	assert!(!is_hostfield("foo.bar:http"));
+	assert!(!is_hostfield("foo.bar:0"));
+	assert!(!is_hostfield("foo.bar:+80"));
+	assert!(!is_hostfield("foo.bar:65536/spring"));
+	assert!(!is_hostfield("ftp://foo.bar"));
+	assert!(!is_hostfield("https://"));
+	assert!(!is_hostfield("/spring"));
+	assert!(!is_hostfield("2001:db8::1"));
+	assert!(!is_hostfield("[2001:db8::1"));
+	assert!(!is_hostfield("[foo.bar]"));
+	assert!(!is_hostfield("[::1]:0"));
+	assert!(!is_hostfield("[::1]8080"));
+}
+
+#[test]
+fn ts_names_to_ascii_lowercase_pass() {
+	assert_eq!(to_ascii("Foo.BAR").unwrap(), "foo.bar");
+	assert_eq!(to_ascii("foo").unwrap(), "foo");
+	
+	let node = spring_dvs::node::Node::new("Foo", "foo.tld", None, NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	assert_eq!(node.springname(), "foo");
+}
+
+#[test]
+#[cfg(not(feature = "idna"))]
+fn ts_names_unicode_without_idna_fail() {
+	assert_eq!(to_ascii("café").unwrap(), "café");
+	assert!(!is_springname(&to_ascii("café").unwrap()));
+	assert_eq!(to_unicode("xn--caf-dma"), "xn--caf-dma");
+}
@@ -41,7 +41,7 @@ fn ts_node_from_str_format_node_triple_pass() {
 	let n : Node = o.unwrap();
 	assert_eq!(n.springname(), "foobar");
 	assert_eq!(n.hostname(), "barfoo");
-	assert_eq!(n.address(), "127.3.4.5");
+	assert_eq!(n.address(), "127.3.4.5".parse().ok());
 	assert_eq!(n.hostpath(), "");
 }
 
@@ -54,7 +54,7 @@ fn ts_node_from_str_format_node_triple_2_pass() {
 	assert_eq!(n.springname(), "foobar");
 	assert_eq!(n.hostname(), "barfoo");
 	assert_eq!(n.hostpath(), "path");
-	assert_eq!(n.address(), "127.3.4.5");
+	assert_eq!(n.address(), "127.3.4.5".parse().ok());
 }
 
 #[test]
@@ -66,7 +66,7 @@ fn ts_node_from_str_format_node_quad_pass() {
 	assert_eq!(n.springname(), "foobar");
 	assert_eq!(n.hostname(), "barfoo");
 	assert_eq!(n.hostpath(), "");
-	assert_eq!(n.address(), "127.3.4.5");
+	assert_eq!(n.address(), "127.3.4.5".parse().ok());
 	assert_eq!(n.service(), NodeService::Http);
 }
 
@@ -80,7 +80,7 @@ fn ts_node_from_str_format_node_quad_2_pass() {
 	assert_eq!(n.springname(), "foobar");
 	assert_eq!(n.hostname(), "barfoo");
 	assert_eq!(n.hostpath(), "path");
-	assert_eq!(n.address(), "127.3.4.5");
+	assert_eq!(n.address(), "127.3.4.5".parse().ok());
 	assert_eq!(n.service(), NodeService::Http);
 }
 
@@ -93,7 +93,7 @@ fn ts_node_from_str_format_node_info_pass() {
 	assert_eq!(n.springname(), "foobar");
 	assert_eq!(n.hostname(), "barfoo");
 	assert_eq!(n.hostpath(), "");
-	assert_eq!(n.address(), "127.3.4.5");
+	assert_eq!(n.address(), "127.3.4.5".parse().ok());
 	assert_eq!(n.role(), NodeRole::Hybrid);
 }
 
@@ -106,10 +106,61 @@ fn ts_node_from_str_format_node_info_2_pass() {
 	assert_eq!(n.springname(), "foobar");
 	assert_eq!(n.hostname(), "barfoo");
 	assert_eq!(n.hostpath(), "path");
-	assert_eq!(n.address(), "127.3.4.5");
+	assert_eq!(n.address(), "127.3.4.5".parse().ok());
 	assert_eq!(n.role(), NodeRole::Hybrid);
 }
 
+#[test]
+fn ts_node_from_str_format_node_double_tls_pass() {
+	let o = Node::from_str("foobar,https://barfoo:8443/path");
+	assert!(o.is_ok());
+	
+	let n : Node = o.unwrap();
+	assert_eq!(n.springname(), "foobar");
+	assert_eq!(n.hostname(), "barfoo");
+	assert_eq!(n.hostpath(), "path");
+	assert_eq!(n.port(), Some(8443));
+	assert!(n.is_tls());
+	assert_eq!(n.hostfield(), "https://barfoo:8443/path");
+}
+
+#[test]
+fn ts_node_from_str_format_node_info_tls_pass() {
+	let o = Node::from_str("spring:foobar,host:https://barfoo/path,address:127.3.4.5");
+	assert!(o.is_ok());
+	
+	let n : Node = o.unwrap();
+	assert_eq!(n.hostname(), "barfoo");
+	assert_eq!(n.hostpath(), "path");
+	assert_eq!(n.port(), None);
+	assert!(n.is_tls());
+}
+
+#[test]
+fn ts_node_new_hostfield_port_pass() {
+	let n = Node::new("foobar", "barfoo:8080", "127.3.4.5".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	assert_eq!(n.hostname(), "barfoo");
+	assert_eq!(n.hostpath(), "");
+	assert_eq!(n.port(), Some(8080));
+	assert!(!n.is_tls());
+	assert_eq!(n.authority(), "barfoo:8080");
+	
+	let n = Node::new("foobar", "http://barfoo/path", "127.3.4.5".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	assert_eq!(n.hostname(), "barfoo");
+	assert_eq!(n.port(), None);
+	assert!(!n.is_tls());
+	assert_eq!(n.hostfield(), "barfoo/path");
+}
+
+#[test]
+fn ts_node_new_hostfield_port_fail() {
+	// A port that is not a number stays part of the hostname
+	let n = Node::new("foobar", "barfoo:http/path", "127.3.4.5".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	assert_eq!(n.hostname(), "barfoo:http");
+	assert_eq!(n.hostpath(), "path");
+	assert_eq!(n.port(), None);
+}
+
 #[test]
 fn ts_node_from_str_format_node_info_fail() {
 	let o = Node::from_str("spring:foobar,hosting:barfoo,address:127.3.4.5,role:hybrid");
@@ -195,7 +246,7 @@ fn ts_node_to_node_triple_pass() {
 	
 	assert_eq!(f.spring, "foobar");
 	assert_eq!(f.host, "barfoo");
-	assert_eq!(f.address, "127.3.4.5");
+	assert_eq!(f.address.to_string(), "127.3.4.5");
 }
 
 #[test]
@@ -211,7 +262,7 @@ fn ts_node_to_node_triple_2_pass() {
 	
 	assert_eq!(f.spring, "foobar");
 	assert_eq!(f.host, "barfoo/path");
-	assert_eq!(f.address, "127.3.4.5");
+	assert_eq!(f.address.to_string(), "127.3.4.5");
 }
 
 #[test]
@@ -237,7 +288,7 @@ fn ts_node_to_node_quad_pass() {
 	
 	assert_eq!(f.spring, "foobar");
 	assert_eq!(f.host, "barfoo");
-	assert_eq!(f.address, "127.3.4.5");
+	assert_eq!(f.address.to_string(), "127.3.4.5");
 	assert_eq!(f.service, NodeService::Http);
 }
 
@@ -254,7 +305,7 @@ fn ts_node_to_node_quad_2_pass() {
 	
 	assert_eq!(f.spring, "foobar");
 	assert_eq!(f.host, "barfoo/path");
-	assert_eq!(f.address, "127.3.4.5");
+	assert_eq!(f.address.to_string(), "127.3.4.5");
 	assert_eq!(f.service, NodeService::Http);
 }
 
@@ -282,7 +333,7 @@ fn ts_node_to_node_info_pass() {
 	
 	assert_eq!(f.spring, "foobar");
 	assert_eq!(f.host, "barfoo");
-	assert_eq!(f.address, "127.3.4.5");
+	assert_eq!(f.address, "127.3.4.5".parse().ok());
 	assert_eq!(f.service, NodeService::Http);
 	assert_eq!(f.state, NodeState::Enabled);
 	assert_eq!(f.role, NodeRole::Hybrid);
@@ -314,4 +365,52 @@ fn ts_nodevec_quadvec() {
 	assert_eq!(qv[2].spring, "farboo");
 	
 		
-}
\ No newline at end of file
+}
+
+#[test]
+fn ts_node_from_str_format_node_triple_ipv6_pass() {
+	let n = Node::from_str("foobar,[2001:db8::1]:8080/path,2001:db8::1").unwrap();
+	assert_eq!(n.springname(), "foobar");
+	assert_eq!(n.hostname(), "[2001:db8::1]");
+	assert_eq!(n.port(), Some(8080));
+	assert_eq!(n.hostpath(), "path");
+	assert_eq!(n.address(), "2001:db8::1".parse().ok());
+	assert_eq!(n.hostfield(), "[2001:db8::1]:8080/path");
+	
+	let n = Node::from_str("foobar,https://[::1],::1").unwrap();
+	assert_eq!(n.hostname(), "[::1]");
+	assert_eq!(n.port(), None);
+	assert!(n.is_tls());
+}
+
+#[test]
+fn ts_node_to_node_quad_ipv6_pass() {
+	let n = Node::new("foo", "foo.tld", "2001:db8::1".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Org, "");
+	let q = n.to_node_quad().unwrap();
+	assert_eq!(q.to_string(), "foo,foo.tld,2001:db8::1,http");
+	assert_eq!(Node::from_node_quad(&NodeQuadFmt::from_str(&q.to_string()).unwrap()).address(), "2001:db8::1".parse().ok());
+}
+
+#[test]
+fn ts_node_address_unset_pass() {
+	// A node without an address is not given a placeholder
+	let n = Node::from_str("foobar,barfoo").unwrap();
+	assert_eq!(n.address(), None);
+	assert!(n.to_node_triple().is_none());
+	assert!(n.to_node_quad().is_none());
+	
+	let mut n = Node::from_str("foobar").unwrap();
+	assert_eq!(n.address(), None);
+	
+	n.update_address("::ffff:192.168.1.2".parse().ok());
+	assert_eq!(n.address(), "192.168.1.2".parse().ok());
+	
+	n.update_address(None);
+	assert_eq!(n.address(), None);
+}
+
+#[test]
+fn ts_node_address_invalid_fail() {
+	assert!(matches!(Node::from_str("foobar,barfoo,0.0.0"), Err(ParseFailure::InvalidAddress)));
+	assert!(matches!(Node::from_str("spring:foobar,address:barfoo"), Err(ParseFailure::InvalidAddress)));
+}
@@ -622,4 +622,156 @@ fn ts_message_service_fail () {
 	
 	let o = Message::from_bytes(b"service sprinddg://cci.esusx.uk/service/"); 
 	assert!(o.is_err());
-}
\ No newline at end of file
+}
+#[test]
+fn ts_content_response_resolution_from_bytes_pass () {
+	let o = ContentResponse::from_bytes(b"200 60 resolve referral foo,bar,127.0.0.1,http;bar,foo,127.0.0.2,http;");
+	assert!(o.is_ok());
+	let cr : ContentResponse = o.unwrap();
+	assert_eq!(cr.code, Response::Ok);
+	
+	let r = match cr.content {
+		ResponseContent::Resolution(r) => r,
+		_ => panic!("unexpected content"),
+	};
+	
+	assert_eq!(r.kind, ResolutionKind::Referral);
+	assert_eq!(r.network.len(), 2);
+	assert_eq!(r.network[1].spring, "bar");
+	assert_eq!(r.network[1].address.to_string(), "127.0.0.2");
+}
+
+#[test]
+fn ts_content_response_resolution_from_bytes_fail () {
+	let o = ContentResponse::from_bytes(b"200 60 resolve foo,bar,127.0.0.1,http;");
+	assert!(o.is_err());
+	
+	let o = ContentResponse::from_bytes(b"200 60 resolve node foo,bar,127.0.0.1;");
+	assert!(o.is_err());
+}
+
+fn assert_response_roundtrip(r: ContentResponse) {
+	let back = ContentResponse::from_bytes(&r.to_bytes()).unwrap();
+	assert_eq!(back, r);
+	
+	let m = Message::new(CmdType::Response, MessageContent::Response(r.clone()));
+	let back = Message::from_bytes(&m.to_bytes()).unwrap();
+	assert_eq!(msg_response!(back.content), &r);
+}
+
+#[test]
+fn ts_content_response_constructed_roundtrip_pass () {
+	assert_response_roundtrip(ContentResponse::new_empty_response(Response::Ok));
+	assert_response_roundtrip(ContentResponse::new_service_text("foo bar"));
+	assert_response_roundtrip(ContentResponse::new_service_multi(
+		ContentServiceMulti::from_bytes(b"201 16 service/text foo 201 16 service/text bar 202").unwrap()
+	));
+	assert_response_roundtrip(ContentResponse::new_node_info(NodeInfoFmt::from_str("spring:foo,state:enabled").unwrap()));
+	assert_response_roundtrip(ContentResponse::new_network(vec![
+		NodeQuadFmt::from_str("foo,foo.tld,192.168.1.2,http").unwrap(),
+		NodeQuadFmt::from_str("bar,bar.tld,192.168.1.3,dvsp").unwrap(),
+	]));
+	assert_response_roundtrip(ContentResponse::new_resolution(
+		ContentResolution::from_bytes(b"node foo,bar,127.0.0.1,http;").unwrap()
+	));
+}
+
+#[test]
+fn ts_content_response_short_from_bytes_fail () {
+	assert!(ContentResponse::from_bytes(b"20").is_err());
+	assert!(ContentResponse::from_bytes(b"200 ").is_err());
+	assert!(ContentResponse::from_bytes("20\u{e0}".as_bytes()).is_err());
+	assert!(Message::from_bytes(b"20").is_err());
+}
+
+#[test]
+fn ts_message_resolution_to_bytes_pass () {
+	let o = Message::from_bytes(b"200 36 resolve node foo,bar,127.0.0.1,http;");
+	assert!(o.is_ok());
+	let m : Message = o.unwrap();
+	
+	let r = msg_response_resolution!(m.content);
+	assert_eq!(r.kind, ResolutionKind::Node);
+	
+	let st = String::from_utf8(m.to_bytes()).unwrap();
+	assert_eq!(st, "200 36 resolve node foo,bar,127.0.0.1,http;");
+}
+
+#[test]
+fn ts_content_service_multi_from_bytes_pass () {
+	let o = ContentServiceMulti::from_bytes(b"201 16 service/text foo 201 24 service/text bar 202 baz 202");
+	assert!(o.is_ok());
+	let cm : ContentServiceMulti = o.unwrap();
+	
+	assert_eq!(cm.parts.len(), 2);
+	assert_eq!(cm.parts[0].content, "foo");
+	assert_eq!(cm.parts[1].content, "bar 202 baz");
+}
+
+#[test]
+fn ts_content_service_multi_from_bytes_empty_pass () {
+	let o = ContentServiceMulti::from_bytes(b"202");
+	assert!(o.is_ok());
+	assert!(o.unwrap().parts.is_empty());
+}
+
+#[test]
+fn ts_content_service_multi_from_bytes_fail () {
+	// Missing terminator
+	assert!(ContentServiceMulti::from_bytes(b"201 16 service/text foo").is_err());
+	// Length overruns the content
+	assert!(ContentServiceMulti::from_bytes(b"201 99 service/text foo 202").is_err());
+	// Length overflows rather than panicking
+	assert_eq!(ContentServiceMulti::from_bytes(b"201 18446744073709551615 service/text foo 202"), Err(ParseFailure::InvalidContentFormat));
+	assert_eq!(ContentServiceMulti::from_bytes(b"201 18446744073709551600 service/text foo 202"), Err(ParseFailure::InvalidContentFormat));
+	// Parts must be chunks
+	assert!(ContentServiceMulti::from_bytes(b"200 16 service/text foo 202").is_err());
+	// Parts must be service text
+	assert!(ContentServiceMulti::from_bytes(b"201 11 network foo 202").is_err());
+	assert!(ContentServiceMulti::from_bytes(b"").is_err());
+}
+
+#[test]
+fn ts_message_content_response_service_multi_from_bytes_pass () {
+	let o = Message::from_bytes(b"200 67 service/multi 201 16 service/text foo 201 18 service/text a b c 202"); 
+	assert!(o.is_ok());
+	let m : Message = o.unwrap();
+	
+	assert_eq!(msg_response!(m.content).code, Response::Ok);
+	let cm = msg_response_servicemulti!(m.content);
+	assert_eq!(cm.parts.len(), 2);
+	assert_eq!(cm.parts[1].content, "a b c");
+}
+
+#[test]
+fn ts_message_content_response_service_multi_to_bytes_pass () {
+	let o = Message::from_bytes(b"200 67 service/multi 201 16 service/text foo 201 18 service/text a b c 202"); 
+	assert!(o.is_ok());
+	let m : Message = o.unwrap();
+	let st = String::from_utf8(m.to_bytes()).unwrap();
+	assert_eq!(st, "200 67 service/multi 201 16 service/text foo 201 18 service/text a b c 202");
+}
+
+#[test]
+fn ts_service_multi_builder_pass () {
+	let mut b = ServiceMultiBuilder::new();
+	assert!(b.is_empty());
+	
+	b.add(ContentServiceText::new("foo"));
+	assert!(b.add_response(&ContentResponse::new_service_text("bar\nbaz")));
+	assert!(!b.add_response(&ContentResponse::new_empty_response(Response::Ok)));
+	assert!(!b.add_response(&ContentResponse::new_empty_response(Response::NetworkError)));
+	
+	let inner = generate_response_service_multi(vec![ContentServiceText::new("one"), ContentServiceText::new("two")]);
+	assert!(b.add_response(msg_response!(inner.content)));
+	assert_eq!(b.len(), 4);
+	
+	let m = Message::new(CmdType::Response, MessageContent::Response(b.build_response()));
+	let bytes = m.to_bytes();
+	
+	let r = Message::from_bytes(&bytes).unwrap();
+	let cm = msg_response_servicemulti!(r.content);
+	assert_eq!(*cm, b.build());
+	assert_eq!(cm.parts[1].content, "bar\nbaz");
+	assert_eq!(cm.parts[3].content, "two");
+}
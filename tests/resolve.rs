@@ -0,0 +1,115 @@
+extern crate spring_dvs;
+
+mod common;
+
+use spring_dvs::protocol::*;
+use spring_dvs::spaces::{Netspace, NetspaceFailure, MemoryNetspace, Node};
+use spring_dvs::resolve::Resolver;
+use spring_dvs::uri::Uri;
+
+use common::new_node;
+
+fn new_netspace() -> MemoryNetspace {
+	let nio = MemoryNetspace::new();
+	nio.gsn_node_register(&new_node("cci", "192.168.1.2")).unwrap();
+	nio.gtn_geosub_register_node(&new_node("cci", "192.168.1.2"), "esusx").unwrap();
+	nio.gtn_geosub_register_node(&new_node("root", "192.168.2.1"), "wsusx").unwrap();
+	nio.gtn_geosub_register_node(&new_node("alt", "192.168.2.2"), "wsusx").unwrap();
+	nio
+}
+
+fn springnames(v: &[Node]) -> Vec<&str> {
+	let mut names : Vec<&str> = v.iter().map(|n| n.springname()).collect();
+	names.sort();
+	names
+}
+
+#[test]
+fn ts_resolve_local_pass() {
+	let nio = new_netspace();
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://cci.esusx.uk").unwrap()).unwrap();
+	assert_eq!(r.kind, ResolutionKind::Node);
+	assert_eq!(springnames(&r.nodes), vec!["cci"]);
+	
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://cci").unwrap()).unwrap();
+	assert_eq!(r.kind, ResolutionKind::Node);
+	assert_eq!(springnames(&r.nodes), vec!["cci"]);
+}
+
+#[test]
+fn ts_resolve_route_case_pass() {
+	let nio = new_netspace();
+	let resolver = Resolver::new(&nio, "esusx");
+	
+	for uri in ["spring://Cci.esusx.uk", "spring://cci.esusx.UK", "spring://CCI"].iter() {
+		let r = resolver.resolve(&Uri::new(uri).unwrap()).unwrap();
+		assert_eq!(r.kind, ResolutionKind::Node, "{}", uri);
+		assert_eq!(springnames(&r.nodes), vec!["cci"]);
+	}
+}
+
+#[test]
+fn ts_resolve_local_fail() {
+	let nio = new_netspace();
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://foo.esusx.uk").unwrap());
+	assert!(r.is_err());
+}
+
+#[test]
+fn ts_resolve_remote_root_pass() {
+	let nio = new_netspace();
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://alt.wsusx.uk").unwrap()).unwrap();
+	assert_eq!(r.kind, ResolutionKind::Node);
+	assert_eq!(springnames(&r.nodes), vec!["alt"]);
+}
+
+#[test]
+fn ts_resolve_remote_referral_pass() {
+	let nio = new_netspace();
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://foo.wsusx.uk").unwrap()).unwrap();
+	assert_eq!(r.kind, ResolutionKind::Referral);
+	assert_eq!(springnames(&r.nodes), vec!["alt", "root"]);
+	
+	// A node with the same name in the local GSN is not the target
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://cci.wsusx.uk").unwrap()).unwrap();
+	assert_eq!(r.kind, ResolutionKind::Referral);
+}
+
+#[test]
+fn ts_resolve_geosub_referral_pass() {
+	let nio = new_netspace();
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://wsusx.uk").unwrap()).unwrap();
+	assert_eq!(r.kind, ResolutionKind::Referral);
+	assert_eq!(springnames(&r.nodes), vec!["alt", "root"]);
+}
+
+#[test]
+fn ts_resolve_remote_fail() {
+	let nio = new_netspace();
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://foo.ksusx.uk").unwrap());
+	assert!(r.is_err());
+	
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://ksusx.uk").unwrap());
+	assert!(r.is_err());
+}
+
+#[test]
+fn ts_resolve_too_many_labels_fail() {
+	let nio = new_netspace();
+	
+	// Not resolved as `cci.esusx`
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://cci.esusx.more.uk").unwrap());
+	assert_eq!(r.unwrap_err(), NetspaceFailure::NodeNotFound);
+	
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://cci.esusx.more").unwrap());
+	assert_eq!(r.unwrap_err(), NetspaceFailure::NodeNotFound);
+}
+
+#[test]
+fn ts_resolve_to_content_pass() {
+	let nio = new_netspace();
+	let r = Resolver::new(&nio, "esusx").resolve(&Uri::new("spring://wsusx.uk").unwrap()).unwrap();
+	let c = r.to_content();
+	assert_eq!(c.kind, ResolutionKind::Referral);
+	assert_eq!(c.network.len(), 2);
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "serde")]
+extern crate serde_json;
+extern crate spring_dvs;
+
+use serde_json::json;
+
+use spring_dvs::enums::Failure;
+use spring_dvs::protocol::*;
+use spring_dvs::spaces::Node;
+use spring_dvs::uri::Uri;
+
+fn roundtrip(msg: &[u8]) -> serde_json::Value {
+	let m = Message::from_bytes(msg).unwrap();
+	let value = serde_json::to_value(&m).unwrap();
+	
+	let back : Message = serde_json::from_value(value.clone()).unwrap();
+	assert_eq!(back.cmd, m.cmd);
+	assert_eq!(back.content, m.content);
+	assert_eq!(back.to_bytes(), m.to_bytes());
+	value
+}
+
+#[test]
+fn ts_serde_enums_display_pass() {
+	assert_eq!(serde_json::to_value(NodeRole::Org).unwrap(), json!("org"));
+	assert_eq!(serde_json::to_value(NodeRole::Undefined).unwrap(), json!("undefined"));
+	assert_eq!(serde_json::to_value(NodeService::Http).unwrap(), json!("http"));
+	assert_eq!(serde_json::to_value(NodeState::Unresponsive).unwrap(), json!("unresponsive"));
+	assert_eq!(serde_json::to_value(Response::NetspaceError).unwrap(), json!("101"));
+	assert_eq!(serde_json::to_value(Failure::OutOfBounds).unwrap(), json!("OutOfBounds"));
+	assert_eq!(serde_json::to_value(CmdType::Register).unwrap(), json!("register"));
+	
+	for r in [NodeRole::Undefined, NodeRole::Hub, NodeRole::Org, NodeRole::Hybrid].iter() {
+		let v = serde_json::to_value(r).unwrap();
+		assert_eq!(v, json!(r.to_string()));
+		assert_eq!(serde_json::from_value::<NodeRole>(v).unwrap(), *r);
+	}
+	
+	for s in [NodeState::Disabled, NodeState::Enabled, NodeState::Unresponsive, NodeState::Unspecified].iter() {
+		let v = serde_json::to_value(s).unwrap();
+		assert_eq!(serde_json::from_value::<NodeState>(v).unwrap(), *s);
+	}
+	
+	assert_eq!(serde_json::from_value::<NodeService>(json!("undefined")).unwrap(), NodeService::Undefined);
+	assert_eq!(serde_json::from_value::<Response>(json!("202")).unwrap(), Response::Eot);
+}
+
+#[test]
+fn ts_serde_enums_fail() {
+	assert!(serde_json::from_value::<NodeRole>(json!("Org")).is_err());
+	assert!(serde_json::from_value::<NodeRole>(json!(2)).is_err());
+	assert!(serde_json::from_value::<NodeState>(json!("asleep")).is_err());
+	assert!(serde_json::from_value::<Response>(json!("200 ")).is_err());
+	assert!(serde_json::from_value::<Response>(json!(200)).is_err());
+}
+
+#[test]
+fn ts_serde_message_shape_pass() {
+	assert_eq!(roundtrip(b"update foo state enabled"), json!({
+		"cmd": "update",
+		"content": {
+			"type": "update",
+			"value": {
+				"spring": "foo",
+				"property": { "name": "state", "value": "enabled" }
+			}
+		}
+	}));
+	
+	assert_eq!(roundtrip(b"info network"), json!({
+		"cmd": "info",
+		"content": { "type": "info", "value": { "info": { "type": "network" } } }
+	}));
+	
+	assert_eq!(roundtrip(b"info node foo all"), json!({
+		"cmd": "info",
+		"content": { "type": "info", "value": { "info": {
+			"type": "node",
+			"value": { "spring": "foo", "property": { "name": "all" } }
+		} } }
+	}));
+	
+	assert_eq!(roundtrip(b"resolve spring://cci.esusx.uk/res"), json!({
+		"cmd": "resolve",
+		"content": { "type": "resolve", "value": { "uri": "spring://cci.esusx.uk/res" } }
+	}));
+	
+	assert_eq!(roundtrip(b"register foo,foo.tld;org;http;abcdef\nKEY"), json!({
+		"cmd": "register",
+		"content": { "type": "registration", "value": {
+			"ndouble": { "spring": "foo", "host": "foo.tld" },
+			"role": "org",
+			"service": "http",
+			"key": "KEY",
+			"token": "abcdef"
+		} }
+	}));
+}
+
+#[test]
+fn ts_serde_response_shape_pass() {
+	assert_eq!(roundtrip(b"200"), json!({
+		"cmd": "response",
+		"content": { "type": "response", "value": { "code": "200", "len": 0, "content": { "type": "empty" } } }
+	}));
+	
+	assert_eq!(roundtrip(b"200 36 network foo,foo.tld,192.168.1.2,http;"), json!({
+		"cmd": "response",
+		"content": { "type": "response", "value": {
+			"code": "200",
+			"len": 36,
+			"content": { "type": "network", "value": { "network": [
+				{ "spring": "foo", "host": "foo.tld", "address": "192.168.1.2", "service": "http" }
+			] } }
+		} }
+	}));
+	
+	let v = roundtrip(b"200 37 node spring:foo,state:unresponsive");
+	assert_eq!(v["content"]["value"]["content"], json!({
+		"type": "node_info",
+		"value": { "info": {
+			"spring": "foo", "host": "", "address": null,
+			"service": "undefined", "state": "unresponsive", "role": "undefined"
+		} }
+	}));
+	
+	let v = roundtrip(b"200 58 service/multi 201 16 service/text one 201 16 service/text two 202");
+	assert_eq!(v["content"]["value"]["content"], json!({
+		"type": "service_multi",
+		"value": { "parts": [ { "content": "one" }, { "content": "two" } ] }
+	}));
+	
+	let v = roundtrip(b"200 36 resolve referral foo,foo.tld,192.168.1.2,http;");
+	assert_eq!(v["content"]["value"]["content"]["type"], json!("resolution"));
+	assert_eq!(v["content"]["value"]["content"]["value"]["kind"], json!("referral"));
+}
+
+#[test]
+fn ts_serde_uri_pass() {
+	let uri = Uri::new("spring://cci.esusx.uk/res?query=test").unwrap();
+	let v = serde_json::to_value(&uri).unwrap();
+	assert_eq!(v, json!("spring://cci.esusx.uk/res?query=test"));
+	assert_eq!(serde_json::from_value::<Uri>(v).unwrap(), uri);
+	
+	assert!(serde_json::from_value::<Uri>(json!("http://cci.esusx.uk")).is_err());
+}
+
+#[test]
+fn ts_serde_node_pass() {
+	let node = Node::new("foo", "https://foo.tld:8443/path", "192.168.1.2".parse().ok(), NodeService::Http, NodeState::Enabled, NodeRole::Hub, "KEY");
+	
+	let v = serde_json::to_value(&node).unwrap();
+	assert_eq!(v, json!({
+		"springname": "foo",
+		"hostname": "foo.tld",
+		"hostpath": "path",
+		"address": "192.168.1.2",
+		"port": 8443,
+		"tls": true,
+		"service": "http",
+		"state": "enabled",
+		"role": "hub",
+		"key": "KEY"
+	}));
+	
+	let back : Node = serde_json::from_value(v).unwrap();
+	assert_eq!(back.hostfield(), node.hostfield());
+	assert_eq!(back.state(), NodeState::Enabled);
+}
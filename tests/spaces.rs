@@ -0,0 +1,60 @@
+extern crate spring_dvs;
+
+mod common;
+
+use spring_dvs::spaces::*;
+use spring_dvs::spaces::conformance;
+
+use common::new_node;
+
+#[test]
+fn ts_spaces_memory_conformance_pass() {
+	conformance::run(|| MemoryNetspace::new());
+}
+
+#[test]
+fn ts_spaces_memory_register_pass() {
+	let nio = MemoryNetspace::new();
+	assert!(nio.gsn_node_register(&new_node("foo", "192.168.1.2")).is_ok());
+	
+	let n = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(n.address(), "192.168.1.2".parse().ok());
+}
+
+#[test]
+fn ts_spaces_memory_register_duplicate_fail() {
+	let nio = MemoryNetspace::new();
+	assert!(nio.gsn_node_register(&new_node("foo", "192.168.1.2")).is_ok());
+	assert_eq!(nio.gsn_node_register(&new_node("foo", "192.168.1.3")), Err(NetspaceFailure::DuplicateNode));
+}
+
+#[test]
+fn ts_spaces_memory_update_missing_fail() {
+	let nio = MemoryNetspace::new();
+	assert_eq!(nio.gsn_node_update_state(&new_node("foo", "192.168.1.2")), Err(NetspaceFailure::NodeNotFound));
+}
+
+#[test]
+fn ts_spaces_memory_update_copy_pass() {
+	let nio = MemoryNetspace::new();
+	let mut n = new_node("foo", "192.168.1.2");
+	assert!(nio.gsn_node_register(&n).is_ok());
+	
+	// Changes to the caller's copy are not visible until updated
+	n.update_state(NodeState::Disabled);
+	assert_eq!(nio.gsn_node_by_springname("foo").unwrap().state(), NodeState::Enabled);
+	
+	assert!(nio.gsn_node_update_state(&n).is_ok());
+	assert_eq!(nio.gsn_node_by_springname("foo").unwrap().state(), NodeState::Disabled);
+}
+
+#[test]
+fn ts_spaces_memory_geosub_dropped_pass() {
+	let nio = MemoryNetspace::new();
+	let n = new_node("foo", "192.168.1.2");
+	assert!(nio.gtn_geosub_register_node(&n, "esusx").is_ok());
+	assert_eq!(nio.gtn_geosubs(), vec!["esusx"]);
+	
+	assert!(nio.gtn_geosub_unregister_node(&n, "esusx").is_ok());
+	assert!(nio.gtn_geosubs().is_empty());
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "sqlite")]
+extern crate spring_dvs;
+extern crate tempfile;
+extern crate rusqlite;
+
+mod common;
+
+use spring_dvs::spaces::*;
+use spring_dvs::spaces::conformance;
+
+use common::new_node;
+
+#[test]
+fn ts_spaces_sqlite_conformance_pass() {
+	conformance::run(|| SqliteNetspace::open_in_memory().unwrap());
+}
+
+#[test]
+fn ts_spaces_sqlite_conformance_file_pass() {
+	let dir = tempfile::tempdir().unwrap();
+	let count = std::cell::Cell::new(0);
+	conformance::run(|| {
+		count.set(count.get() + 1);
+		SqliteNetspace::open(dir.path().join(format!("netspace{}.db", count.get()))).unwrap()
+	});
+}
+
+#[test]
+fn ts_spaces_sqlite_schema_version_pass() {
+	let nio = SqliteNetspace::open_in_memory().unwrap();
+	assert_eq!(nio.schema_version(), Ok(SCHEMA_VERSION));
+}
+
+#[test]
+fn ts_spaces_sqlite_persist_pass() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("netspace.db");
+	
+	{
+		let nio = SqliteNetspace::open(&path).unwrap();
+		assert!(nio.gsn_node_register(&new_node("foo", "192.168.1.2")).is_ok());
+		assert!(nio.gtn_geosub_register_node(&new_node("bar", "192.168.1.3"), "esusx").is_ok());
+		nio.gsn_add_token("abc", "foo");
+	}
+	
+	let nio = SqliteNetspace::open(&path).unwrap();
+	assert_eq!(nio.schema_version(), Ok(SCHEMA_VERSION));
+	
+	let n = nio.gsn_node_by_springname("foo").unwrap();
+	assert_eq!(n.hostname(), "foo.tld");
+	assert_eq!(n.hostpath(), "spring");
+	assert_eq!(n.address(), "192.168.1.2".parse().ok());
+	assert_eq!(n.key(), "KEY");
+	
+	assert_eq!(nio.gtn_geosub_root_nodes("esusx").len(), 1);
+	assert!(nio.gsn_check_token("abc"));
+}
+
+#[test]
+fn ts_spaces_sqlite_null_address_pass() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("netspace.db");
+	
+	let nio = SqliteNetspace::open(&path).unwrap();
+	let mut n = new_node("foo", "");
+	assert!(nio.gsn_node_register(&n).is_ok());
+	assert_eq!(nio.gsn_node_by_springname("foo").unwrap().address(), None);
+	
+	let conn = rusqlite::Connection::open(&path).unwrap();
+	let null : bool = conn.query_row("SELECT address IS NULL FROM geosub_netspace", [], |r| r.get(0)).unwrap();
+	assert!(null);
+	
+	n.update_address("192.168.1.2".parse().ok());
+	assert!(nio.gsn_node_update_address(&n).is_ok());
+	n.update_address(None);
+	assert!(nio.gsn_node_update_address(&n).is_ok());
+	let null : bool = conn.query_row("SELECT address IS NULL FROM geosub_netspace", [], |r| r.get(0)).unwrap();
+	assert!(null);
+}
+
+#[test]
+fn ts_spaces_sqlite_register_duplicate_fail() {
+	let nio = SqliteNetspace::open_in_memory().unwrap();
+	assert!(nio.gsn_node_register(&new_node("foo", "192.168.1.2")).is_ok());
+	assert_eq!(nio.gsn_node_register(&new_node("foo", "192.168.1.3")), Err(NetspaceFailure::DuplicateNode));
+}
+
+#[test]
+fn ts_spaces_sqlite_open_fail() {
+	let dir = tempfile::tempdir().unwrap();
+	let r = SqliteNetspace::open(dir.path().join("missing").join("netspace.db"));
+	assert_eq!(r.err(), Some(NetspaceFailure::DatabaseError));
+}
+
+#[test]
+fn ts_spaces_sqlite_newer_schema_fail() {
+	// A database written by a later version of the crate
+	let conn = rusqlite::Connection::open_in_memory().unwrap();
+	conn.execute_batch(&format!("PRAGMA user_version = {};", SCHEMA_VERSION + 1)).unwrap();
+	
+	assert_eq!(SqliteNetspace::from_connection(conn).err(), Some(NetspaceFailure::DatabaseError));
+}
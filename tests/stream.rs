@@ -0,0 +1,153 @@
+extern crate spring_dvs;
+
+use std::io;
+use std::io::Read;
+
+use spring_dvs::enums::ParseFailure;
+use spring_dvs::protocol::*;
+use spring_dvs::stream::*;
+
+/// Hands out its bytes a few at a time
+struct Trickle<'a> {
+	bytes: &'a [u8],
+	step: usize,
+}
+
+impl<'a> Read for Trickle<'a> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = self.step.min(buf.len()).min(self.bytes.len());
+		buf[..n].copy_from_slice(&self.bytes[..n]);
+		self.bytes = &self.bytes[n..];
+		Ok(n)
+	}
+}
+
+fn messages() -> Vec<Message> {
+	let text : Vec<&[u8]> = vec![
+		b"register foo,foo.tld;org;http;abcdef\nPUBLIC KEY\nAbCdEf",
+		b"info node foo state",
+		b"update foo state enabled",
+		b"resolve spring://cci.esusx.uk/res?query=test",
+		b"200",
+	];
+	
+	text.into_iter().map(|t| Message::from_bytes(t).unwrap()).collect()
+}
+
+fn write_all(msgs: &[Message]) -> Vec<u8> {
+	let mut writer = MessageWriter::new(Vec::new());
+	for m in msgs {
+		writer.write_message(m).unwrap();
+	}
+	writer.flush().unwrap();
+	writer.into_inner()
+}
+
+#[test]
+fn ts_stream_encode_frame_pass() {
+	assert_eq!(encode_frame(b"info network"), b"12:info network,");
+	assert_eq!(encode_frame(b""), b"0:,");
+	assert_eq!(encode_frame(b"a\nb"), b"3:a\nb,");
+}
+
+#[test]
+fn ts_stream_decoder_pass() {
+	let mut d = FrameDecoder::new();
+	assert!(d.is_empty());
+	assert_eq!(d.next_frame().unwrap(), None);
+	
+	d.feed(b"12:info net");
+	assert_eq!(d.next_frame().unwrap(), None);
+	d.feed(b"work,3:2");
+	assert_eq!(d.next_frame().unwrap(), Some(b"info network".to_vec()));
+	assert_eq!(d.next_frame().unwrap(), None);
+	assert!(!d.is_empty());
+	
+	d.feed(b"00,0:,");
+	assert_eq!(d.next_frame().unwrap(), Some(b"200".to_vec()));
+	assert_eq!(d.next_frame().unwrap(), Some(Vec::new()));
+	assert!(d.is_empty());
+}
+
+#[test]
+fn ts_stream_decoder_fail() {
+	let mut d = FrameDecoder::new();
+	d.feed(b"12:info network;");
+	assert!(matches!(d.next_frame(), Err(StreamError::MalformedFrame)));
+	
+	let mut d = FrameDecoder::new();
+	d.feed(b"info network");
+	assert!(matches!(d.next_frame(), Err(StreamError::MalformedFrame)));
+	
+	let mut d = FrameDecoder::new();
+	d.feed(b":info,");
+	assert!(matches!(d.next_frame(), Err(StreamError::MalformedFrame)));
+	
+	let mut d = FrameDecoder::new();
+	d.feed(b"123456789012345678901234");
+	assert!(matches!(d.next_frame(), Err(StreamError::MalformedFrame)));
+	
+	let mut d = FrameDecoder::new();
+	d.feed(b"99999999999999999999:");
+	assert!(matches!(d.next_frame(), Err(StreamError::FrameTooLarge)));
+	
+	let mut d = FrameDecoder::new().with_max_frame(8);
+	d.feed(b"12:");
+	assert!(matches!(d.next_frame(), Err(StreamError::FrameTooLarge)));
+}
+
+#[test]
+fn ts_stream_roundtrip_pass() {
+	let msgs = messages();
+	let bytes = write_all(&msgs);
+	
+	let mut reader = MessageReader::new(&bytes[..]);
+	for m in &msgs {
+		assert_eq!(reader.read_message().unwrap().unwrap().to_bytes(), m.to_bytes());
+	}
+	
+	assert!(reader.read_message().unwrap().is_none());
+}
+
+#[test]
+fn ts_stream_trickle_pass() {
+	let msgs = messages();
+	let bytes = write_all(&msgs);
+	
+	for step in 1..8 {
+		let reader = MessageReader::new(Trickle { bytes: &bytes, step });
+		let read : Vec<Vec<u8>> = reader.map(|m| m.unwrap().to_bytes()).collect();
+		assert_eq!(read, msgs.iter().map(Message::to_bytes).collect::<Vec<_>>());
+	}
+}
+
+#[test]
+fn ts_stream_reader_parse_fail() {
+	let bytes = b"4:void,12:info network,";
+	let mut reader = MessageReader::new(&bytes[..]);
+	
+	assert!(matches!(reader.read_message(), Err(StreamError::Parse(ParseFailure::InvalidCommand))));
+	assert_eq!(reader.read_message().unwrap().unwrap().cmd, CmdType::Info);
+	assert!(reader.read_message().unwrap().is_none());
+	
+	// The iteration carries on past a message that does not parse
+	let read : Vec<bool> = MessageReader::new(&bytes[..]).map(|r| r.is_ok()).collect();
+	assert_eq!(read, vec![false, true]);
+}
+
+#[test]
+fn ts_stream_reader_closed_fail() {
+	let mut reader = MessageReader::new(&b"12:info network,3:20"[..]);
+	assert!(reader.read_message().unwrap().is_some());
+	assert!(matches!(reader.read_message(), Err(StreamError::Closed)));
+}
+
+#[test]
+fn ts_stream_reader_malformed_fail() {
+	let bytes = b"12:info network,info network";
+	let read : Vec<bool> = MessageReader::new(&bytes[..]).map(|r| r.is_ok()).collect();
+	assert_eq!(read, vec![true, false]);
+	
+	let mut reader = MessageReader::new(&b"12:info network,"[..]).with_max_frame(4);
+	assert!(matches!(reader.read_message(), Err(StreamError::FrameTooLarge)));
+}
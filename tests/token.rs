@@ -0,0 +1,135 @@
+extern crate spring_dvs;
+
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use spring_dvs::protocol::*;
+use spring_dvs::spaces::{Netspace, MemoryNetspace};
+use spring_dvs::token::*;
+
+fn registration(spring: &str, token: &str) -> ContentRegistration {
+	let s = format!("{},{}.tld;org;http;{}\nKEY", spring, spring, token);
+	ContentRegistration::from_bytes(s.as_bytes()).unwrap()
+}
+
+fn at(secs: u64) -> SystemTime {
+	UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+#[test]
+fn ts_token_generate_parse_pass() {
+	let t = Token::generate(at(1000), Duration::from_secs(60)).unwrap();
+	assert_eq!(t.expires(), at(1060));
+	assert!(t.value().ends_with(".1060"));
+	assert_eq!(t.value().len(), 37);
+	
+	let p = Token::parse(t.value()).unwrap();
+	assert_eq!(p, t);
+	assert!(!p.is_expired(at(1059)));
+	assert!(p.is_expired(at(1060)));
+	
+	let u = Token::generate(at(1000), Duration::from_secs(60)).unwrap();
+	assert!(u != t);
+}
+
+#[test]
+fn ts_token_parse_fail() {
+	assert_eq!(Token::parse("abcdef"), Err(TokenFailure::Malformed));
+	assert_eq!(Token::parse("0123456789abcdef0123456789abcdef."), Err(TokenFailure::Malformed));
+	assert_eq!(Token::parse("0123456789abcdef0123456789abcdef.12a"), Err(TokenFailure::Malformed));
+	assert_eq!(Token::parse("0123456789abcdef0123456789abcdeg.12"), Err(TokenFailure::Malformed));
+	assert_eq!(Token::parse("0123456789abcdef.12"), Err(TokenFailure::Malformed));
+	assert_eq!(Token::parse("0123456789abcdef0123456789abcdef.+12"), Err(TokenFailure::Malformed));
+	assert_eq!(Token::parse("0123456789abcdef0123456789abcdef.99999999999999999999999"), Err(TokenFailure::Malformed));
+	assert_eq!(Token::parse("0123456789abcdef0123456789abcdef.18446744073709551615"), Err(TokenFailure::Malformed));
+}
+
+#[test]
+fn ts_token_issue_consume_pass() {
+	let nio = MemoryNetspace::new();
+	let tokens = TokenIssuer::new(&nio).with_lifetime(Duration::from_secs(60));
+	
+	let t = tokens.issue_at("foo", at(1000)).unwrap();
+	assert_eq!(nio.gsn_token_by_springname("foo"), vec![(String::from(t.value()), String::from("foo"))]);
+	
+	let reg = registration("foo", t.value());
+	assert_eq!(tokens.validate_at(&reg, at(1030)), Ok(t.clone()));
+	assert_eq!(tokens.consume_at(&reg, at(1030)), Ok(t));
+	assert!(nio.gsn_tokens().is_empty());
+}
+
+#[test]
+fn ts_token_consume_single_use_fail() {
+	let nio = MemoryNetspace::new();
+	let tokens = TokenIssuer::new(&nio);
+	
+	let t = tokens.issue("foo").unwrap();
+	let reg = registration("foo", t.value());
+	assert!(tokens.consume(&reg).is_ok());
+	assert_eq!(tokens.consume(&reg), Err(TokenFailure::Unknown));
+}
+
+#[test]
+fn ts_token_validate_fail() {
+	let nio = MemoryNetspace::new();
+	let tokens = TokenIssuer::new(&nio).with_lifetime(Duration::from_secs(60));
+	let t = tokens.issue_at("foo", at(1000)).unwrap();
+	
+	assert_eq!(tokens.validate_at(&registration("foo", "abcdef"), at(1000)), Err(TokenFailure::Malformed));
+	assert_eq!(tokens.validate_at(&registration("foo", "0123456789abcdef0123456789abcdef.1060"), at(1000)), Err(TokenFailure::Unknown));
+	assert_eq!(tokens.validate_at(&registration("bar", t.value()), at(1000)), Err(TokenFailure::WrongSpringname));
+	assert_eq!(tokens.validate_at(&registration("foo", t.value()), at(1060)), Err(TokenFailure::Expired));
+	
+	// Failed checks do not use up the token
+	assert_eq!(tokens.consume_at(&registration("foo", t.value()), at(1060)), Err(TokenFailure::Expired));
+	assert!(tokens.consume_at(&registration("foo", t.value()), at(1059)).is_ok());
+}
+
+#[test]
+fn ts_token_reissue_revokes_pass() {
+	let nio = MemoryNetspace::new();
+	let tokens = TokenIssuer::new(&nio);
+	
+	let first = tokens.issue("foo").unwrap();
+	let second = tokens.issue("foo").unwrap();
+	tokens.issue("bar").unwrap();
+	
+	assert_eq!(tokens.validate(&registration("foo", first.value())), Err(TokenFailure::Unknown));
+	assert!(tokens.validate(&registration("foo", second.value())).is_ok());
+	assert_eq!(nio.gsn_tokens().len(), 2);
+}
+
+#[test]
+fn ts_token_purge_expired_pass() {
+	let nio = MemoryNetspace::new();
+	let tokens = TokenIssuer::new(&nio).with_lifetime(Duration::from_secs(60));
+	
+	tokens.issue_at("foo", at(1000)).unwrap();
+	let live = tokens.issue_at("bar", at(2000)).unwrap();
+	nio.gsn_add_token("abcdef", "baz");
+	
+	assert_eq!(tokens.purge_expired_at(at(1500)), 1);
+	
+	// A token added by hand has no expiry and is kept
+	let kept = nio.gsn_tokens();
+	assert_eq!(kept.len(), 2);
+	assert!(kept.contains(&(String::from("abcdef"), String::from("baz"))));
+	assert!(kept.contains(&(String::from(live.value()), String::from("bar"))));
+	assert_eq!(tokens.purge_expired_at(at(1500)), 0);
+}
+
+#[test]
+fn ts_token_consume_concurrent_fail() {
+	let nio = MemoryNetspace::new();
+	let tokens = TokenIssuer::new(&nio);
+	let t = tokens.issue("foo").unwrap();
+	let reg = registration("foo", t.value());
+	
+	// Only one of many simultaneous registrations gets the token
+	let used = thread::scope(|s| {
+		let workers : Vec<_> = (0..8).map(|_| s.spawn(|| tokens.consume(&reg).is_ok())).collect();
+		workers.into_iter().map(|w| w.join().unwrap()).filter(|ok| *ok).count()
+	});
+	
+	assert_eq!(used, 1);
+}
@@ -187,12 +187,13 @@ fn ts_model_uri_mut_route_pass() {
 }
 
 #[test]
-fn ts_uri_fail_no_uri() {
-	let s = "spring://";
-	let r = Uri::new(s);
-	
-	assert!(r.is_err());
+fn ts_uri_no_route_pass() {
+	let mut uri = Uri::new("spring://cci").unwrap();
+	uri.route_mut().clear();
 	
+	assert_eq!(uri.to_string(), "spring://");
+	assert_eq!(Uri::new("spring://").unwrap(), uri);
+	assert!(uri.route().is_empty());
 }
 
 
@@ -252,4 +253,208 @@ fn ts_uri_query_param_fail() {
 	let uri = r.unwrap();
 	
 	assert!(uri.query_param("void").is_none())
-}
\ No newline at end of file
+}
+
+#[test]
+fn ts_model_uri_new_fail_bad_label() {
+	assert_eq!(Uri::new("spring://cci.es_usx.uk"), Err(Failure::InvalidFormat));
+	assert_eq!(Uri::new("spring://-cci.esusx.uk"), Err(Failure::InvalidFormat));
+	assert_eq!(Uri::new("spring://cci.esusx.uk:80/res"), Err(Failure::InvalidFormat));
+}
+
+#[test]
+fn ts_uri_parse_fragment_pass() {
+	let uri = Uri::parse("spring://cci.esusx.uk/res?query=test#top").unwrap();
+	
+	assert_eq!(uri.res()[0], "res");
+	assert_eq!(uri.query(), "query=test");
+	assert_eq!(uri.fragment(), "top");
+	assert_eq!(uri.to_string(), "spring://cci.esusx.uk/res?query=test#top");
+	
+	let uri = Uri::parse("spring://cci.esusx.uk#top?not=query").unwrap();
+	assert_eq!(uri.query(), "");
+	assert_eq!(uri.fragment(), "top?not=query");
+}
+
+#[test]
+fn ts_uri_parse_percent_encoded_pass() {
+	let uri = Uri::parse("spring://cci.esusx.uk/open%20days/a%2Fb/%E2%9C%93#sec%20one").unwrap();
+	
+	assert_eq!(uri.res(), &vec!["open days", "a/b", "✓"]);
+	assert_eq!(uri.fragment(), "sec one");
+	assert_eq!(uri.to_string(), "spring://cci.esusx.uk/open%20days/a%2Fb/%E2%9C%93#sec%20one");
+}
+
+#[test]
+fn ts_uri_parse_fail() {
+	assert_eq!(Uri::parse("cci.esusx.uk/res"), Err(UriError::Scheme));
+	assert_eq!(Uri::parse("http://cci.esusx.uk"), Err(UriError::Scheme));
+	assert_eq!(Uri::parse("spring://cci.es_usx.uk"), Err(UriError::Route));
+	assert_eq!(Uri::parse("spring://cci.esusx.uk/res%2"), Err(UriError::Resource));
+	assert_eq!(Uri::parse("spring://cci.esusx.uk/open\tdays"), Err(UriError::Resource));
+	assert_eq!(Uri::parse("spring://cci.esusx.uk/res?a=%zz"), Err(UriError::Query));
+	assert_eq!(Uri::parse("spring://cci.esusx.uk/res#%FF"), Err(UriError::Fragment));
+	
+	assert_eq!(Uri::new("spring://cci.esusx.uk/res%2"), Err(Failure::InvalidFormat));
+}
+
+#[test]
+fn ts_uri_query_map_encoded_pass() {
+	let uri = Uri::parse("spring://cci.esusx.uk/res?a%26b=c%3Dd&&e&f=%20").unwrap();
+	let qm = uri.query_map().unwrap();
+	
+	assert_eq!(qm.len(), 3);
+	assert_eq!(qm["a&b"], "c=d");
+	assert_eq!(qm["e"], "");
+	assert_eq!(qm["f"], " ");
+}
+
+#[test]
+fn ts_uri_display_empty_route_pass() {
+	let mut uri = Uri::parse("spring://cci/res").unwrap();
+	uri.route_mut().clear();
+	
+	assert_eq!(uri.to_string(), "spring:///res");
+	assert_eq!(Uri::new("spring:///res").unwrap(), uri);
+	assert_eq!(Uri::parse("spring://./res").unwrap(), uri);
+}
+
+#[test]
+fn ts_uri_new_spaces_pass() {
+	let uri = Uri::new("spring://cci.esusx.uk/open days?from=a b#the top").unwrap();
+	
+	assert_eq!(uri.res(), &vec!["open days".to_string()]);
+	assert_eq!(uri.query_param("from").unwrap(), "a b");
+	assert_eq!(uri.fragment(), "the top");
+	assert_eq!(Uri::parse(&uri.to_string()).unwrap(), uri);
+}
+
+#[test]
+fn ts_uri_builder_pass() {
+	let uri = UriBuilder::new()
+		.with_route("cci.esusx")
+		.with_label("uk")
+		.with_resource("open days")
+		.with_resource("")
+		.with_resource("a/b")
+		.with_query("from", "a&b=c")
+		.with_query("to", "")
+		.with_fragment("top")
+		.build()
+		.unwrap();
+	
+	assert_eq!(uri.route(), &vec!["cci", "esusx", "uk"]);
+	assert_eq!(uri.gtn(), "uk");
+	assert_eq!(uri.res(), &vec!["open days", "a/b"]);
+	assert_eq!(uri.query_param("from").unwrap(), "a&b=c");
+	assert_eq!(uri.to_string(), "spring://cci.esusx.uk/open%20days/a%2Fb?from=a%26b%3Dc&to=#top");
+}
+
+#[test]
+fn ts_uri_builder_fail() {
+	assert_eq!(UriBuilder::new().build(), Err(UriError::Route));
+	assert_eq!(UriBuilder::new().with_label("").build(), Err(UriError::Route));
+	assert_eq!(UriBuilder::new().with_label("cci.esusx").build(), Err(UriError::Route));
+	assert_eq!(UriBuilder::new().with_route("cci.-esusx").build(), Err(UriError::Route));
+}
+
+#[test]
+fn ts_uri_normalise_pass() {
+	let uri = Uri::parse("SPRING://CCI.Esusx.UK/a/./b/../c?x=%7e&&y=a%2fb&%5A#top").unwrap();
+	let n = uri.normalise();
+	
+	assert_eq!(n.gtn(), "uk");
+	assert_eq!(n.to_string(), "spring://cci.esusx.uk/a/c?x=~&y=a/b&Z#top");
+	assert_eq!(normalise("spring://cci.esusx.uk/../res?").unwrap(), "spring://cci.esusx.uk/res");
+	assert_eq!(normalise("spring://cci/res%"), Err(UriError::Resource));
+}
+
+#[test]
+fn ts_uri_percent_encoding_pass() {
+	assert_eq!(percent_encode("a b/c~✓"), "a%20b%2Fc~%E2%9C%93");
+	assert_eq!(percent_decode("a%20b%2fc~%E2%9C%93").unwrap(), "a b/c~✓");
+	
+	assert!(percent_decode("%").is_none());
+	assert!(percent_decode("%4").is_none());
+	assert!(percent_decode("%+4").is_none());
+	assert!(percent_decode("%C3").is_none());
+	assert!(percent_decode("a\tb").is_none());
+	assert_eq!(percent_decode("a b").unwrap(), "a b");
+}
+
+fn sample_uris() -> Vec<Uri> {
+	let routes = ["cci", "cci.esusx.uk", "a-1.b"];
+	let parts = ["", "res", "open days", "a/b", "50%", "?#&=+", ".", "..", "✓", "~-_."];
+	
+	let mut v = Vec::new();
+	for route in &routes {
+		for p in &parts {
+			for q in &parts {
+				v.push(UriBuilder::new()
+					.with_route(route)
+					.with_resource(p)
+					.with_resource(q)
+					.with_query(p, q)
+					.with_query(q, p)
+					.with_fragment(q)
+					.build()
+					.unwrap());
+			}
+		}
+	}
+	v
+}
+
+#[test]
+fn ts_uri_roundtrip_pass() {
+	for uri in sample_uris() {
+		let s = uri.to_string();
+		let parsed = Uri::parse(&s).unwrap();
+		
+		assert_eq!(parsed, uri, "{}", s);
+		assert_eq!(parsed.to_string(), s);
+		assert_eq!(parsed.query_map(), uri.query_map());
+		
+		let mut bare = uri.clone();
+		bare.route_mut().clear();
+		let s = bare.to_string();
+		
+		assert_eq!(Uri::parse(&s).unwrap(), bare, "{}", s);
+	}
+	
+	let mut bare = Uri::parse("spring://cci").unwrap();
+	bare.route_mut().clear();
+	assert_eq!(Uri::parse(&bare.to_string()).unwrap(), bare);
+}
+
+#[test]
+fn ts_uri_normalise_roundtrip_pass() {
+	for uri in sample_uris() {
+		let n = uri.normalise();
+		let s = n.to_string();
+		
+		assert_eq!(n.normalise(), n, "{}", s);
+		assert_eq!(Uri::parse(&s).unwrap(), n, "{}", s);
+		assert_eq!(normalise(&uri.to_string()).unwrap(), s);
+		assert_eq!(n.query_map(), uri.query_map());
+	}
+}
+
+#[test]
+fn ts_uri_gtn_follows_route_pass() {
+	let mut uri = Uri::parse("spring://cci.esusx.uk").unwrap();
+	assert_eq!(uri.gtn(), "uk");
+	
+	uri.route_mut().pop();
+	assert_eq!(uri.gtn(), "");
+}
+
+#[test]
+fn ts_uri_route_lowercase_pass() {
+	let uri = Uri::parse("spring://Cci.ESUSX.Uk").unwrap();
+	assert_eq!(uri.route(), &vec!["cci", "esusx", "uk"]);
+	assert_eq!(uri.gtn(), "uk");
+	
+	let uri = UriBuilder::new().with_route("CCI.esusx").build().unwrap();
+	assert_eq!(uri.to_string(), "spring://cci.esusx");
+}
@@ -0,0 +1,210 @@
+extern crate spring_dvs;
+
+use spring_dvs::enums::Failure;
+use spring_dvs::formats::{NodeQuadFmt, NodeInfoFmt};
+use spring_dvs::protocol::*;
+use spring_dvs::serialise::NetSerial;
+use spring_dvs::wire::is_binary;
+
+fn requests() -> Vec<Message> {
+	let text : Vec<&[u8]> = vec![
+		b"register foo,foo.tld;org;http;abcdef\nPUBLIC KEY\nAbCdEf",
+		b"register foo,https://foo.tld:8443/path;hybrid;dvsp;\nKEY",
+		b"unregister foo",
+		b"info network",
+		b"info node foo",
+		b"info node foo state",
+		b"info node foo hostname",
+		b"info node foo address",
+		b"update foo state enabled",
+		b"update foo service dvsp",
+		b"update foo role hub",
+		b"resolve spring://cci.esusx.uk/res?query=test",
+		b"service spring://cci.esusx.uk/res/home/",
+		b"200",
+		b"101",
+		b"202",
+	];
+	
+	text.into_iter().map(|t| Message::from_bytes(t).unwrap()).collect()
+}
+
+fn network() -> Vec<NodeQuadFmt> {
+	vec![
+		NodeQuadFmt::from_str("foo,foo.tld,192.168.1.2,http").unwrap(),
+		NodeQuadFmt::from_str("bar,bar.tld,::1,dvsp").unwrap(),
+	]
+}
+
+fn responses() -> Vec<Message> {
+	let mut info = NodeInfoFmt::new();
+	info.spring = String::from("foo");
+	info.host = String::from("foo.tld");
+	info.state = NodeState::Unresponsive;
+	
+	vec![
+		generate_response_empty_code(Response::NetspaceDuplication),
+		generate_response_network(network()),
+		generate_response_network(Vec::new()),
+		generate_response_node_info(info),
+		generate_response_node_info(NodeInfoFmt::new()),
+		generate_response_service_text("foobar — ☃"),
+		generate_response_service_text(""),
+		generate_response_service_multi(vec![ContentServiceText::new("one"), ContentServiceText::new("202 two")]),
+		generate_response_resolution(ContentResolution::new(ResolutionKind::Node, network())),
+		generate_response_resolution(ContentResolution::new(ResolutionKind::Referral, Vec::new())),
+		Message::new(CmdType::Response, MessageContent::Response(ContentResponse {
+			code: Response::Ok,
+			len: 3,
+			content: ResponseContent::NodeSingle(ContentNodeSingle::from_bytes(b"foo").unwrap()),
+		})),
+	]
+}
+
+#[test]
+fn ts_wire_message_roundtrip_pass() {
+	for msg in requests().into_iter().chain(responses()) {
+		let bytes = msg.serialise();
+		assert!(is_binary(&bytes));
+		
+		let decoded = Message::deserialise(&bytes).unwrap();
+		assert_eq!(decoded.cmd, msg.cmd);
+		assert_eq!(decoded.content, msg.content);
+		assert_eq!(decoded.to_bytes(), msg.to_bytes());
+	}
+}
+
+#[test]
+fn ts_wire_text_roundtrip_pass() {
+	// Text parsed, encoded, decoded and written as text again
+	let text : Vec<&[u8]> = vec![
+		b"register foo,foo.tld;org;http;abcdef\nKEY",
+		b"info node foo all",
+		b"update foo state enabled",
+		b"resolve spring://cci.esusx.uk/res?query=test",
+		b"200",
+		b"200 48 network foo,foo.tld,192.168.1.2,http;bar,bar.tld,::1,dvsp;",
+		b"200 38 node spring:foo,host:foo.tld,state:enabled",
+		b"200 19 service/text foobar",
+		b"200 58 service/multi 201 16 service/text one 201 16 service/text two 202",
+		b"200 36 resolve node foo,foo.tld,192.168.1.2,http;",
+	];
+	
+	for t in text {
+		let parsed = Message::from_bytes(t).unwrap();
+		let decoded = Message::deserialise(&parsed.serialise()).unwrap();
+		assert_eq!(decoded.to_bytes(), parsed.to_bytes());
+		assert_eq!(decoded.content, parsed.content);
+	}
+}
+
+#[test]
+fn ts_wire_message_smaller_pass() {
+	let msgs : Vec<Message> = requests().into_iter().chain(responses()).collect();
+	let binary : usize = msgs.iter().map(|m| m.serialise().len()).sum();
+	let text : usize = msgs.iter().map(|m| m.to_bytes().len()).sum();
+	assert!(binary < text);
+	
+	let msg = generate_response_network(network());
+	assert!(msg.serialise().len() < msg.to_bytes().len());
+}
+
+#[test]
+fn ts_wire_content_roundtrip_pass() {
+	let reg = ContentRegistration::from_bytes(b"foo,foo.tld;org;http;abcdef\nKEY").unwrap();
+	assert_eq!(ContentRegistration::deserialise(&reg.serialise()), Ok(reg));
+	
+	let uri = ContentUri::from_bytes(b"spring://cci.esusx.uk/res").unwrap();
+	assert_eq!(ContentUri::deserialise(&uri.serialise()), Ok(uri));
+	
+	let multi = ContentServiceMulti { parts: vec![ContentServiceText::new("a"), ContentServiceText::new("b")] };
+	assert_eq!(ContentServiceMulti::deserialise(&multi.serialise()), Ok(multi));
+	
+	let resolution = ContentResolution::new(ResolutionKind::Referral, network());
+	assert_eq!(ContentResolution::deserialise(&resolution.serialise()), Ok(resolution));
+}
+
+#[test]
+fn ts_wire_long_content_pass() {
+	// Lengths over 127 take more than one byte
+	let text = "x".repeat(20000);
+	let msg = generate_response_service_text(&text);
+	let bytes = msg.serialise();
+	
+	let decoded = Message::deserialise(&bytes).unwrap();
+	assert_eq!(decoded.to_bytes(), msg.to_bytes());
+}
+
+/// Replace the first occurrence of `from` with `to`, which is
+/// the same length
+fn replace(bytes: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+	let i = bytes.windows(from.len()).position(|w| w == from).unwrap();
+	let mut v = bytes.to_vec();
+	v[i..i+to.len()].copy_from_slice(to);
+	v
+}
+
+#[test]
+fn ts_wire_truncated_fail() {
+	for msg in requests().into_iter().chain(responses()) {
+		let bytes = msg.serialise();
+		for len in 0..bytes.len() {
+			assert!(Message::deserialise(&bytes[..len]).is_err(), "{:?}", &bytes[..len]);
+		}
+	}
+}
+
+#[test]
+fn ts_wire_deserialise_fail() {
+	let mut bytes = Message::from_bytes(b"unregister foo").unwrap().serialise();
+	
+	// Trailing bytes
+	bytes.push(0);
+	assert_eq!(Message::deserialise(&bytes).err(), Some(Failure::InvalidBytes));
+	
+	// Unknown command
+	assert_eq!(Message::deserialise(&[0, 0]).err(), Some(Failure::InvalidConversion));
+	assert_eq!(Message::deserialise(b"unregister foo").err(), Some(Failure::InvalidConversion));
+	
+	// Invalid springname
+	assert_eq!(Message::deserialise(&[2, 3, b'f', b'_', b'o']).err(), Some(Failure::InvalidFormat));
+	
+	// Invalid springnames and hostnames in any content
+	let update = Message::from_bytes(b"update foo state enabled").unwrap().serialise();
+	assert_eq!(Message::deserialise(&replace(&update, b"foo", b"f_o")).err(), Some(Failure::InvalidFormat));
+	
+	let info = Message::from_bytes(b"info node foo state").unwrap().serialise();
+	assert_eq!(Message::deserialise(&replace(&info, b"foo", b"-fo")).err(), Some(Failure::InvalidFormat));
+	
+	let mut node = NodeInfoFmt::new();
+	node.spring = String::from("foo");
+	node.host = String::from("bar.tld");
+	let response = generate_response_node_info(node).serialise();
+	assert!(Message::deserialise(&response).is_ok());
+	assert_eq!(Message::deserialise(&replace(&response, b"foo", b"f o")).err(), Some(Failure::InvalidFormat));
+	assert_eq!(Message::deserialise(&replace(&response, b"bar.tld", b"ba_.tld")).err(), Some(Failure::InvalidFormat));
+	
+	// Invalid UTF-8
+	assert_eq!(Message::deserialise(&[2, 2, 0xc3, 0x28]).err(), Some(Failure::InvalidBytes));
+	
+	// Length past the end
+	assert_eq!(Message::deserialise(&[2, 0xff, 0xff, 0xff, 0xff, 0x0f, b'f']).err(), Some(Failure::OutOfBounds));
+	
+	// Unknown response code and content
+	assert_eq!(Message::deserialise(&[7, 199, 0, 0]).err(), Some(Failure::InvalidConversion));
+	assert_eq!(Message::deserialise(&[7, 200, 0, 9]).err(), Some(Failure::InvalidConversion));
+	
+	// Unknown state
+	let mut bytes = Message::from_bytes(b"update foo state enabled").unwrap().serialise();
+	let last = bytes.len() - 1;
+	bytes[last] = 9;
+	assert_eq!(Message::deserialise(&bytes).err(), Some(Failure::InvalidConversion));
+}
+
+#[test]
+fn ts_wire_is_binary_pass() {
+	assert!(!is_binary(b"info network"));
+	assert!(!is_binary(b"200"));
+	assert!(!is_binary(b""));
+	assert!(is_binary(&Message::from_bytes(b"info network").unwrap().serialise()));
+}